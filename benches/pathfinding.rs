@@ -0,0 +1,108 @@
+use bevy::prelude::Entity;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use not_snake::{level::Level, path_find::PathFinder, simulation, tile::Tile, Position};
+
+// Snake AI cost for every shipped level, measured on Level and PathFinder
+// directly so nothing gets rendered. The web build has the least frame time
+// to spare, so anything that gets slower here shows up there first.
+const LEVELS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data/levels.custom");
+
+struct Spots {
+    snakes: Vec<Position>,
+    targets: Vec<Position>,
+}
+
+fn load_levels() -> Vec<(usize, Level, Spots)> {
+    let levels = simulation::load_levels(LEVELS).expect("couldn't load levels");
+
+    levels
+        .levels
+        .into_iter()
+        .enumerate()
+        .map(|(i, info)| {
+            let level = Level::from_level_info(info);
+            let spots = find_spots(&level);
+            (i, level, spots)
+        })
+        .collect()
+}
+
+fn find_spots(level: &Level) -> Spots {
+    let mut spots = Spots {
+        snakes: vec![],
+        targets: vec![],
+    };
+
+    for position in level.positions() {
+        match level.get_tile(position) {
+            Some(Tile::Snake) | Some(Tile::ElectricSnake) => spots.snakes.push(position),
+            Some(tile) if tile == Tile::WinFlag || tile.is_food() => spots.targets.push(position),
+            _ => (),
+        }
+    }
+
+    spots
+}
+
+fn loaded_path_finder(level: &Level) -> PathFinder {
+    let mut path_finder = PathFinder::new();
+    path_finder.load_level(level);
+    path_finder.update_all(level);
+    path_finder
+}
+
+fn build_graph(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_graph");
+    for (i, level, _) in load_levels().iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(i), level, |b, level| {
+            b.iter(|| {
+                let mut path_finder = PathFinder::new();
+                path_finder.load_level(level);
+                path_finder
+            })
+        });
+    }
+    group.finish();
+}
+
+fn update_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_all");
+    for (i, level, _) in load_levels().iter() {
+        let mut path_finder = loaded_path_finder(level);
+        group.bench_with_input(BenchmarkId::from_parameter(i), level, |b, level| {
+            b.iter(|| path_finder.update_all(level))
+        });
+    }
+    group.finish();
+}
+
+fn update_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_path");
+    for (i, level, spots) in load_levels().iter() {
+        if spots.snakes.is_empty() || spots.targets.is_empty() {
+            continue;
+        }
+
+        let mut path_finder = loaded_path_finder(level);
+        let snake = Entity::from_raw(0);
+        group.bench_with_input(BenchmarkId::from_parameter(i), level, |b, _| {
+            b.iter(|| {
+                for start in spots.snakes.iter() {
+                    // snakes spawn with their body one spot up, if there's room
+                    let body = Position { x: start.x + 1, ..*start };
+                    let claimed_nodes: Vec<_> = path_finder.node(&body).into_iter().collect();
+                    for goal in spots.targets.iter() {
+                        // every query should search instead of reusing the last path
+                        path_finder.retain_paths(|entity| entity != snake);
+                        path_finder.update_path(&claimed_nodes, snake, start, goal);
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build_graph, update_all, update_path);
+criterion_main!(benches);
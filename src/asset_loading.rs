@@ -11,7 +11,8 @@ impl Plugin for AssetLoadingPlugin {
             .add_system_set(SystemSet::on_enter(AppState::Loading).with_system(setup))
             .add_system_set(SystemSet::on_exit(AppState::Loading).with_system(cleanup::<CleanupMarker>))
             .add_system_set(
-                SystemSet::on_update(AppState::Loading).with_system(check_assets_ready),
+                SystemSet::on_update(AppState::Loading)
+                    .with_system(check_assets_ready.label("check_assets_ready")),
             );
     }
 }
@@ -26,7 +27,7 @@ pub struct GameTexture {
 struct CleanupMarker;
 
 pub struct NextState {
-    state: AppState,
+    pub state: AppState,
 }
 impl Default for NextState {
     fn default() -> Self {
@@ -1,7 +1,7 @@
 use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_kira_audio::{AudioApp, AudioChannel, AudioPlugin, AudioSource};
 use std::marker::PhantomData;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use bevy::reflect::TypeUuid;
 use crate::{level, assets::GameAssets};
 
@@ -27,9 +27,11 @@ pub struct CurrentMusic {
 
 #[derive(SystemParam)]
 pub struct GameAudio<'w, 's> {
-    music_channel: Res<'w, AudioChannel<MusicChannel>>,
-    sound_channel: Res<'w, AudioChannel<SoundChannel>>,
-    electricity_channel: Res<'w, AudioChannel<ElectricityChannel>>,
+    // channels are missing when running without GameAudioPlugin (e.g. the
+    // headless simulation), in which case playing anything is a no-op
+    music_channel: Option<Res<'w, AudioChannel<MusicChannel>>>,
+    sound_channel: Option<Res<'w, AudioChannel<SoundChannel>>>,
+    electricity_channel: Option<Res<'w, AudioChannel<ElectricityChannel>>>,
 
     #[system_param(ignore)]
     phantom: PhantomData<&'s ()>,
@@ -37,18 +39,24 @@ pub struct GameAudio<'w, 's> {
 
 impl<'w, 's> GameAudio<'w, 's> {
     pub fn play_bgm(&mut self, handle: &Handle<AudioSource>) {
-        self.music_channel.stop();
-        self.music_channel.set_volume(0.5);
-        self.music_channel.play_looped(handle.clone());
+        if let Some(music_channel) = &self.music_channel {
+            music_channel.stop();
+            music_channel.set_volume(0.5);
+            music_channel.play_looped(handle.clone());
+        }
     }
 
     pub fn stop_bgm(&mut self) {
-        self.music_channel.stop();
+        if let Some(music_channel) = &self.music_channel {
+            music_channel.stop();
+        }
     }
 
     pub fn play_sfx(&mut self, handle: &Handle<AudioSource>) {
-        self.sound_channel.set_volume(0.5);
-        self.sound_channel.play(handle.clone());
+        if let Some(sound_channel) = &self.sound_channel {
+            sound_channel.set_volume(0.5);
+            sound_channel.play(handle.clone());
+        }
     }
 
     pub fn play_electricity(&mut self, handle: &Handle<AudioSource>) {
@@ -57,12 +65,12 @@ impl<'w, 's> GameAudio<'w, 's> {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
 #[uuid = "67fadc56-aa9c-4543-8640-a018b74b5052"]
 pub struct LevelMusic {
-    before: Vec<MusicPiece>,
-    during: Vec<MusicPiece>,
-    after: Vec<MusicPiece>,
+    pub before: Vec<MusicPiece>,
+    pub during: Vec<MusicPiece>,
+    pub after: Vec<MusicPiece>,
 }
 
 impl LevelMusic {
@@ -75,7 +83,7 @@ impl LevelMusic {
     }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize, TypeUuid, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, TypeUuid, PartialEq)]
 #[uuid = "23badc56-aa9c-4543-8640-a018b74b5052"]
 pub enum MusicPiece {
     BassDrum,
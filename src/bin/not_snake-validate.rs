@@ -0,0 +1,39 @@
+use not_snake::{level_validation, simulation};
+
+// Checks a .custom level file without starting the game.
+//
+//    cargo run --bin not_snake-validate -- assets/data/levels.custom
+fn main() {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: not_snake-validate <levels.custom>..");
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for path in paths.iter() {
+        let levels = match simulation::load_levels(path) {
+            Ok(levels) => levels,
+            Err(e) => {
+                eprintln!("{}: couldn't read levels: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let errors = level_validation::validate_levels(&levels);
+        for error in errors.iter() {
+            eprintln!("{}: {}", path, error);
+        }
+
+        if errors.is_empty() {
+            println!("{}: {} levels ok", path, levels.levels.len());
+        } else {
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
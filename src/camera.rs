@@ -2,12 +2,12 @@ use crate::{dude, environment, level::Level, food, holdable};
 use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 use bevy::reflect::TypeUuid;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct CameraTarget;
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
 #[uuid = "59cadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
 pub enum CameraBehavior {
     Static,
@@ -0,0 +1,411 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::{LevelMusic, MusicPiece},
+    camera::CameraBehavior,
+    dude::PlayerAction,
+    environment,
+    food::FoodEatenEvent,
+    game_time,
+    grid::GridCoord,
+    level::{Level, LevelInfo, LevelText, LevelsAsset},
+    level_generator,
+    path_find::PathFinder,
+    snake::{self, Enemy, SnakeSpawn},
+    snake_control::{PlayerSnake, SnakeAction},
+    tile::Tile,
+    user_data, AppState, Direction, Position,
+};
+
+// Classic snake: one layer of play seen from straight above. The player's
+// snake comes from snake mode, but here it never stops moving, the arrows
+// point it in a direction on the screen instead of turning it, and running
+// into a wall or itself is the end of the game. Growing is the usual food
+// and AddBodyPartEvent, best scores are kept in the user data directory.
+pub struct ClassicPlugin;
+impl Plugin for ClassicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Classic>()
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(start_game))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(show_score.after(environment::update_hud_text_position)),
+            )
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(end_game)
+                    .with_system(steer.before(snake::update_enemy))
+                    .with_system(count_food.before(steer)),
+            );
+    }
+}
+
+pub const WIDTH: usize = 17;
+pub const LENGTH: usize = 17;
+pub const SNAKE_SPEED: f32 = 0.2;
+pub const MAX_HIGH_SCORES: usize = 5;
+// how long the crash stays up before the score shows
+const GAME_OVER_SECONDS: f32 = 1.5;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    // best first
+    pub scores: Vec<usize>,
+}
+
+impl HighScores {
+    pub fn path() -> Option<PathBuf> {
+        user_data::directory().map(|directory| directory.join("classic_scores.ron"))
+    }
+
+    // a missing or unreadable file is the same as no scores yet
+    pub fn load() -> Self {
+        HighScores::path()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| ron::de::from_bytes::<HighScores>(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let path = HighScores::path().ok_or_else(|| anyhow::anyhow!("there's no user data directory"))?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn best(&self) -> usize {
+        self.scores.first().copied().unwrap_or(0)
+    }
+
+    // returns whether the score made the list
+    pub fn add(&mut self, score: usize) -> bool {
+        let index = self.scores.iter().position(|s| score > *s).unwrap_or(self.scores.len());
+        if index >= MAX_HIGH_SCORES {
+            return false;
+        }
+
+        self.scores.insert(index, score);
+        self.scores.truncate(MAX_HIGH_SCORES);
+        true
+    }
+}
+
+pub struct Classic {
+    pub active: bool,
+    pub score: usize,
+    pub high_scores: HighScores,
+    pub made_high_scores: bool,
+    game_over: bool,
+    game_over_timer: Timer,
+}
+
+impl Default for Classic {
+    fn default() -> Self {
+        Classic {
+            active: false,
+            score: 0,
+            high_scores: HighScores::default(),
+            made_high_scores: false,
+            game_over: false,
+            game_over_timer: Timer::from_seconds(GAME_OVER_SECONDS, false),
+        }
+    }
+}
+
+impl Classic {
+    // like endless, level 0 is where the game sits before the one being played
+    pub fn start(&mut self) -> LevelsAsset {
+        *self = Classic {
+            active: true,
+            high_scores: HighScores::load(),
+            ..Classic::default()
+        };
+
+        LevelsAsset {
+            start_level: 0,
+            palette: level_generator::palettes().remove(0),
+            levels: vec![arena(), arena()],
+            level_files: vec![],
+            level_handles: vec![],
+            last_level: None,
+            lost_score_level: None,
+            versus_levels: vec![],
+        }
+    }
+
+    fn finish(&mut self) {
+        self.game_over = true;
+        self.made_high_scores = self.high_scores.add(self.score);
+        if self.made_high_scores {
+            if let Err(e) = self.high_scores.save() {
+                println!("Couldn't save classic high scores: {}", e);
+            }
+        }
+    }
+
+    pub fn score_text(&self) -> String {
+        let scores = self
+            .high_scores
+            .scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| format!("{}. {}", i + 1, score))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if self.made_high_scores {
+            format!("new high score!\nyour score is {}\n\n{}", self.score, scores)
+        } else {
+            format!("your score is {}\n\n{}", self.score, scores)
+        }
+    }
+
+    // classic is a single game so the score screen goes back to the title
+    pub fn next_state(&mut self, level: &mut Level) -> AppState {
+        self.active = false;
+        level.current_level = 0;
+        AppState::MainMenu
+    }
+}
+
+// A floor with a wall around the edge and the play field on the layer above
+// it. The wall can't be stood on so food never shows up on top of it. The
+// snake starts in the middle heading right.
+pub fn arena() -> LevelInfo {
+    // [x][y][z] like Level, flipped into the file layout at the end
+    let mut tiles = vec![vec![vec![Tile::Empty; LENGTH]; 2]; WIDTH];
+    for x in 0..WIDTH {
+        for z in 0..LENGTH {
+            tiles[x][0][z] = Tile::Block;
+            if x == 0 || z == 0 || x == WIDTH - 1 || z == LENGTH - 1 {
+                tiles[x][1][z] = Tile::UnstandableBlock;
+            }
+        }
+    }
+    let start = Position { x: WIDTH as i32 / 2, y: 1, z: LENGTH as i32 / 2 };
+    tiles[start.x as usize][1][start.z as usize] = Tile::Snake;
+
+    let mut level = vec![vec![vec![0; LENGTH]; WIDTH]; 2];
+    for x in 0..WIDTH {
+        for y in 0..2 {
+            for z in 0..LENGTH {
+                let position = IVec3::new(x as i32, y as i32, z as i32);
+                let (file_y, file_x, file_z) = position.to_file_index(WIDTH, 2, LENGTH).unwrap();
+                level[file_y][file_x][file_z] = tiles[x][y][z].code();
+            }
+        }
+    }
+
+    // straight down with +x at the top of the screen, which is the way Up
+    // points in every other mode
+    let rotation = Quat::from_rotation_arc(-Vec3::Z, -Vec3::Y);
+    let rotation = Quat::from_rotation_arc(rotation * Vec3::Y, Vec3::X) * rotation;
+    let (axis, angle) = rotation.to_axis_angle();
+
+    LevelInfo {
+        title: "classic".to_string(),
+        level,
+        legend: Default::default(),
+        layers: vec![],
+        score_text: vec![],
+        level_text: vec![LevelText::JustText("classic".to_string())],
+        is_food_random: true,
+        minimum_food: 0,
+        palette: None,
+        snake_speed: Some(SNAKE_SPEED),
+        snake_target: None,
+        snake_min_length: None,
+        snakes: vec![SnakeSpawn {
+            position: start,
+            facing: Some(Direction::Right),
+            length: Some(2),
+            speed: None,
+            is_electric: None,
+            brain: None,
+            color: None,
+        }],
+        snake_rules: Default::default(),
+        camera_x: (WIDTH as f32 - 1.0) / 2.0,
+        camera_y: 22.0,
+        camera_z: (LENGTH as f32 - 1.0) / 2.0,
+        camera_rotation_x: axis.x,
+        camera_rotation_y: axis.y,
+        camera_rotation_z: axis.z,
+        camera_rotation_angle: angle,
+        camera_behaviors: vec![CameraBehavior::Static],
+        camera_cull_x: None,
+        camera_cull_y: None,
+        camera_cull_z: None,
+        teleporter_links: vec![],
+        music: LevelMusic {
+            before: vec![],
+            during: vec![MusicPiece::Classic],
+            after: vec![],
+        },
+    }
+}
+
+// Which way a press sends the snake, in the same directions the dude uses.
+// Backing into itself isn't a turn so it's ignored.
+pub fn heading(enemy: &Enemy, action: Option<SnakeAction>) -> Vec3 {
+    let heading = match action {
+        Some(SnakeAction::Up) => Vec3::X,
+        Some(SnakeAction::Down) => -Vec3::X,
+        Some(SnakeAction::Left) => -Vec3::Z,
+        Some(SnakeAction::Right) => Vec3::Z,
+        None => enemy.forward,
+    };
+
+    if heading == -enemy.forward {
+        enemy.forward
+    } else {
+        heading
+    }
+}
+
+// snake_control::next_target takes turns relative to the snake
+fn relative_turn(enemy: &Enemy, heading: Vec3) -> Option<SnakeAction> {
+    if heading == enemy.up.cross(enemy.forward) {
+        Some(SnakeAction::Left)
+    } else if heading == enemy.forward.cross(enemy.up) {
+        Some(SnakeAction::Right)
+    } else {
+        None
+    }
+}
+
+fn start_game(mut commands: Commands, mut classic: ResMut<Classic>) {
+    if !classic.active {
+        return;
+    }
+
+    classic.score = 0;
+    classic.game_over = false;
+    classic.game_over_timer.reset();
+
+    // there's no dude, this is what pausing listens to instead
+    let mut input_map = InputMap::default();
+    input_map.insert(PlayerAction::Pause, KeyCode::Escape);
+    commands
+        .spawn()
+        .insert_bundle(InputManagerBundle {
+            input_map,
+            action_state: ActionState::default(),
+        })
+        .insert(environment::CleanupMarker);
+}
+
+// runs right before update_enemy picks the snake's next spot so it can
+// swap the pressed direction for a turn, or end the game if that spot is
+// a wall or the snake
+fn steer(
+    mut classic: ResMut<Classic>,
+    path_find: Res<PathFinder>,
+    mut snakes: Query<(Entity, &mut Enemy, &Position, &mut PlayerSnake)>,
+    mut kill_snake_event_writer: EventWriter<snake::KillSnakeEvent>,
+) {
+    if !classic.active || classic.game_over {
+        return;
+    }
+
+    for (entity, mut enemy, position, mut player_snake) in snakes.iter_mut() {
+        if enemy.is_dead || enemy.is_moving() {
+            continue;
+        }
+
+        let heading = heading(&enemy, player_snake.queued);
+        let target = Position::from_vec(position.to_vec() + heading);
+        // the tail moves out of the way as the head moves, unless the snake just grew
+        let body = enemy.body_positions.len();
+        let kept = if enemy.length() > body { body } else { body.saturating_sub(1) };
+        let hits_itself = enemy.body_positions[..kept]
+            .iter()
+            .any(|body_position| body_position.translation == target.to_vec());
+        if hits_itself || !path_find.can_move(position, &target) {
+            println!("Classic snake crashed with a score of {}", classic.score);
+            // stops update_enemy from going straight instead this tick
+            enemy.is_dead = true;
+            kill_snake_event_writer.send(snake::KillSnakeEvent(entity));
+            classic.finish();
+        } else {
+            player_snake.queued = relative_turn(&enemy, heading);
+        }
+    }
+}
+
+fn count_food(
+    mut classic: ResMut<Classic>,
+    mut food_eaten_event_reader: EventReader<FoodEatenEvent>,
+    snakes: Query<Entity, With<PlayerSnake>>,
+) {
+    for eater in food_eaten_event_reader.iter() {
+        if classic.active && !classic.game_over && snakes.get(eater.0).is_ok() {
+            classic.score += 1;
+        }
+    }
+}
+
+fn end_game(
+    mut classic: ResMut<Classic>,
+    mut state: ResMut<State<AppState>>,
+    time: Res<game_time::GameTime>,
+) {
+    if classic.active && classic.game_over && classic.game_over_timer.tick(time.delta()).just_finished() {
+        state.set(AppState::ScoreDisplay).unwrap();
+    }
+}
+
+fn show_score(
+    classic: Res<Classic>,
+    mut text_query: Query<&mut Text, With<environment::FollowText>>,
+) {
+    if !classic.active {
+        return;
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "Score: {}  Best: {}",
+            classic.score,
+            classic.high_scores.best().max(classic.score)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_stay_in_order() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.best(), 0);
+
+        for score in [4, 9, 1, 9] {
+            assert!(high_scores.add(score));
+        }
+        assert_eq!(high_scores.scores, vec![9, 9, 4, 1]);
+        assert_eq!(high_scores.best(), 9);
+    }
+
+    #[test]
+    fn only_the_top_scores_are_kept() {
+        let mut high_scores = HighScores {
+            scores: vec![50, 40, 30, 20, 10],
+        };
+
+        assert!(!high_scores.add(10));
+        assert!(!high_scores.add(5));
+        assert!(high_scores.add(35));
+        assert_eq!(high_scores.scores, vec![50, 40, 35, 30, 20]);
+        assert_eq!(high_scores.scores.len(), MAX_HIGH_SCORES);
+    }
+}
@@ -1,32 +1,40 @@
 use crate::{
     block, dust, environment, facing::Facing, game_controller, holdable, level::Level, moveable,
     direction, snake, audio, Direction, EntityType, GameObject, Position, assets::GameAssets,
+    game_time, game_time::GameTime, versus,
 };
 use bevy::prelude::*;
 use bevy_utils::Instant;
 use std::collections::HashMap;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct DudePlugin;
 impl Plugin for DudePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(crate::AppState::InGame)
-                .with_system(player_input.after("handle_input"))
-                .with_system(
-                    handle_controllers
-                        .label("handle_input")
-                        .after("store_controller_inputs"),
-                )
-                .with_system(pause_game.after(handle_controllers))
-                .with_system(hop_on_snake)
-                .with_system(push_block),
-        )
-        .add_plugin(InputManagerPlugin::<PlayerAction>::default());
+        app.init_resource::<BufferedPresses>()
+            .add_system_set(
+                SystemSet::on_update(crate::AppState::InGame)
+                    .with_system(
+                        handle_controllers
+                            .label("handle_input")
+                            .after("store_controller_inputs"),
+                    )
+                    .with_system(buffer_presses.after("handle_input"))
+                    .with_system(pause_game.after(handle_controllers))
+                    .with_system(hop_on_snake),
+            )
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(crate::AppState::InGame)
+                    .with_system(player_input)
+                    .with_system(push_block),
+            )
+            .add_plugin(InputManagerPlugin::<PlayerAction>::default());
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum PlayerAction {
     Up,
     Down,
@@ -44,13 +52,20 @@ pub enum PlayerAction {
 }
 
 impl PlayerAction {
-    const DIRECTIONS: [Self; 4] = [
+    pub const DIRECTIONS: [Self; 4] = [
         PlayerAction::Up,
         PlayerAction::Down,
         PlayerAction::Left,
         PlayerAction::Right,
     ];
 
+    // the actions player_input acts on the moment they're pressed
+    pub const PRESSES: [Self; 3] = [
+        PlayerAction::ActionDown,
+        PlayerAction::Debug1,
+        PlayerAction::Debug2,
+    ];
+
     fn direction(self) -> direction::Direction {
         match self {
             PlayerAction::Up => direction::Direction::UP,
@@ -92,7 +107,7 @@ pub struct KillDudeEvent {
     pub death_type: DudeDeath,
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum DudeDeath {
     Fall,
     Eaten,
@@ -119,9 +134,7 @@ pub fn spawn_player<T: Component>(
 ) {
     let player_entity = create_not_snake(commands, meshes, x as isize, y as isize, z as isize, cleanup_marker);
     level.set(
-        x as i32,
-        y as i32,
-        z as i32,
+        IVec3::new(x as i32, y as i32, z as i32),
         Some(GameObject::new(player_entity, EntityType::Dude)),
     );
 }
@@ -176,7 +189,7 @@ pub fn create_not_snake<T: Component>(
 pub fn handle_squashes(
     mut squashes: Query<(&mut SquashQueue, &Children)>,
     mut transforms: Query<&mut Transform>,
-    time: Res<Time>,
+    time: Res<GameTime>,
 ) {
     for (mut queue, children) in squashes.iter_mut() {
         if let Some(mut squash) = queue.squashes.pop() {
@@ -276,10 +289,15 @@ fn pause_game(
 
 fn handle_controllers(
     controllers: Res<game_controller::GameController>,
+    versus: Res<versus::Versus>,
     mut players: Query<(Entity, &mut ActionState<PlayerAction>), With<Dude>>,
 ) {
     for (_, mut action_state) in players.iter_mut() {
-        for (_, pressed) in controllers.pressed.iter() {
+        for (_, pressed) in controllers
+            .pressed
+            .iter()
+            .filter(|(gamepad, _)| versus::is_dude_gamepad(&versus, **gamepad))
+        {
             if pressed.contains(&game_controller::GameButton::Left) {
                 action_state.release(PlayerAction::Left);
                 action_state.press(PlayerAction::Left);
@@ -298,7 +316,11 @@ fn handle_controllers(
             }
         }
 
-        for (_, just_pressed) in controllers.just_pressed.iter() {
+        for (_, just_pressed) in controllers
+            .just_pressed
+            .iter()
+            .filter(|(gamepad, _)| versus::is_dude_gamepad(&versus, **gamepad))
+        {
             if just_pressed.contains(&game_controller::GameButton::ActionUp) {
                 action_state.release(PlayerAction::ActionUp);
                 action_state.press(PlayerAction::ActionUp);
@@ -323,6 +345,24 @@ fn handle_controllers(
     }
 }
 
+// just_pressed only lasts for the frame it happened in but a frame can run
+// zero or several fixed ticks, so presses are held here until a tick uses them
+#[derive(Default)]
+pub struct BufferedPresses(pub Vec<PlayerAction>);
+
+fn buffer_presses(
+    action_state: Query<&ActionState<PlayerAction>, With<Dude>>,
+    mut buffered_presses: ResMut<BufferedPresses>,
+) {
+    for action_state in action_state.iter() {
+        for action in PlayerAction::PRESSES {
+            if action_state.just_pressed(action) && !buffered_presses.0.contains(&action) {
+                buffered_presses.0.push(action);
+            }
+        }
+    }
+}
+
 fn default_input_map() -> InputMap<PlayerAction> {
     use PlayerAction::*;
     let mut input_map = InputMap::default();
@@ -370,9 +410,9 @@ fn default_input_map() -> InputMap<PlayerAction> {
 }
  
 
-fn player_input(
+pub fn player_input(
     action_state: Query<&ActionState<PlayerAction>>,
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut lift_holdable_event_writer: EventWriter<holdable::LiftHoldableEvent>,
     mut dudes: Query<
         (
@@ -394,7 +434,7 @@ fn player_input(
     mut right_buffer: Local<Option<u128>>,
     mut left_buffer: Local<Option<u128>>,
     mut create_dust_event_writer: EventWriter<dust::CreateDustEvent>,
-    mut state: ResMut<State<crate::AppState>>,
+    mut buffered_presses: ResMut<BufferedPresses>,
 ) {
     let time_buffer = 100;
     //  // this is for debugging. If we're flying, don't move the player
@@ -430,16 +470,17 @@ fn player_input(
     }
 
     let action_state = action_state.single();
+    let presses = std::mem::take(&mut buffered_presses.0);
 
-    if action_state.just_pressed(PlayerAction::Debug1) {
+    if presses.contains(&PlayerAction::Debug1) {
         kill_dude_event_writer.send(KillDudeEvent { death_type: DudeDeath::Eaten });
     }
-    if action_state.just_pressed(PlayerAction::Debug2) {
+    if presses.contains(&PlayerAction::Debug2) {
         level_over_event_writer.send(crate::level_over::LevelOverEvent {});
     }
 
     for (entity, mut moveable, transform, facing, mut squash_queue) in dudes.iter_mut() {
-        if action_state.just_pressed(PlayerAction::ActionDown)
+        if presses.contains(&PlayerAction::ActionDown)
            && !moveable.is_moving()
             && action_buffer.is_none()
         {
@@ -525,7 +566,7 @@ fn player_input(
     }
 }
 
-fn push_block(
+pub fn push_block(
     keyboard_input: Res<Input<KeyCode>>,
     level: Res<Level>,
     dudes: Query<(&Transform, &Position, &Facing)>,
@@ -596,7 +637,7 @@ pub fn handle_snake_escapes(
     mut dudes: Query<(Entity, &Position), (With<Dude>, With<environment::Shrink>)>,
     mut timers: Local<HashMap<Entity, (Position, f32)>>,
     mut dude_died_event_writer: EventWriter<DudeDiedEvent>,
-    time: Res<Time>,
+    time: Res<GameTime>,
 ) {
     for (entity, position) in dudes.iter_mut() {
         if let Some((pos, timer)) = &mut timers.get_mut(&entity) {
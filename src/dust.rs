@@ -1,6 +1,6 @@
-use crate::{level::Level, Direction, EntityType, Position, environment};
+use crate::{level::Level, Direction, EntityType, Position, environment, game_rng::GameRng, grid::GridCoord};
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 #[derive(Component)]
 pub struct Dust {
@@ -54,11 +54,12 @@ pub fn handle_create_dust_event(
     mut materials: ResMut<Assets<StandardMaterial>>,
     ground_materials: Query<&Handle<StandardMaterial>>,
     level: Res<Level>,
+    mut rng: ResMut<GameRng>,
 ) {
     for event in create_dust_event_reader.iter() {
         let position = event.position;
 
-        let ground = level.get(position.x, position.y - 1, position.z);
+        let ground = level.get(position.neighbour(Direction::Beneath));
         if let Some(ground) = ground {
             if ground.entity_type != EntityType::Block
                 && ground.entity_type != EntityType::Platform
@@ -83,8 +84,8 @@ pub fn handle_create_dust_event(
                 Transform::from_xyz(position.x as f32, position.y as f32, position.z as f32);
 
             for _ in 0..3 {
-                let inner_mesh_x = thread_rng().gen_range(-25 as i32..25) as f32 / 100.0;
-                let inner_mesh_z = thread_rng().gen_range(-25 as i32..25) as f32 / 100.0;
+                let inner_mesh_x = rng.gen_range(-25 as i32..25) as f32 / 100.0;
+                let inner_mesh_z = rng.gen_range(-25 as i32..25) as f32 / 100.0;
 
                 let color = Color::rgba(color.r(), color.g(), color.b(), 0.7 + inner_mesh_x.abs());
                 let move_toward = match event.move_away_from {
@@ -0,0 +1,528 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::{
+    assets::GameAssets, camera::MainCamera, cleanup, level, level::Level, level::Palette,
+    grid::{opposite, GridCoord}, level_pack, level_writer, menus, teleporter::Teleporter, tile::Tile, tile::TILES, ui::text_size, AppState,
+    Direction, Position,
+};
+
+// F5 while playing opens the current level in the editor and F5 in the editor
+// plays it again with the changes.
+//
+//   arrows, page up/down  move the cursor
+//   wasd, r/f             move the camera, hold shift to turn it instead
+//   [ ]                   pick the tile to place
+//   space / delete        place / erase at the cursor
+//   tab                   switch palettes
+//   t                     start a teleporter link, t again somewhere else to finish it
+//   p                     use the editor camera as the level's camera
+//   ctrl+s                save every level to <user data>/level_packs/editor.custom
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(setup_editor))
+            .add_system_set(
+                SystemSet::on_update(AppState::Editor)
+                    .with_system(move_editor_camera)
+                    .with_system(move_cursor.label("move_cursor"))
+                    .with_system(edit_level.label("edit_level").after("move_cursor"))
+                    .with_system(build_tiles.after("edit_level"))
+                    .with_system(update_editor_text.after("edit_level"))
+                    .with_system(toggle_play_test),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Editor).with_system(cleanup::<EditorCleanupMarker>),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(toggle_play_test),
+            );
+    }
+}
+
+#[derive(Component)]
+struct EditorCleanupMarker;
+#[derive(Component)]
+struct EditorCamera;
+#[derive(Component)]
+struct EditorCursor;
+#[derive(Component)]
+struct EditorTile;
+#[derive(Component)]
+struct EditorText;
+
+pub struct Editor {
+    pub cursor: Position,
+    pub tile: Tile,
+    // which way the cursor last moved, teleporters send things out this way
+    pub facing: Direction,
+    pub teleporter_start: Option<Position>,
+    pub camera: Option<Transform>,
+    pub message: String,
+    tiles_changed: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor {
+            cursor: Position { x: 0, y: 1, z: 0 },
+            tile: Tile::Block,
+            facing: Direction::Up,
+            teleporter_start: None,
+            camera: None,
+            message: "".to_string(),
+            tiles_changed: true,
+        }
+    }
+}
+
+fn setup_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    level: Res<Level>,
+    game_assets: Res<GameAssets>,
+    text_scaler: text_size::TextScaler,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
+    main_cameras: Query<Entity, With<MainCamera>>,
+) {
+    // the game camera sticks around between levels, it gets remade when play resumes
+    for entity in main_cameras.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.50,
+    });
+    clear_color.0 = Color::hex(level.get_palette().base).unwrap();
+
+    let camera_transform = editor.camera.unwrap_or_else(|| {
+        let mut transform = Transform::from_translation(level.get_camera_position());
+        transform.rotation = level.get_camera_rotation();
+        transform
+    });
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: camera_transform,
+            ..Default::default()
+        })
+        .insert(EditorCamera)
+        .insert(EditorCleanupMarker);
+
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(EditorCleanupMarker);
+
+    editor.cursor = clamp_to_level(&level, editor.cursor);
+    editor.teleporter_start = None;
+    editor.tiles_changed = true;
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.05 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 1.0, 1.0, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            }),
+            transform: Transform::from_translation(editor.cursor.to_vec()),
+            ..Default::default()
+        })
+        .insert(EditorCursor)
+        .insert(EditorCleanupMarker);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(15.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "".to_string(),
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: text_scaler.scale(menus::BUTTON_LABEL_FONT_SIZE * 0.6),
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(EditorText)
+        .insert(EditorCleanupMarker);
+}
+
+fn clamp_to_level(level: &Level, position: Position) -> Position {
+    let max_y = level.height() as i32 - level::HEIGHT_BUFFER as i32 - 1;
+    Position {
+        x: position.x.clamp(0, (level.width() as i32 - 1).max(0)),
+        y: position.y.clamp(0, max_y.max(0)),
+        z: position.z.clamp(0, (level.length() as i32 - 1).max(0)),
+    }
+}
+
+fn move_editor_camera(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut editor: ResMut<Editor>,
+    mut cameras: Query<&mut Transform, With<EditorCamera>>,
+) {
+    let speed = 8.0 * time.delta_seconds();
+    let turn_speed = 1.5 * time.delta_seconds();
+    let turning = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+    // ctrl is for shortcuts like ctrl+s, the camera stays put
+    if keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl) {
+        return;
+    }
+
+    for mut transform in cameras.iter_mut() {
+        let forward = transform.forward();
+        let forward = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
+        let right = transform.right();
+
+        if turning {
+            if keys.pressed(KeyCode::A) {
+                transform.rotate(Quat::from_rotation_y(turn_speed));
+            }
+            if keys.pressed(KeyCode::D) {
+                transform.rotate(Quat::from_rotation_y(-turn_speed));
+            }
+            if keys.pressed(KeyCode::W) {
+                let rotation = Quat::from_axis_angle(right, turn_speed);
+                transform.rotate(rotation);
+            }
+            if keys.pressed(KeyCode::S) {
+                let rotation = Quat::from_axis_angle(right, -turn_speed);
+                transform.rotate(rotation);
+            }
+        } else {
+            if keys.pressed(KeyCode::W) {
+                transform.translation += forward * speed;
+            }
+            if keys.pressed(KeyCode::S) {
+                transform.translation -= forward * speed;
+            }
+            if keys.pressed(KeyCode::D) {
+                transform.translation += right * speed;
+            }
+            if keys.pressed(KeyCode::A) {
+                transform.translation -= right * speed;
+            }
+        }
+        if keys.pressed(KeyCode::R) {
+            transform.translation.y += speed;
+        }
+        if keys.pressed(KeyCode::F) {
+            transform.translation.y -= speed;
+        }
+
+        editor.camera = Some(*transform);
+    }
+}
+
+fn move_cursor(
+    keys: Res<Input<KeyCode>>,
+    level: Res<Level>,
+    mut editor: ResMut<Editor>,
+    mut cursors: Query<&mut Transform, With<EditorCursor>>,
+) {
+    let mut cursor = editor.cursor;
+    let moves = [
+        (KeyCode::Up, Direction::Up),
+        (KeyCode::Down, Direction::Down),
+        (KeyCode::Left, Direction::Left),
+        (KeyCode::Right, Direction::Right),
+        (KeyCode::PageUp, Direction::Above),
+        (KeyCode::PageDown, Direction::Beneath),
+    ];
+
+    for (key, direction) in moves {
+        if keys.just_pressed(key) {
+            cursor = cursor.neighbour(direction);
+            if direction != Direction::Above && direction != Direction::Beneath {
+                editor.facing = direction;
+            }
+        }
+    }
+
+    editor.cursor = clamp_to_level(&level, cursor);
+    for mut transform in cursors.iter_mut() {
+        transform.translation = editor.cursor.to_vec();
+    }
+}
+
+fn edit_level(
+    keys: Res<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut level: ResMut<Level>,
+    mut clear_color: ResMut<ClearColor>,
+    cameras: Query<&Transform, With<EditorCamera>>,
+) {
+    let cursor = editor.cursor;
+
+    if keys.just_pressed(KeyCode::RBracket) {
+        editor.tile = TILES[(editor.tile.code() + 1) % TILES.len()].tile;
+    }
+    if keys.just_pressed(KeyCode::LBracket) {
+        editor.tile = TILES[(editor.tile.code() + TILES.len() - 1) % TILES.len()].tile;
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        level.set_level_info(cursor, editor.tile.code());
+        editor.tiles_changed = true;
+    }
+
+    if keys.just_pressed(KeyCode::Delete) || keys.just_pressed(KeyCode::Back) {
+        level.set_level_info(cursor, Tile::Empty.code());
+        level
+            .current_level_info_mut()
+            .teleporter_links
+            .retain(|t| t.position != cursor && t.target != cursor);
+        editor.tiles_changed = true;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        // the level's own palette, then every other palette in use, then back to the default
+        let mut palettes: Vec<Option<Palette>> = vec![None];
+        for info in level.level_infos().iter() {
+            if info.palette.is_some() && !palettes.contains(&info.palette) {
+                palettes.push(info.palette.clone());
+            }
+        }
+
+        let info = level.current_level_info_mut();
+        let current = palettes.iter().position(|p| *p == info.palette).unwrap_or(0);
+        info.palette = palettes[(current + 1) % palettes.len()].clone();
+        clear_color.0 = Color::hex(level.get_palette().base).unwrap();
+        editor.tiles_changed = true;
+    }
+
+    if keys.just_pressed(KeyCode::T) {
+        match editor.teleporter_start.take() {
+            None => {
+                editor.teleporter_start = Some(cursor);
+                editor.message = format!("teleporter from ({}, {}, {})", cursor.x, cursor.y, cursor.z);
+            }
+            Some(start) if start == cursor => editor.message = "teleporter cancelled".to_string(),
+            Some(start) => {
+                // going through comes out the way the cursor last moved and
+                // coming back comes out the opposite way
+                let facing = editor.facing;
+                let links = &mut level.current_level_info_mut().teleporter_links;
+                links.push(Teleporter {
+                    position: start,
+                    target: cursor,
+                    move_to: cursor.neighbour(facing),
+                    facing,
+                });
+                links.push(Teleporter {
+                    position: cursor,
+                    target: start,
+                    move_to: start.neighbour(opposite(facing)),
+                    facing: opposite(facing),
+                });
+                editor.message = "teleporters linked".to_string();
+                editor.tiles_changed = true;
+            }
+        }
+    }
+
+    if keys.just_pressed(KeyCode::P) {
+        if let Ok(transform) = cameras.get_single() {
+            let (axis, angle) = transform.rotation.to_axis_angle();
+            let info = level.current_level_info_mut();
+            info.camera_x = transform.translation.x;
+            info.camera_y = transform.translation.y;
+            info.camera_z = transform.translation.z;
+            info.camera_rotation_x = axis.x;
+            info.camera_rotation_y = axis.y;
+            info.camera_rotation_z = axis.z;
+            info.camera_rotation_angle = angle;
+
+            println!("camera_x: {:?},", info.camera_x);
+            println!("camera_y: {:?},", info.camera_y);
+            println!("camera_z: {:?},", info.camera_z);
+            println!("camera_rotation_x: {:?},", info.camera_rotation_x);
+            println!("camera_rotation_y: {:?},", info.camera_rotation_y);
+            println!("camera_rotation_z: {:?},", info.camera_rotation_z);
+            println!("camera_rotation_angle: {:?},", info.camera_rotation_angle);
+            editor.message = "camera captured".to_string();
+        }
+    }
+
+    let control = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if control && keys.just_pressed(KeyCode::S) {
+        let path = save_path();
+        let levels = level.to_levels_asset();
+        let saved = level_writer::write_levels(&levels).and_then(|text| {
+            if let Some(directory) = path.parent() {
+                std::fs::create_dir_all(directory)?;
+            }
+            Ok(std::fs::write(&path, text)?)
+        });
+        editor.message = match saved {
+            Ok(()) => format!("saved to {:?}", path),
+            Err(e) => format!("couldn't save to {:?}: {}", path, e),
+        };
+        println!("{}", editor.message);
+    }
+}
+
+fn save_path() -> PathBuf {
+    match level_pack::LevelPacks::directory() {
+        Some(directory) => directory.join("editor.custom"),
+        None => PathBuf::from("editor.custom"),
+    }
+}
+
+fn tile_color(tile: Tile, palette: &Palette) -> Color {
+    let hex = |h: &String| Color::hex(h).unwrap_or(Color::WHITE);
+    match tile {
+        Tile::Block => hex(&palette.ground_2),
+        Tile::AltBlock | Tile::UnstandableBlock => hex(&palette.ground_1),
+        Tile::InvisibleBlock => hex(&palette.ground_1).set_a(0.3).as_rgba(),
+        Tile::HoldableBlock => hex(&palette.block),
+        Tile::WinFlag => hex(&palette.flag),
+        Tile::Snake => hex(&palette.enemy),
+        Tile::ElectricSnake => Color::hex("C8C96B").unwrap(),
+        Tile::Dude => hex(&palette.dude),
+        Tile::BonusFood | Tile::ShadowFood | Tile::Food | Tile::PathfindIgnoreFood => {
+            hex(&palette.food)
+        }
+        Tile::PathfindIgnore | Tile::Empty => Color::rgba(1.0, 1.0, 1.0, 0.2),
+    }
+}
+
+fn build_tiles(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    level: Res<Level>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    tiles: Query<Entity, With<EditorTile>>,
+) {
+    if !editor.tiles_changed {
+        return;
+    }
+    editor.tiles_changed = false;
+
+    for entity in tiles.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette = level.get_palette();
+    let cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    let tile_materials: Vec<Handle<StandardMaterial>> = TILES
+        .iter()
+        .map(|info| {
+            let color = tile_color(info.tile, &palette);
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: if color.a() < 1.0 {
+                    AlphaMode::Blend
+                } else {
+                    AlphaMode::Opaque
+                },
+                reflectance: 0.0,
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    let mut spawn = |position: Vec3, scale: f32, material: Handle<StandardMaterial>| {
+        let mut transform = Transform::from_translation(position);
+        transform.scale = Vec3::splat(scale);
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: cube.clone(),
+                material,
+                transform,
+                ..Default::default()
+            })
+            .insert(EditorTile)
+            .insert(EditorCleanupMarker);
+    };
+
+    for position in level.positions() {
+        let tile = level.get_tile(position).unwrap_or(Tile::Empty);
+        if tile == Tile::Empty {
+            continue;
+        }
+
+        // blocks fill their spot, the things that spawn are drawn smaller
+        let scale = match tile {
+            Tile::Block
+            | Tile::AltBlock
+            | Tile::UnstandableBlock
+            | Tile::InvisibleBlock
+            | Tile::HoldableBlock => 0.9,
+            _ => 0.5,
+        };
+        spawn(position.to_vec(), scale, tile_materials[tile.code()].clone());
+    }
+
+    let teleporter_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        ..Default::default()
+    });
+    for teleporter in level.get_teleporters() {
+        spawn(teleporter.position.to_vec(), 0.3, teleporter_material.clone());
+    }
+}
+
+fn update_editor_text(editor: Res<Editor>, level: Res<Level>, mut texts: Query<&mut Text, With<EditorText>>) {
+    let info = editor.tile.info();
+    let cursor = editor.cursor;
+    let under_cursor = level.get_tile(cursor).unwrap_or(Tile::Empty);
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!(
+            "EDITING level {}\nplacing: {:?} '{}' {}\ncursor: ({}, {}, {}) {:?}, facing {:?}\n{}",
+            level.current_level,
+            info.tile,
+            info.symbol,
+            info.description,
+            cursor.x,
+            cursor.y,
+            cursor.z,
+            under_cursor,
+            editor.facing,
+            editor.message,
+        );
+    }
+}
+
+fn toggle_play_test(
+    keys: Res<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut level: ResMut<Level>,
+    mut level_packs: ResMut<level_pack::LevelPacks>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    match state.current() {
+        AppState::Editor => {
+            // the edits only live in Level, this keeps them from being
+            // replaced by the level asset when play starts
+            level_packs.selected = Some(level.to_levels_asset());
+            level.reset_level();
+            state.set(AppState::InGame).unwrap();
+        }
+        AppState::InGame => {
+            state.set(AppState::Editor).unwrap();
+        }
+        _ => (),
+    }
+}
@@ -2,9 +2,10 @@ use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
 
 use crate::{
-    block, camera, collectable, credits, dude, dust, food, asset_loading, ui, menus, LAST_LEVEL,
+    block, camera, collectable, credits, dude, dust, food, asset_loading, ui, menus,
     holdable, level, level::Level, level_over, moveable, path_find, path_find::PathFinder, score,
-    snake, audio, teleporter, win_flag, EntityType, GameObject, Position, assets::GameAssets,
+    snake, audio, speedrun, teleporter, win_flag, EntityType, GameObject, Position, assets::GameAssets,
+    game_time, game_rng::GameRng, tile::Tile, level_pack, level_generator, level_validation,
 };
 //use bevy_mod_debugdump::print_schedule_runner;
 
@@ -29,6 +30,9 @@ impl Plugin for EnvironmentPlugin {
             .insert_resource(LevelReady(false))
             .insert_resource(GameOver(false))
             .insert_resource(score::Score::new())
+            .init_resource::<GameRng>()
+            .init_resource::<level_pack::LevelPacks>()
+            .init_resource::<level_generator::Endless>()
             .init_resource::<dude::DudeMeshes>()
             .init_resource::<snake::EnemyMeshes>()
             .init_resource::<camera::CameraMeshes>()
@@ -43,6 +47,8 @@ impl Plugin for EnvironmentPlugin {
             .add_event::<level_over::LevelOverEvent>()
             .add_event::<snake::AddBodyPartEvent>()
             .add_event::<snake::KillSnakeEvent>()
+            .add_event::<snake::SnakeCollisionEvent>()
+            .add_event::<snake::LoseBodyPartsEvent>()
             .add_event::<dude::KillDudeEvent>()
             .add_event::<dude::DudeDiedEvent>()
             .add_event::<dust::CreateDustEvent>()
@@ -52,6 +58,10 @@ impl Plugin for EnvironmentPlugin {
                 SystemSet::on_enter(crate::AppState::Loading)
                     .with_system(load_assets)
             )
+            .add_system_set(
+                SystemSet::on_update(crate::AppState::Loading)
+                    .with_system(queue_level_files.before("check_assets_ready"))
+            )
             .add_system_set(
                 SystemSet::on_update(crate::AppState::ChangingLevel)
                     .with_system(change_level_screen),
@@ -74,6 +84,7 @@ impl Plugin for EnvironmentPlugin {
             )
             .add_system_set(
                 SystemSet::on_enter(crate::AppState::InGame)
+                    .with_system(level_generator::extend_endless_levels.before("load_levels_from_asset"))
                     .with_system(try_set_level_from_asset.label("load_levels_from_asset"))
                     .with_system(
                         load_level
@@ -92,50 +103,62 @@ impl Plugin for EnvironmentPlugin {
                     .with_system(reset_score)
             )
             .insert_resource(credits::CreditsDelay(Timer::from_seconds(1.5, false)))
-            .add_system_set(
+            .add_system(validate_loaded_levels)
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
                 SystemSet::on_update(crate::AppState::InGame)
                     .with_system(holdable::lift_holdable.label("handle_lift_events"))
                     .with_system(holdable::update_held.before("handle_lift_events"))
                     .with_system(moveable::update_moveable.label("handle_moveables"))
-                    .with_system(win_flag::update_flag)
                     .with_system(collectable::check_collected)
-                    .with_system(update_hud_text_position)
-                    .with_system(level_over::level_over_check)
-                    //             .with_system(path_find::show_path)
                     .with_system(snake::update_enemy.after(path_find::update_path))
                     .with_system(snake::handle_food_eaten)
                     .with_system(score::handle_food_eaten)
-                    .with_system(food::animate_food)
                     .with_system(food::update_food)
                     .with_system(food::handle_food_eaten)
-                    .with_system(food::disable_food_shadows)
-                    //               .with_system(hide_blocks)
-                    //.with_system(light_thing)
-                    //              .with_system(snake::add_body_part)
                     .with_system(snake::add_body_parts)
                     .with_system(snake::add_body_to_reach_level_min)
                     .with_system(snake::update_following.after(snake::update_enemy))
                     .with_system(snake::handle_kill_snake.after(snake::update_following))
+                    .with_system(snake::detect_snake_collisions.after(snake::update_enemy))
+                    .with_system(snake::handle_snake_collisions.after(snake::detect_snake_collisions))
+                    .with_system(
+                        snake::lose_body_parts
+                            .after(snake::handle_snake_collisions)
+                            .before(snake::update_following),
+                    )
                     .with_system(dude::handle_squashes)
-                    .with_system(camera::handle_player_death)
                     .with_system(dude::handle_kill_dude)
                     .with_system(dude::handle_snake_escapes)
-                    .with_system(path_find::update_graph.label("graph_update"))
+                    .with_system(path_find::update_graph.label("graph_update").after(level::broadcast_changes))
                     .with_system(path_find::update_path.after("graph_update"))
+                    .with_system(level::broadcast_changes.after("handle_moveables"))
+                    .with_system(snake::detect_dude_on_electric_snake)
+                    .with_system(dust::handle_create_dust_event),
+            )
+            .add_system_set(
+                SystemSet::on_update(crate::AppState::InGame)
+                    .with_system(win_flag::update_flag)
+                    .with_system(update_hud_text_position)
+                    .with_system(level_over::level_over_check)
+                    //             .with_system(path_find::show_path)
+                    .with_system(food::animate_food)
+                    .with_system(food::disable_food_shadows)
+                    //               .with_system(hide_blocks)
+                    //.with_system(light_thing)
+                    //              .with_system(snake::add_body_part)
+                    .with_system(camera::handle_player_death)
                     //             .with_system(path_find::draw_edges)
                     //               .with_system(material_test)
                     //               .with_system(level::print_level)
                     //               .with_system(update_text_position)
-                    .with_system(level::broadcast_changes.after("handle_moveables"))
                     .with_system(food::animate_spawn_particles)
                     //.with_system(update_fps)
                     .with_system(camera::cull_blocks)
                     .with_system(camera::cull_moveable_blocks)
-                    .with_system(snake::detect_dude_on_electric_snake)
                     .with_system(shrink_shrinkables)
                     .with_system(grow_growables)
                     //.with_system(debug_level_over)
-                    .with_system(dust::handle_create_dust_event)
                     .with_system(dust::animate_dust), //.with_system(snake::debug_trigger_snake_death)
             );
         //        println!("{}", schedule_graph(&app.app.schedule));
@@ -230,7 +253,7 @@ pub fn load_assets(
     assets_handler.add_mesh(&mut camera_meshes.spikes, "models/spikes.glb#Mesh0/Primitive0");
 
     assets_handler.add_mesh(&mut flag_meshes.flag, "models/winflag.glb#Mesh0/Primitive0");
-    assets_handler.add_asset(&mut level_asset_state.handle, "data/test.custom");
+    assets_handler.add_asset(&mut level_asset_state.handle, "data/levels.custom");
 
     assets_handler.add_material(&mut game_assets.bevy_icon, "textures/bevy.png", true);
 
@@ -346,6 +369,7 @@ pub fn cleanup_environment(
 
 pub fn reset_score(mut score: ResMut<score::Score>) {
     score.current_level = 0;
+    score.current_level_bonus = 0;
 }
 
 pub fn set_clear_color(level: Res<Level>, mut clear_color: ResMut<ClearColor>) {
@@ -355,18 +379,45 @@ pub fn set_clear_color(level: Res<Level>, mut clear_color: ResMut<ClearColor>) {
 
 pub fn load_level_into_path_finder(level: Res<Level>, mut path_finder: ResMut<PathFinder>) {
     path_finder.load_level(&level);
+    // after this the graph only changes where PositionChangeEvents say to
+    path_finder.update_all(&level);
+}
+
+// the level files aren't known until the manifest is read, so they get added
+// to the loading list once it shows up
+pub fn queue_level_files(
+    level_asset_state: Res<level::LevelAssetState>,
+    levels_asset: Res<Assets<level::LevelsAsset>>,
+    mut assets_loading: ResMut<asset_loading::AssetsLoading>,
+) {
+    if let Some(levels_asset) = levels_asset.get(&level_asset_state.handle) {
+        for handle in levels_asset.level_handles.iter() {
+            let handle = handle.clone_untyped();
+            if !assets_loading.asset_handles.contains(&handle) {
+                assets_loading.asset_handles.push(handle);
+            }
+        }
+    }
 }
 
 pub fn try_set_level_from_asset(
     mut level: ResMut<Level>,
     level_asset_state: Res<level::LevelAssetState>,
     levels_asset: ResMut<Assets<level::LevelsAsset>>,
+    level_infos: Res<Assets<level::LevelInfo>>,
+    level_packs: Res<level_pack::LevelPacks>,
     mut state: ResMut<State<crate::AppState>>,
 ) {
     println!("Starting to load level...");
-    let levels_asset = levels_asset.get(&level_asset_state.handle);
+    // the title screen always uses the shipped levels
+    let levels_asset = match &level_packs.selected {
+        Some(pack) if *state.current() != crate::AppState::MainMenu => Some(pack.clone()),
+        _ => levels_asset
+            .get(&level_asset_state.handle)
+            .and_then(|levels_asset| levels_asset.with_loaded_levels(&level_infos)),
+    };
     if let Some(level_asset) = levels_asset {
-        level.load_stored_levels(level_asset.clone());
+        level.load_stored_levels(level_asset);
     } else {
         // try again later?
         println!("failed to load level");
@@ -375,9 +426,35 @@ pub fn try_set_level_from_asset(
     }
 }
 
+// Level files load on their own, so the levels can only be checked once
+// they've all come in. Runs again whenever any of them gets reloaded.
+pub fn validate_loaded_levels(
+    mut levels_asset_events: EventReader<AssetEvent<level::LevelsAsset>>,
+    mut level_info_events: EventReader<AssetEvent<level::LevelInfo>>,
+    level_asset_state: Option<Res<level::LevelAssetState>>,
+    levels_asset: Res<Assets<level::LevelsAsset>>,
+    level_infos: Res<Assets<level::LevelInfo>>,
+) {
+    let changed = levels_asset_events.iter().count() + level_info_events.iter().count() > 0;
+    let level_asset_state = match level_asset_state {
+        Some(level_asset_state) if changed => level_asset_state,
+        _ => return,
+    };
+
+    if let Some(levels) = levels_asset
+        .get(&level_asset_state.handle)
+        .and_then(|levels_asset| levels_asset.with_loaded_levels(&level_infos))
+    {
+        for error in level_validation::validate_levels(&levels) {
+            println!("Level problem: {}", error);
+        }
+    }
+}
+
 pub fn load_level(
     mut commands: Commands,
     mut level: ResMut<Level>,
+    mut rng: ResMut<GameRng>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut level_ready: ResMut<LevelReady>,
@@ -458,8 +535,13 @@ pub fn load_level(
     for x in 0..level.width() {
         for y in 0..level.height() {
             for z in 0..level.length() {
-                match level.get_level_info(x, y, z) {
-                    14 => {
+                let position = Position {
+                    x: x as i32,
+                    y: y as i32,
+                    z: z as i32,
+                };
+                match level.get_tile(position).unwrap_or(Tile::Empty) {
+                    Tile::InvisibleBlock => {
                         let entity = commands
                             .spawn_bundle(PbrBundle {
                                 material: ground_1_material.clone(),
@@ -469,15 +551,13 @@ pub fn load_level(
                             .insert(CleanupMarker)
                             .id();
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
+                            position,
                             Some(GameObject::new(entity, EntityType::Block)),
                         );
                     }
-                    item @ 1 | item @ 8 | item @ 9 => {
+                    item @ Tile::Block | item @ Tile::AltBlock | item @ Tile::UnstandableBlock => {
                         // platform
-                        let entity_type = if item == 9 {
+                        let entity_type = if item == Tile::UnstandableBlock {
                             EntityType::UnstandableBlock
                         } else {
                             EntityType::Block
@@ -485,7 +565,7 @@ pub fn load_level(
                         let mut block = commands
                             .spawn_bundle(PbrBundle {
                                 mesh: if y == 0 { plane.clone() } else { cube.clone() },
-                                material: if item == 1 {
+                                material: if item == Tile::Block {
                                     ground_2_material.clone()
                                 } else {
                                     ground_1_material.clone()
@@ -515,15 +595,13 @@ pub fn load_level(
                             .insert(CleanupMarker)
                             .insert(BlockMesh);
 
-                        if level.current_level == LAST_LEVEL {
+                        if level.is_last_level() {
                             block.insert(bevy::pbr::NotShadowCaster);
                         } 
 
                         let entity = block.id();
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
+                            position,
                             Some(GameObject::new(entity, entity_type)),
                         );
 
@@ -555,7 +633,7 @@ pub fn load_level(
                                 .insert(PlatformMesh);
                         }
                     }
-                    2 => {
+                    Tile::HoldableBlock => {
                         // moveable block
                         let inner_mesh_vertical_offset = 0.0;
                         let mut block = commands
@@ -588,38 +666,31 @@ pub fn load_level(
 
                                 inner.insert(HoldableBlockMesh);
 
-                                if level.current_level == LAST_LEVEL {
+                                if level.is_last_level() {
                                     inner.insert(bevy::pbr::NotShadowCaster);
                                 } 
                             })
                             .insert(CleanupMarker)
                             .insert(EntityType::Block)
                             .insert(holdable::Holdable {})
-                            .insert(Position {
-                                x: x as i32,
-                                y: y as i32,
-                                z: z as i32,
-                            })
+                            .insert(position)
                             .insert(block::BlockObject {})
                             .insert(moveable::Moveable::new(0.1, inner_mesh_vertical_offset));
 
-                        if level.current_level == LAST_LEVEL {
+                        if level.is_last_level() {
                             block.insert(bevy::pbr::NotShadowCaster);
                         } 
 
                         let block_entity = block.id();
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
+                            position,
                             Some(GameObject::new(block_entity, EntityType::Block)),
                         );
                     }
-                    3 => {
+                    Tile::WinFlag => {
                         // win_flag
                         let mut transform = Transform::from_xyz(x as f32, y as f32, z as f32);
                         transform.apply_non_uniform_scale(Vec3::new(0.25, 0.25, 0.25));
-                        let position = Position::from_vec(transform.translation);
 
                         let entity = commands
                             .spawn_bundle(PbrBundle {
@@ -649,13 +720,11 @@ pub fn load_level(
                             .id();
 
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
+                            position,
                             Some(GameObject::new(entity, EntityType::WinFlag)),
                         );
                     }
-                    7 => {
+                    Tile::PathfindIgnore => {
                         let entity = commands
                             .spawn_bundle(PbrBundle {
                                 ..Default::default()
@@ -663,62 +732,64 @@ pub fn load_level(
                             .insert(CleanupMarker)
                             .id();
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
+                            position,
                             Some(GameObject::new(entity, EntityType::PathfindIgnore)),
                         );
                     }
-                    11 => dude::spawn_player(&mut commands, &dude_meshes, &mut level, x, y, z, CleanupMarker),
-                    item @ 5 | item @ 10 => {
+                    Tile::Dude => dude::spawn_player(&mut commands, &dude_meshes, &mut level, x, y, z, CleanupMarker),
+                    item @ Tile::Snake | item @ Tile::ElectricSnake => {
+                        let is_electric = level
+                            .snake_spawn(&position)
+                            .and_then(|spawn| spawn.is_electric)
+                            .unwrap_or(item == Tile::ElectricSnake);
                         snake::spawn_enemy(
                             &mut commands,
                             &enemy_meshes,
+                            &mut materials,
                             &mut level,
                             x,
                             y,
                             z,
-                            item == 10,
+                            is_electric,
                             CleanupMarker
                         );
 
-                        if item == 10 {
+                        if is_electric {
                             audio.play_electricity(&game_assets.electricity_handle);
                         }
                     }
-                    13 => {
-                        let id = food::spawn_food(
+                    Tile::PathfindIgnoreFood => {
+                        food::spawn_food(
                             &mut commands,
                             &mut level,
+                            &mut rng,
                             &mut meshes,
                             &mut materials,
-                            Some(Position {
-                                x: x as i32,
-                                y: y as i32,
-                                z: z as i32,
-                            }),
+                            Some(position),
                             false,
                             CleanupMarker,
                         );
+                        // the marker sits under the food rather than replacing it
+                        let entity = commands
+                            .spawn_bundle(PbrBundle {
+                                ..Default::default()
+                            })
+                            .insert(CleanupMarker)
+                            .id();
                         level.set(
-                            x as i32,
-                            y as i32,
-                            z as i32,
-                            Some(GameObject::new(id, EntityType::PathfindIgnore)),
+                            position,
+                            Some(GameObject::new(entity, EntityType::PathfindIgnore)),
                         );
                     }
-                    item @ 4 | item @ 6 | item @ 12 => {
+                    Tile::BonusFood | Tile::ShadowFood | Tile::Food => {
                         food::spawn_food(
                             &mut commands,
                             &mut level,
+                            &mut rng,
                             &mut meshes,
                             &mut materials,
-                            Some(Position {
-                                x: x as i32,
-                                y: y as i32,
-                                z: z as i32,
-                            }),
-                            false, //item == 6 || item == 4,
+                            Some(position),
+                            false, //item == Tile::ShadowFood || item == Tile::BonusFood,
                             CleanupMarker,
                         );
                     }
@@ -732,10 +803,31 @@ pub fn load_level(
         teleporter::spawn_teleporter(&mut commands, teleporter, CleanupMarker);
     }
 
+    // snakes from the level's snakes list that don't have a tile of their own
+    for spawn in level.snakes_without_tiles() {
+        let is_electric = spawn.is_electric.unwrap_or(false);
+        snake::spawn_enemy(
+            &mut commands,
+            &enemy_meshes,
+            &mut materials,
+            &mut level,
+            spawn.position.x as usize,
+            spawn.position.y as usize,
+            spawn.position.z as usize,
+            is_electric,
+            CleanupMarker
+        );
+
+        if is_electric {
+            audio.play_electricity(&game_assets.electricity_handle);
+        }
+    }
+
     if level.is_food_random() {
         food::spawn_food(
             &mut commands,
             &mut level,
+            &mut rng,
             &mut meshes,
             &mut materials,
             None,
@@ -754,6 +846,7 @@ fn create_hud(
     state: Res<State<crate::AppState>>,
     asset_server: Res<AssetServer>,
     text_scaler: ui::text_size::TextScaler,
+    speedrun: Res<speedrun::Speedrun>,
 ) {
     if *state.current() == crate::AppState::MainMenu {
         return;
@@ -787,6 +880,10 @@ fn create_hud(
         .insert(CleanupMarker)
         .insert(FollowText);
 
+    if speedrun.is_showing() {
+        speedrun::spawn_timer_text(&mut commands, font.clone(), &text_scaler, CleanupMarker);
+    }
+
 //  commands
 //      .spawn_bundle(TextBundle {
 //          style: Style {
@@ -840,7 +937,7 @@ pub fn update_hud_text_position(
     level: Res<Level>,
 ) {
     for mut text in text_query.iter_mut() {
-        if level.current_level == crate::LOST_SCORE_LEVEL {
+        if level.is_lost_score_level() {
             text.sections[0].value = format!(
                 "Score: {} / {}",
                 "{UNDEFINED}",
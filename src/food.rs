@@ -1,4 +1,5 @@
-use crate::{dude, level, level::Level, snake, EntityType, GameObject, Position, environment, LAST_LEVEL};
+use crate::{dude, level, level::Level, snake, EntityType, GameObject, Position, environment,
+    game_rng::GameRng};
 use bevy::prelude::*;
 
 #[derive(Component)]
@@ -21,6 +22,7 @@ pub struct FoodSpawnParticle {
 pub fn spawn_food<T: Component>(
     commands: &mut Commands,
     level: &mut ResMut<Level>,
+    rng: &mut GameRng,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     position: Option<Position>,
@@ -41,7 +43,7 @@ pub fn spawn_food<T: Component>(
     let position = if position.is_some() {
         position.unwrap()
     } else {
-        level.get_random_standable(&None, false)
+        level.get_random_standable(&None, false, rng)
     };
     let transform = Transform::from_xyz(position.x as f32, position.y as f32, position.z as f32);
     let cube = meshes.add(Mesh::from(shape::Cube { size: 0.1 }));
@@ -123,7 +125,7 @@ pub fn spawn_food<T: Component>(
         .insert(cleanup_marker)
         .id();
 
-    level.set_with_position(position, Some(GameObject::new(food_id, EntityType::Food)));
+    level.set(position, Some(GameObject::new(food_id, EntityType::Food)));
 
     food_id
 }
@@ -174,6 +176,7 @@ pub fn handle_food_eaten(
     mut commands: Commands,
     mut food_eaten_event_reader: EventReader<FoodEatenEvent>,
     mut level: ResMut<Level>,
+    mut rng: ResMut<GameRng>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     dudes: Query<&Position, With<dude::Dude>>,
@@ -196,11 +199,12 @@ pub fn handle_food_eaten(
                 away_froms = Some(positions);
             }
 
-            let new_position = level.get_random_standable(&away_froms, false);
+            let new_position = level.get_random_standable(&away_froms, false, &mut *rng);
             println!("Spawning food");
             spawn_food(
                 &mut commands,
                 &mut level,
+                &mut rng,
                 &mut meshes,
                 &mut materials,
                 Some(new_position),
@@ -214,6 +218,7 @@ pub fn handle_food_eaten(
 pub fn update_food(
     mut commands: Commands,
     mut foods: Query<(Entity, &Position, &Food)>,
+    mut level: ResMut<Level>,
     mut position_change_event_reader: EventReader<level::PositionChangeEvent>,
     mut food_eaten_event_writer: EventWriter<FoodEatenEvent>,
 ) {
@@ -222,6 +227,8 @@ pub fn update_food(
             for (entity, position, food) in foods.iter_mut() {
                 if position_change.0 == *position && game_object.entity != entity {
                     commands.entity(entity).despawn_recursive();
+                    // whatever ate it is still standing there
+                    level.remove(*position, entity);
 
                     println!("Sending food eaten event {}", food.is_bonus);
                     food_eaten_event_writer.send(FoodEatenEvent(game_object.entity, food.is_bonus));
@@ -237,7 +244,7 @@ pub fn disable_food_shadows(
     food_outer: Query<&Transform, With<FoodOuter>>,
     mut food_inner: Query<(&mut Visibility, &Parent), With<FoodInnerMesh>>,
 ) {
-    if level.current_level == LAST_LEVEL {
+    if level.is_last_level() {
         if let Ok(dude_transform) = dudes.get_single() {
             for (mut visibility, parent) in food_inner.iter_mut() {
                 if let Ok(food_transform) = food_outer.get(**parent) {
@@ -0,0 +1,74 @@
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+
+// Every random choice that affects the game goes through this so that a
+// seed plus the player's inputs is enough to reproduce a run.
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        *self = GameRng::new(seed);
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(rand::thread_rng().gen())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolls(rng: &mut GameRng) -> Vec<u32> {
+        (0..20).map(|_| rng.gen_range(0..1000)).collect()
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        assert_eq!(rolls(&mut GameRng::new(42)), rolls(&mut GameRng::new(42)));
+        assert_ne!(rolls(&mut GameRng::new(42)), rolls(&mut GameRng::new(43)));
+    }
+
+    #[test]
+    fn reseeding_starts_over() {
+        let mut rng = GameRng::new(7);
+        let first = rolls(&mut rng);
+        rng.reseed(7);
+
+        assert_eq!(rng.seed(), 7);
+        assert_eq!(rolls(&mut rng), first);
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::AppState;
+
+pub const TICK: Duration = Duration::from_nanos(16_666_667);
+
+// caps how much real time a single frame can queue up so a long stall
+// (loading, dragging the window) doesn't fast forward the game
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+// Gameplay systems run in this stage once per TICK regardless of frame rate,
+// so the same inputs always play out the same way.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub struct FixedUpdateStage;
+
+pub struct GameTimePlugin;
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameTime>().add_stage_after(
+            CoreStage::Update,
+            FixedUpdateStage,
+            SystemStage::parallel()
+                .with_run_criteria(run_fixed_ticks)
+                .with_system_set(State::<AppState>::get_driver()),
+        );
+    }
+}
+
+// Gameplay systems read time from here instead of bevy's Time. Each tick
+// advances it by exactly TICK; the headless simulation steps it manually
+// instead of following the real clock.
+#[derive(Debug, Clone)]
+pub struct GameTime {
+    delta: Duration,
+    time_since_startup: Duration,
+    ticks: u64,
+    accumulated: Duration,
+    follows_real_time: bool,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        GameTime {
+            delta: Duration::from_secs(0),
+            time_since_startup: Duration::from_secs(0),
+            ticks: 0,
+            accumulated: Duration::from_secs(0),
+            follows_real_time: true,
+        }
+    }
+}
+
+impl GameTime {
+    // runs exactly one tick per app update
+    pub fn manual() -> Self {
+        GameTime {
+            follows_real_time: false,
+            ..Default::default()
+        }
+    }
+
+    fn advance(&mut self) {
+        self.delta = TICK;
+        self.time_since_startup += TICK;
+        self.ticks += 1;
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn time_since_startup(&self) -> Duration {
+        self.time_since_startup
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.time_since_startup.as_secs_f64()
+    }
+}
+
+fn run_fixed_ticks(
+    time: Res<Time>,
+    mut game_time: ResMut<GameTime>,
+    mut looping: Local<bool>,
+) -> ShouldRun {
+    if !*looping {
+        let frame_time = if game_time.follows_real_time {
+            time.delta()
+        } else {
+            TICK
+        };
+        game_time.accumulated = (game_time.accumulated + frame_time).min(MAX_FRAME_TIME);
+    }
+
+    if game_time.accumulated >= TICK {
+        game_time.accumulated -= TICK;
+        game_time.advance();
+        *looping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        *looping = false;
+        ShouldRun::No
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{Direction, Position};
+use bevy::prelude::*;
+
+pub const DIRECTIONS: [Direction; 6] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+    Direction::Above,
+    Direction::Beneath,
+];
+
+// Anything that names a spot in the level. Level takes any of these so the
+// same lookup works on a Position, a translation or an IVec3 without needing
+// a version of every method for each.
+pub trait GridCoord: Copy {
+    fn to_ivec3(self) -> IVec3;
+    fn from_ivec3(coord: IVec3) -> Self;
+
+    fn to_position(self) -> Position {
+        let coord = self.to_ivec3();
+        Position {
+            x: coord.x,
+            y: coord.y,
+            z: coord.z,
+        }
+    }
+
+    fn neighbour(self, direction: Direction) -> Self {
+        Self::from_ivec3(self.to_ivec3() + offset(direction))
+    }
+
+    // every spot touching this one along with which way it is
+    fn neighbours(self) -> [(Direction, Self); 6] {
+        DIRECTIONS.map(|direction| (direction, self.neighbour(direction)))
+    }
+
+    // where the spot is in a width x height x length grid, if it's in it at all
+    fn to_index(self, width: usize, height: usize, length: usize) -> Option<(usize, usize, usize)> {
+        let coord = self.to_ivec3();
+        if coord.x < 0 || coord.y < 0 || coord.z < 0 {
+            return None;
+        }
+
+        let (x, y, z) = (coord.x as usize, coord.y as usize, coord.z as usize);
+        if x < width && y < height && z < length {
+            Some((x, y, z))
+        } else {
+            None
+        }
+    }
+
+    // In order to make writing the levels easier, level files are stored weird.
+    // Each layer reads like looking down at the level, so the vec is [y][x][z]
+    // with y and x counted from the other end.
+    fn to_file_index(
+        self,
+        width: usize,
+        height: usize,
+        length: usize,
+    ) -> Option<(usize, usize, usize)> {
+        let (x, y, z) = self.to_index(width, height, length)?;
+        Some((height - y - 1, width - x - 1, z))
+    }
+}
+
+impl GridCoord for Position {
+    fn to_ivec3(self) -> IVec3 {
+        IVec3::new(self.x, self.y, self.z)
+    }
+
+    fn from_ivec3(coord: IVec3) -> Self {
+        Position {
+            x: coord.x,
+            y: coord.y,
+            z: coord.z,
+        }
+    }
+}
+
+impl GridCoord for IVec3 {
+    fn to_ivec3(self) -> IVec3 {
+        self
+    }
+
+    fn from_ivec3(coord: IVec3) -> Self {
+        coord
+    }
+}
+
+// translations round toward zero, same as Position::from_vec
+impl GridCoord for Vec3 {
+    fn to_ivec3(self) -> IVec3 {
+        self.as_ivec3()
+    }
+
+    fn from_ivec3(coord: IVec3) -> Self {
+        coord.as_vec3()
+    }
+}
+
+pub fn offset(direction: Direction) -> IVec3 {
+    match direction {
+        Direction::Up => IVec3::new(1, 0, 0),
+        Direction::Down => IVec3::new(-1, 0, 0),
+        Direction::Left => IVec3::new(0, 0, -1),
+        Direction::Right => IVec3::new(0, 0, 1),
+        Direction::Above => IVec3::new(0, 1, 0),
+        Direction::Beneath => IVec3::new(0, -1, 0),
+    }
+}
+
+pub fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+        Direction::Above => Direction::Beneath,
+        Direction::Beneath => Direction::Above,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_index_counts_y_and_x_from_the_other_end() {
+        let position = Position { x: 0, y: 0, z: 2 };
+        assert_eq!(position.to_file_index(4, 3, 5), Some((2, 3, 2)));
+
+        let position = Position { x: 3, y: 2, z: 0 };
+        assert_eq!(position.to_file_index(4, 3, 5), Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn file_index_is_none_out_of_bounds() {
+        for position in [
+            Position { x: -1, y: 0, z: 0 },
+            Position { x: 4, y: 0, z: 0 },
+            Position { x: 0, y: 3, z: 0 },
+            Position { x: 0, y: 0, z: 5 },
+        ] {
+            assert_eq!(position.to_file_index(4, 3, 5), None);
+        }
+    }
+
+    #[test]
+    fn every_coord_type_agrees() {
+        let position = Position { x: 1, y: 2, z: 3 };
+        assert_eq!(Vec3::new(1.4, 2.0, 3.9).to_position(), position);
+        assert_eq!(IVec3::new(1, 2, 3).to_position(), position);
+        assert_eq!(position.neighbour(Direction::Above), Position { x: 1, y: 3, z: 3 });
+        for (direction, neighbour) in position.neighbours() {
+            assert_eq!(neighbour.neighbour(opposite(direction)), position);
+        }
+    }
+}
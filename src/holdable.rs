@@ -1,4 +1,4 @@
-use crate::{facing::Facing, level::Level, Direction, EntityType, GameObject, Position};
+use crate::{facing::Facing, grid::GridCoord, level::Level, Direction, EntityType, GameObject, Position};
 use bevy::prelude::*;
 
 #[derive(Component)]
@@ -66,8 +66,8 @@ pub fn lift_holdable(
                             };
 
                             if let Some(potential_holder_position) = new_holder_position {
-                                if level.is_position_type(potential_holder_position, None)
-                                    || level.is_position_collectable(potential_holder_position)
+                                if level.is_type(potential_holder_position, None)
+                                    || level.is_collectable(potential_holder_position)
                                 {
                                     new_holder_rotation = match direction_to_try {
                                         Direction::Up => Some(Quat::from_axis_angle(
@@ -105,7 +105,7 @@ pub fn lift_holdable(
                     match (transforms.get_mut(held_entity), positions.get_mut(*entity)) {
                         (Ok(mut transform), Ok(position)) => {
                             transform.translation = position.to_vec();
-                            level.set_with_vec(
+                            level.set(
                                 transform.translation,
                                 Some(GameObject::new(held_entity, EntityType::Block)),
                             );
@@ -130,7 +130,7 @@ pub fn lift_holdable(
                             transform.rotation = new_holder_rotation.unwrap();
 
                             if let Ok(entity_type) = entity_types.get(*entity) {
-                                level.set_with_vec(
+                                level.set(
                                     transform.translation,
                                     Some(GameObject::new(*entity, *entity_type)),
                                 );
@@ -165,15 +165,10 @@ pub fn lift_holdable(
                     if let (Some(holder_position), Some(holdee_position)) =
                         (holder_position, holdee_position)
                     {
-                        if level.is_position_type(holdee_position, Some(EntityType::Block))
-                            && level.is_type(
-                                holder_position.x,
-                                holder_position.y + 1,
-                                holder_position.z,
-                                None,
-                            )
+                        if level.is_type(holdee_position, Some(EntityType::Block))
+                            && level.is_type(holder_position.neighbour(Direction::Above), None)
                         {
-                            if let Some(holdable) = level.get_with_position(holdee_position) {
+                            if let Some(holdable) = level.get(holdee_position) {
                                 if let Ok(_) = holdables.get(holdable.entity) {
                                     // checks if actually is "holdable"
                                     println!(
@@ -183,7 +178,7 @@ pub fn lift_holdable(
                                     commands
                                         .entity(holdable.entity)
                                         .insert(BeingHeld { held_by: *entity });
-                                    level.set_with_position(holdee_position, None);
+                                    level.set(holdee_position, None);
                                     commands.entity(holdable.entity).remove::<Position>();
                                     holder.holding = Some(holdable.entity);
                                 }
@@ -199,8 +194,8 @@ pub fn lift_holdable(
                                 y: holder_position.y - 1,
                                 z: holder_position.z,
                             };
-                            if level.is_position_type(holdee_position, Some(EntityType::Block)) {
-                                if let Some(holdable) = level.get_with_position(holdee_position) {
+                            if level.is_type(holdee_position, Some(EntityType::Block)) {
+                                if let Some(holdable) = level.get(holdee_position) {
                                     if let Ok(_) = holdables.get(holdable.entity) {
                                         // checks if actually is "holdable"
                                         println!(
@@ -210,7 +205,7 @@ pub fn lift_holdable(
                                         commands
                                             .entity(holdable.entity)
                                             .insert(BeingHeld { held_by: *entity });
-                                        level.set_with_position(holdee_position, None);
+                                        level.set(holdee_position, None);
                                         commands.entity(holdable.entity).remove::<Position>();
                                         holder.holding = Some(holdable.entity);
                                     }
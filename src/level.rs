@@ -1,40 +1,163 @@
 use crate::{
-    camera::CameraBehavior, dude, snake, audio, teleporter, EntityType, GameObject, Position,
-    assets::GameAssets,
+    camera::CameraBehavior, dude, snake, snake_brain, audio, teleporter, tile, tile::Tile,
+    EntityType, GameObject, Position, assets::GameAssets, grid::GridCoord, Direction,
 };
+use std::collections::BTreeMap;
+use std::path::Path;
 use bevy::prelude::*;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 
-use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset};
 use bevy::reflect::TypeUuid;
 use bevy::utils::BoxedFuture;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub struct PositionChangeEvent(pub Position, pub Option<GameObject>);
 pub struct NextLevelEvent;
 
-static HEIGHT_BUFFER: usize = 3;
+pub static HEIGHT_BUFFER: usize = 3;
 static INITIAL_LEVEL: usize = 99999;
 
 pub struct Level {
-    pub game_objects: Vec<Vec<Vec<Option<GameObject>>>>,
+    pub cells: Vec<Vec<Vec<Cell>>>,
     pub current_level: usize,
     palette: Palette,
     frame_updates: Vec<(usize, usize, usize)>,
     level_info: Vec<LevelInfo>,
+    start_level: usize,
+    last_level: Option<usize>,
+    lost_score_level: Option<usize>,
+    versus_levels: Vec<usize>,
     player_death_detected: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+// Which part of a cell something goes in, so things sharing a spot don't
+// overwrite each other. Terrain is what the level marks the spot as and never
+// moves, items get collected or eaten and actors take up the whole spot.
+// Blocks are actors since they get carried and pushed around like the rest.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Layer {
+    Terrain,
+    Item,
+    Actor,
+}
+
+impl Layer {
+    pub fn of(entity_type: EntityType) -> Layer {
+        match entity_type {
+            EntityType::PathfindIgnore => Layer::Terrain,
+            EntityType::WinFlag | EntityType::Food => Layer::Item,
+            EntityType::Block
+            | EntityType::UnstandableBlock
+            | EntityType::Platform
+            | EntityType::Dude
+            | EntityType::Enemy
+            | EntityType::EnemyHead => Layer::Actor,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Cell {
+    pub terrain: Option<GameObject>,
+    pub item: Option<GameObject>,
+    pub actor: Option<GameObject>,
+}
+
+impl Cell {
+    pub fn get(&self, layer: Layer) -> Option<GameObject> {
+        match layer {
+            Layer::Terrain => self.terrain,
+            Layer::Item => self.item,
+            Layer::Actor => self.actor,
+        }
+    }
+
+    fn get_mut(&mut self, layer: Layer) -> &mut Option<GameObject> {
+        match layer {
+            Layer::Terrain => &mut self.terrain,
+            Layer::Item => &mut self.item,
+            Layer::Actor => &mut self.actor,
+        }
+    }
+
+    // what's seen of the spot from outside, whoever's standing on whatever is there
+    pub fn top(&self) -> Option<GameObject> {
+        self.actor.or(self.item).or(self.terrain)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.top().is_none()
+    }
+
+    pub fn contains(&self, entity_type: EntityType) -> bool {
+        self.get(Layer::of(entity_type))
+            .map_or(false, |game_object| game_object.entity_type == entity_type)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
 #[uuid = "39cadc56-aa9c-4543-8640-a018b74b5052"]
 pub struct LevelsAsset {
     pub start_level: usize,
     pub palette: Palette,
+    #[serde(default)]
     pub levels: Vec<LevelInfo>,
+    // a manifest lists one file per level instead of putting them in `levels`,
+    // the paths are relative to the manifest
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_files: Vec<String>,
+    #[serde(skip)]
+    pub level_handles: Vec<Handle<LevelInfo>>,
+    // the level that ends the game, defaults to the last one
+    #[serde(default)]
+    pub last_level: Option<usize>,
+    // the level where the score shows up as {UNDEFINED}
+    #[serde(default)]
+    pub lost_score_level: Option<usize>,
+    // levels that work for versus mode, played in this order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versus_levels: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+impl LevelsAsset {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut asset = ron::de::from_bytes::<LevelsAsset>(bytes)?;
+        if !asset.levels.is_empty() && !asset.level_files.is_empty() {
+            return Err(anyhow::anyhow!("levels and level_files can't both be used"));
+        }
+        for info in asset.levels.iter_mut() {
+            info.read_layers()?;
+        }
+
+        Ok(asset)
+    }
+
+    // for reading levels outside of the asset server, level files are read right away
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let mut asset = LevelsAsset::from_bytes(&std::fs::read(path)?)?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        for file in asset.level_files.iter() {
+            let bytes = std::fs::read(directory.join(file))?;
+            asset.levels.push(LevelInfo::from_bytes(&bytes)?);
+        }
+
+        Ok(asset)
+    }
+
+    // a copy with `levels` filled in from the level files, None until they've all loaded
+    pub fn with_loaded_levels(&self, level_infos: &Assets<LevelInfo>) -> Option<LevelsAsset> {
+        let mut asset = self.clone();
+        for handle in self.level_handles.iter() {
+            asset.levels.push(level_infos.get(handle)?.clone());
+        }
+
+        Some(asset)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TypeUuid)]
 #[uuid = "99cadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
 pub struct Palette {
     pub base: String,
@@ -52,11 +175,18 @@ pub struct Palette {
 //  pub snake: Color,
 }
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
 #[uuid = "49cadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
 pub struct LevelInfo {
     pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub level: Vec<Vec<Vec<usize>>>,
+    // instead of `level`, layers can be written as rows of tile symbols,
+    // see tile::TILES for the defaults. Spaces in a row are ignored.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub legend: BTreeMap<char, Tile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<Vec<String>>,
     pub score_text: Vec<LevelText>,
     pub level_text: Vec<LevelText>,
     pub is_food_random: bool,
@@ -65,6 +195,11 @@ pub struct LevelInfo {
     pub snake_speed: Option<f32>,
     pub snake_target: Option<snake::SnakeTarget>,
     pub snake_min_length: Option<usize>,
+    // settings for single snakes, anything not listed uses the ones above
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snakes: Vec<snake::SnakeSpawn>,
+    #[serde(default, skip_serializing_if = "snake::SnakeRules::is_off")]
+    pub snake_rules: snake::SnakeRules,
     pub camera_x: f32,
     pub camera_y: f32,
     pub camera_z: f32,
@@ -80,7 +215,7 @@ pub struct LevelInfo {
     pub music: audio::LevelMusic,
 }
 
-#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
 #[uuid = "49cadc56-aa9c-4543-8640-a018b74b5032"] // this needs to be actually generated
 pub enum LevelText {
     DeathBeforeAfter(String, String),
@@ -92,6 +227,55 @@ pub enum LevelText {
     JustText(String),
 }
 
+impl LevelInfo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut info = ron::de::from_bytes::<LevelInfo>(bytes)?;
+        info.read_layers()?;
+        Ok(info)
+    }
+
+    // fills in `level` for levels written with `layers`
+    pub fn read_layers(&mut self) -> Result<(), anyhow::Error> {
+        if self.layers.is_empty() {
+            return Ok(());
+        }
+        if !self.level.is_empty() {
+            return Err(anyhow::anyhow!(
+                "level \"{}\" has both level and layers, pick one",
+                self.title
+            ));
+        }
+
+        let legend = tile::legend(&self.legend);
+        let mut level = vec![];
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            let mut rows = vec![];
+            for (row_index, row) in layer.iter().enumerate() {
+                let mut codes = vec![];
+                for symbol in row.chars().filter(|c| !c.is_whitespace()) {
+                    match legend.get(&symbol) {
+                        Some(tile) => codes.push(tile.code()),
+                        None => {
+                            return Err(anyhow::anyhow!(
+                                "level \"{}\" layer {} row {}: '{}' isn't in the legend",
+                                self.title,
+                                layer_index,
+                                row_index,
+                                symbol
+                            ))
+                        }
+                    }
+                }
+                rows.push(codes);
+            }
+            level.push(rows);
+        }
+
+        self.level = level;
+        Ok(())
+    }
+}
+
 static EMPTY_LEVEL_TEXT: Vec<LevelText> = vec![];
 
 impl LevelText {
@@ -149,8 +333,18 @@ impl AssetLoader for LevelsAssetLoader {
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
             println!("Level asset reloaded");
-            let custom_asset = ron::de::from_bytes::<LevelsAsset>(bytes)?;
-            load_context.set_default_asset(LoadedAsset::new(custom_asset));
+            let mut custom_asset = LevelsAsset::from_bytes(bytes)?;
+
+            // each level file is its own asset so editing one only reloads that level
+            let directory = load_context.path().parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+            let mut dependencies = vec![];
+            for file in custom_asset.level_files.iter() {
+                let path = AssetPath::new(directory.join(file), None);
+                custom_asset.level_handles.push(load_context.get_handle(path.clone()));
+                dependencies.push(path);
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(custom_asset).with_dependencies(dependencies));
             Ok(())
         })
     }
@@ -160,6 +354,28 @@ impl AssetLoader for LevelsAssetLoader {
     }
 }
 
+#[derive(Default)]
+pub struct LevelInfoLoader;
+
+impl AssetLoader for LevelInfoLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            println!("Level reloaded: {:?}", load_context.path());
+            let level_info = LevelInfo::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level_info));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
 #[derive(Default)]
 pub struct LevelAssetState {
     pub handle: Handle<LevelsAsset>,
@@ -168,7 +384,7 @@ pub struct LevelAssetState {
 impl Level {
     pub fn new() -> Self {
         Level {
-            game_objects: vec![],
+            cells: vec![],
             frame_updates: vec![],
             palette: Palette {
                 base: "000000".to_string(),
@@ -187,6 +403,10 @@ impl Level {
             },
             current_level: INITIAL_LEVEL,
             level_info: vec![],
+            start_level: 0,
+            last_level: None,
+            lost_score_level: None,
+            versus_levels: vec![],
             player_death_detected: false,
         }
     }
@@ -287,26 +507,121 @@ impl Level {
         self.level_info[self.current_level].snake_min_length
     }
 
-    pub fn snake_target(&self) -> snake::SnakeTarget {
+    pub fn snake_rules(&self) -> snake::SnakeRules {
+        self.level_info[self.current_level].snake_rules
+    }
+
+    pub fn snake_spawn(&self, position: &Position) -> Option<&snake::SnakeSpawn> {
         self.level_info[self.current_level]
-            .snake_target
-            .clone()
-            .unwrap_or(snake::SnakeTarget::Normal)
+            .snakes
+            .iter()
+            .find(|spawn| spawn.position == *position)
+    }
+
+    pub fn snakes_without_tiles(&self) -> Vec<snake::SnakeSpawn> {
+        self.level_info[self.current_level]
+            .snakes
+            .iter()
+            .filter(|spawn| {
+                self.is_inbounds(spawn.position)
+                    && !matches!(
+                        self.get_tile(spawn.position),
+                        Some(Tile::Snake) | Some(Tile::ElectricSnake)
+                    )
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn snake_brain(&self, position: &Position) -> snake_brain::BrainKind {
+        self.snake_spawn(position)
+            .and_then(|spawn| spawn.brain.clone())
+            .unwrap_or_else(|| {
+                self.level_info[self.current_level]
+                    .snake_target
+                    .unwrap_or(snake::SnakeTarget::Normal)
+                    .into()
+            })
     }
 
     pub fn load_stored_levels(&mut self, asset: LevelsAsset) {
         println!("Loading stored levels..");
         self.level_info = asset.levels;
         self.palette = asset.palette;
+        self.start_level = asset.start_level;
+        self.last_level = asset.last_level;
+        self.lost_score_level = asset.lost_score_level;
+        self.versus_levels = asset.versus_levels;
         self.current_level = if self.current_level == INITIAL_LEVEL {
             asset.start_level
         } else {
             self.current_level
         };
-        self.game_objects = vec![vec![vec![None; self.length()]; self.height()]; self.width()];
+        self.cells = vec![vec![vec![Cell::default(); self.length()]; self.height()]; self.width()];
         self.frame_updates = vec![];
     }
 
+    // just the one level with its tiles placed in cells but nothing
+    // spawned, for looking at a level without playing it
+    pub fn from_level_info(info: LevelInfo) -> Level {
+        let mut level = Level::new();
+        level.current_level = 0;
+        level.level_info = vec![info];
+        level.reset_level();
+
+        let placeholder = Entity::from_raw(0);
+        for position in level.positions().collect::<Vec<_>>() {
+            let entity_type = match level.get_tile(position).unwrap_or(Tile::Empty) {
+                Tile::Block | Tile::AltBlock | Tile::InvisibleBlock | Tile::HoldableBlock => {
+                    EntityType::Block
+                }
+                Tile::UnstandableBlock => EntityType::UnstandableBlock,
+                Tile::WinFlag => EntityType::WinFlag,
+                Tile::Dude => EntityType::Dude,
+                Tile::PathfindIgnore => EntityType::PathfindIgnore,
+                Tile::PathfindIgnoreFood => {
+                    level.set(position, Some(GameObject::new(placeholder, EntityType::PathfindIgnore)));
+                    EntityType::Food
+                }
+                Tile::BonusFood | Tile::ShadowFood | Tile::Food => EntityType::Food,
+                // snakes move, so their spots count as empty
+                Tile::Snake | Tile::ElectricSnake | Tile::Empty => continue,
+            };
+            level.set(position, Some(GameObject::new(placeholder, entity_type)));
+        }
+        level.frame_updates = vec![];
+
+        level
+    }
+
+    // everything that's loaded, including changes made in the editor
+    pub fn to_levels_asset(&self) -> LevelsAsset {
+        let mut levels = self.level_info.clone();
+        for info in levels.iter_mut() {
+            // `level` is what gets edited, keeping both would fail to load
+            info.layers.clear();
+        }
+
+        LevelsAsset {
+            start_level: self.start_level,
+            palette: self.palette.clone(),
+            levels,
+            level_files: vec![],
+            level_handles: vec![],
+            last_level: self.last_level,
+            lost_score_level: self.lost_score_level,
+            versus_levels: self.versus_levels.clone(),
+        }
+    }
+
+    pub fn level_infos(&self) -> &Vec<LevelInfo> {
+        &self.level_info
+    }
+
+    pub fn current_level_info_mut(&mut self) -> &mut LevelInfo {
+        &mut self.level_info[self.current_level]
+    }
+
     pub fn get_level_cull_x(&self) -> Option<(f32, f32)> {
         self.level_info[self.current_level].camera_cull_x
     }
@@ -341,151 +656,175 @@ impl Level {
         current.is_food_random
     }
 
-    pub fn get_level_info(&self, x: usize, y: usize, z: usize) -> usize {
-        // in order to make writing the levels easier, they're stored weird
-        // y and x are reversed and the vec is stored [y][x][z]
-        let height = self.height();
-        let width = self.width();
-        let current = &self.level_info[self.current_level];
+    // every spot in the level, x then y then z
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (width, height, length) = (
+            self.width() as i32,
+            self.height() as i32,
+            self.length() as i32,
+        );
+        (0..width).flat_map(move |x| {
+            (0..height).flat_map(move |y| (0..length).map(move |z| Position { x, y, z }))
+        })
+    }
 
-        if y > current.level.len() - 1 && y < height + 1 {
-            0
-        } else {
-            let height = self.height() - HEIGHT_BUFFER;
-            current.level[height - y - 1][width - x - 1][z]
+    // the level file doesn't include the empty buffer on top
+    fn level_file_index<C: GridCoord>(&self, coord: C) -> Option<(usize, usize, usize)> {
+        coord.to_file_index(self.width(), self.height() - HEIGHT_BUFFER, self.length())
+    }
+
+    pub fn get_level_info<C: GridCoord>(&self, coord: C) -> usize {
+        match self.level_file_index(coord) {
+            Some((y, x, z)) => self.level_info[self.current_level].level[y][x][z],
+            None => 0,
+        }
+    }
+
+    pub fn set_level_info<C: GridCoord>(&mut self, coord: C, value: usize) {
+        if let Some((y, x, z)) = self.level_file_index(coord) {
+            self.current_level_info_mut().level[y][x][z] = value;
         }
     }
 
+    pub fn get_tile<C: GridCoord>(&self, coord: C) -> Option<Tile> {
+        Tile::from_code(self.get_level_info(coord))
+    }
+
+    pub fn last_level(&self) -> usize {
+        self.last_level.unwrap_or(self.level_info.len() - 1)
+    }
+
     pub fn is_last_level(&self) -> bool {
-        self.current_level == self.level_info.len() - 1
+        self.current_level == self.last_level()
+    }
+
+    pub fn versus_levels(&self) -> &[usize] {
+        &self.versus_levels
+    }
+
+    pub fn is_lost_score_level(&self) -> bool {
+        self.lost_score_level == Some(self.current_level)
     }
 
     pub fn change_to_next_level(&mut self) {
         self.current_level += 1;
-        self.game_objects = vec![vec![vec![None; self.length()]; self.height()]; self.width()];
+        self.cells = vec![vec![vec![Cell::default(); self.length()]; self.height()]; self.width()];
     }
 
     pub fn reset_level(&mut self) {
-        self.game_objects = vec![vec![vec![None; self.length()]; self.height()]; self.width()];
+        self.cells = vec![vec![vec![Cell::default(); self.length()]; self.height()]; self.width()];
     }
 
-    pub fn set_with_vec(&mut self, position: Vec3, game_object: Option<GameObject>) {
-        self.set(
-            position.x as i32,
-            position.y as i32,
-            position.z as i32,
-            game_object,
-        );
+    fn index<C: GridCoord>(&self, coord: C) -> Option<(usize, usize, usize)> {
+        let height = self.cells.first().map_or(0, |x_cells| x_cells.len());
+        let length = self
+            .cells
+            .first()
+            .and_then(|x_cells| x_cells.first())
+            .map_or(0, |y_cells| y_cells.len());
+        coord.to_index(self.cells.len(), height, length)
     }
 
-    pub fn is_inbounds(&self, x: i32, y: i32, z: i32) -> bool {
-        x >= 0
-            && y >= 0
-            && z >= 0
-            && (x as usize) < self.game_objects.len()
-            && (y as usize) < self.game_objects[0].len()
-            && (z as usize) < self.game_objects[0][0].len()
+    pub fn is_inbounds<C: GridCoord>(&self, coord: C) -> bool {
+        self.index(coord).is_some()
     }
 
-    pub fn set_with_position(&mut self, position: Position, game_object: Option<GameObject>) {
-        self.set(position.x, position.y, position.z, game_object);
+    fn cell<C: GridCoord>(&self, coord: C) -> Option<&Cell> {
+        let (x, y, z) = self.index(coord)?;
+        Some(&self.cells[x][y][z])
     }
 
-    pub fn set(&mut self, x: i32, y: i32, z: i32, game_object: Option<GameObject>) {
-        if x < 0 || y < 0 || z < 0 {
-            return;
-        }
+    // out of bounds is an empty cell
+    pub fn get_cell<C: GridCoord>(&self, coord: C) -> Cell {
+        self.cell(coord).copied().unwrap_or_default()
+    }
 
-        let (x, y, z) = (x as usize, y as usize, z as usize);
-        if x < self.game_objects.len()
-            && y < self.game_objects[x].len()
-            && z < self.game_objects[x][y].len()
-        {
-            if let Some(game_object) = game_object {
-                if let Some(current) = self.game_objects[x][y][z] {
-                    if current.entity_type == EntityType::EnemyHead
-                        && game_object.entity_type == EntityType::Dude
-                        || current.entity_type == EntityType::Dude
-                            && game_object.entity_type == EntityType::EnemyHead
-                    {
-                        self.player_death_detected = true;
+    pub fn get_layer<C: GridCoord>(&self, coord: C, layer: Layer) -> Option<GameObject> {
+        self.get_cell(coord).get(layer)
+    }
+
+    // Puts the object in its own layer of the cell. Setting None is something
+    // moving out, so it only clears the actor, use remove to take out items
+    // and terrain.
+    pub fn set<C: GridCoord>(&mut self, coord: C, game_object: Option<GameObject>) {
+        let (x, y, z) = match self.index(coord) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let cell = &mut self.cells[x][y][z];
+        match game_object {
+            Some(game_object) => {
+                let layer = Layer::of(game_object.entity_type);
+                if layer == Layer::Actor {
+                    // the dude and a snake head sharing a spot is the dude getting
+                    // eaten, whichever of them got there first
+                    if let Some(current) = cell.actor {
+                        self.player_death_detected |= current.entity_type == EntityType::EnemyHead
+                            && game_object.entity_type == EntityType::Dude
+                            || current.entity_type == EntityType::Dude
+                                && game_object.entity_type == EntityType::EnemyHead;
                     }
                 }
+                *cell.get_mut(layer) = Some(game_object);
             }
-            self.game_objects[x][y][z] = game_object;
-            self.frame_updates.push((x, y, z));
+            None => cell.actor = None,
         }
-    }
 
-    pub fn get_with_vec(&self, position: Vec3) -> Option<GameObject> {
-        self.get(position.x as i32, position.y as i32, position.z as i32)
+        self.frame_updates.push((x, y, z));
     }
 
-    pub fn get_with_position(&self, position: Position) -> Option<GameObject> {
-        self.get(position.x, position.y, position.z)
-    }
+    // takes the entity out of whichever layer it's in, if it's still there
+    pub fn remove<C: GridCoord>(&mut self, coord: C, entity: Entity) {
+        let (x, y, z) = match self.index(coord) {
+            Some(index) => index,
+            None => return,
+        };
 
-    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<GameObject> {
-        if x < 0 || y < 0 || z < 0 {
-            return None;
+        let mut removed = false;
+        for layer in [Layer::Terrain, Layer::Item, Layer::Actor] {
+            let slot = self.cells[x][y][z].get_mut(layer);
+            if slot.map_or(false, |game_object| game_object.entity == entity) {
+                *slot = None;
+                removed = true;
+            }
         }
 
-        let (x, y, z) = (x as usize, y as usize, z as usize);
-        if x < self.game_objects.len()
-            && y < self.game_objects[x].len()
-            && z < self.game_objects[x][y].len()
-        {
-            self.game_objects[x as usize][y as usize][z as usize]
-        } else {
-            None
+        if removed {
+            self.frame_updates.push((x, y, z));
         }
     }
 
+    // the top of the cell, see Cell::top
+    pub fn get<C: GridCoord>(&self, coord: C) -> Option<GameObject> {
+        self.get_cell(coord).top()
+    }
+
     pub fn get_random_standable(
         &self,
         away_froms: &Option<Vec<Position>>,
         allow_path_ignores: bool,
+        rng: &mut impl Rng,
     ) -> Position {
-        let mut standables = vec![];
-
-        for x in 0..self.width() {
-            for y in 0..self.height() {
-                for z in 0..self.length() {
-                    // I'm sorry, I'm sorry, I'm sorry, I'm sorry
-                    if (self.is_standable(x as i32, y as i32, z as i32)
-                        || (allow_path_ignores
-                            && (y > 0
-                                && self.is_type(
-                                    x as i32,
-                                    (y - 1) as i32,
-                                    z as i32,
-                                    Some(EntityType::PathfindIgnore),
-                                ))))
-                        && (!self.is_inbounds(x as i32, y as i32 - 1, z as i32)
-                            || !self.is_type(
-                                x as i32,
-                                y as i32 - 1,
-                                z as i32,
-                                Some(EntityType::Enemy),
-                            ))
-                        && self.is_type(x as i32, y as i32, z as i32, None)
-                    {
-                        standables.push((x as i32, y as i32, z as i32));
-                    }
-                }
-            }
-        }
+        let mut standables: Vec<Position> = self
+            .positions()
+            .filter(|&position| {
+                let below = position.neighbour(Direction::Beneath);
+                // I'm sorry, I'm sorry, I'm sorry, I'm sorry
+                (self.is_standable(position)
+                    || (allow_path_ignores
+                        && self.is_type(below, Some(EntityType::PathfindIgnore))))
+                    && (!self.is_inbounds(below) || !self.is_type(below, Some(EntityType::Enemy)))
+                    && self.is_type(position, None)
+            })
+            .collect();
 
         if let Some(away_froms) = away_froms {
             // return one distances summed up across all the away_froms
             standables.sort_by_key(|standable| {
                 let mut distance = 0.0;
                 for away_from in away_froms.iter() {
-                    let away_from =
-                        Vec3::new(away_from.x as f32, away_from.y as f32, away_from.z as f32);
-                    distance +=
-                        Vec3::new(standable.0 as f32, standable.1 as f32, standable.2 as f32)
-                            .distance(away_from);
+                    distance += standable.to_vec().distance(away_from.to_vec());
                 }
 
                 (distance / away_froms.len() as f32) as i32
@@ -495,33 +834,13 @@ impl Level {
             standables = standables.drain((standables.len() / 2)..).collect();
         }
 
-        let (x, y, z) = standables
-            .choose(&mut rand::thread_rng())
-            .expect("Nothing was standable");
-
-        Position {
-            x: *x,
-            y: *y,
-            z: *z,
-        }
+        *standables.choose(rng).expect("Nothing was standable")
     }
 
-    pub fn is_position_collectable(&self, position: Position) -> bool {
-        self.is_collectable(position.x, position.y, position.z)
-    }
-
-    pub fn is_collectable_with_vec(&self, position: Vec3) -> bool {
-        self.is_collectable(position.x as i32, position.y as i32, position.z as i32)
-    }
-
-    pub fn is_collectable(&self, x: i32, y: i32, z: i32) -> bool {
-        match self.get(x, y, z) {
-            Some(game_object) => match game_object.entity_type {
-                EntityType::WinFlag | EntityType::Food => true,
-                _ => false,
-            },
-            _ => false,
-        }
+    // there's something to pick up and nobody's standing on it
+    pub fn is_collectable<C: GridCoord>(&self, coord: C) -> bool {
+        let cell = self.get_cell(coord);
+        cell.actor.is_none() && (cell.contains(EntityType::WinFlag) || cell.contains(EntityType::Food))
     }
 
     pub fn get_current_minimum_food(&self) -> usize {
@@ -532,29 +851,18 @@ impl Level {
         self.level_info[self.current_level + 1].title.clone()
     }
 
-    pub fn is_enterable_with_vec(&self, position: Vec3) -> bool {
-        self.is_type_with_vec(position, None)
-            || self.is_collectable_with_vec(position)
-            || self.is_type_with_vec(position, Some(EntityType::PathfindIgnore))
-    }
-
-    pub fn is_position_enterable(&self, position: Position) -> bool {
-        self.is_position_type(position, None) || self.is_position_collectable(position)
+    // nothing is taking up the spot, items and terrain never get in the way
+    pub fn is_vacant<C: GridCoord>(&self, coord: C) -> bool {
+        self.cell(coord).map_or(false, |cell| cell.actor.is_none())
     }
 
-    pub fn is_enterable(&self, x: i32, y: i32, z: i32) -> bool {
-        y != 0
-            && (self.is_type(x, y, z, Some(EntityType::PathfindIgnore))
-                || self.is_type(x, y, z, None)
-                || self.is_collectable(x, y, z))
+    // same as vacant but the bottom layer is the floor, which nothing walks into
+    pub fn is_enterable<C: GridCoord>(&self, coord: C) -> bool {
+        coord.to_ivec3().y != 0 && self.is_vacant(coord)
     }
 
-    pub fn is_position_entity(&self, position: &Position) -> bool {
-        self.is_entity(position.x, position.y, position.z)
-    }
-
-    pub fn is_entity(&self, x: i32, y: i32, z: i32) -> bool {
-        match self.get(x, y, z) {
+    pub fn is_entity<C: GridCoord>(&self, coord: C) -> bool {
+        match self.get_layer(coord, Layer::Actor) {
             Some(game_object) => match game_object.entity_type {
                 EntityType::Dude | EntityType::EnemyHead | EntityType::Enemy => true,
                 _ => false,
@@ -563,76 +871,35 @@ impl Level {
         }
     }
 
-    pub fn is_standable(&self, x: i32, y: i32, z: i32) -> bool {
-        // this is awful
-        self.is_type(x, y - 1, z, Some(EntityType::Block)) //|| y - 1 < 0
-            || self.is_type(x, y - 1, z, Some(EntityType::Enemy))
-    }
-
-    pub fn is_position_standable(&self, position: Position) -> bool {
-        self.is_standable(position.x, position.y, position.z)
+    // a block or a snake's body is under the spot
+    pub fn is_standable<C: GridCoord>(&self, coord: C) -> bool {
+        let below = coord.neighbour(Direction::Beneath);
+        self.is_type(below, Some(EntityType::Block)) || self.is_type(below, Some(EntityType::Enemy))
     }
 
-    pub fn is_type_with_vec(&self, position: Vec3, entity_type: Option<EntityType>) -> bool {
-        self.is_type(
-            position.x as i32,
-            position.y as i32,
-            position.z as i32,
-            entity_type,
-        )
-    }
-
-    pub fn is_with_vec(&self, position: Vec3, game_object: Option<GameObject>) -> bool {
-        self.is(
-            position.x as i32,
-            position.y as i32,
-            position.z as i32,
-            game_object,
-        )
-    }
-
-    pub fn is(&self, x: i32, y: i32, z: i32, game_object: Option<GameObject>) -> bool {
-        if x < 0 || y < 0 || z < 0 {
-            return false;
-        }
-
-        let (x, y, z) = (x as usize, y as usize, z as usize);
-
-        if let Some(x_objects) = self.game_objects.get(x) {
-            if let Some(y_objects) = x_objects.get(y) {
-                if let Some(stored_game_object) = y_objects.get(z) {
-                    return *stored_game_object == game_object;
-                }
+    // None checks the whole cell is empty, otherwise whether the object is in its layer
+    pub fn is<C: GridCoord>(&self, coord: C, game_object: Option<GameObject>) -> bool {
+        match (self.cell(coord), game_object) {
+            (Some(cell), None) => cell.is_empty(),
+            (Some(cell), Some(game_object)) => {
+                cell.get(Layer::of(game_object.entity_type)) == Some(game_object)
             }
+            _ => false,
         }
-
-        false
-    }
-
-    pub fn is_position_type(&self, position: Position, entity_type: Option<EntityType>) -> bool {
-        self.is_type(position.x, position.y, position.z, entity_type)
     }
 
-    pub fn is_type(&self, x: i32, y: i32, z: i32, entity_type: Option<EntityType>) -> bool {
-        if x < 0 || y < 0 || z < 0 {
-            return false;
-        }
-
-        let (x, y, z) = (x as usize, y as usize, z as usize);
-
-        if let Some(x_objects) = self.game_objects.get(x) {
-            if let Some(y_objects) = x_objects.get(y) {
-                if let Some(game_object) = y_objects.get(z) {
-                    return match (game_object, entity_type) {
-                        (None, None) => true,
-                        (None, _) | (_, None) => false,
-                        _ => game_object.unwrap().entity_type == entity_type.unwrap(),
-                    };
-                }
-            }
+    // None checks the whole cell is empty, otherwise whether the type is in its layer
+    pub fn is_type<C: GridCoord>(&self, coord: C, entity_type: Option<EntityType>) -> bool {
+        match (self.cell(coord), entity_type) {
+            (Some(cell), None) => cell.is_empty(),
+            (Some(cell), Some(entity_type)) => cell.contains(entity_type),
+            _ => false,
         }
+    }
 
-        false
+    // a snake catching the dude on purpose, like in snake mode, isn't a death
+    pub fn forget_player_death(&mut self) {
+        self.player_death_detected = false;
     }
 
     pub fn drain_frame_updates(&mut self) -> Vec<(Position, Option<GameObject>)> {
@@ -652,7 +919,7 @@ impl Level {
                         y: y as i32,
                         z: z as i32,
                     },
-                    self.game_objects[x][y][z],
+                    self.cells[x][y][z].top(),
                 )
             })
             .collect()
@@ -664,13 +931,9 @@ pub fn print_level(mut time: Local<f32>, timer: Res<Time>, level: Res<Level>) {
     if *time > 1.0 {
         *time = 0.0;
         println!("--------------------------");
-        for x in 0..level.width() {
-            for y in 0..level.height() {
-                for z in 0..level.length() {
-                    if let Some(game_object) = level.get(x as i32, y as i32, z as i32) {
-                        println!("x: {} y: {} z: {} {:?}", x, y, z, game_object.entity_type);
-                    }
-                }
+        for position in level.positions() {
+            if let Some(game_object) = level.get(position) {
+                println!("x: {} y: {} z: {} {:?}", position.x, position.y, position.z, game_object.entity_type);
             }
         }
         println!("--------------------------");
@@ -696,3 +959,57 @@ pub fn broadcast_changes(
         audio.play_sfx(&game_assets.bite_handle[0]);
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // a level with nothing set but its layers, top layer first
+    pub fn level_info(legend: &str, layers: &[&[&str]]) -> LevelInfo {
+        let layers: Vec<Vec<String>> = layers
+            .iter()
+            .map(|layer| layer.iter().map(|row| row.to_string()).collect())
+            .collect();
+        let text = format!(
+            "(title: \"test\", legend: {{ {} }}, layers: {:?}, score_text: [], level_text: [],
+              is_food_random: false, minimum_food: 0, palette: None, snake_speed: None,
+              snake_target: None, snake_min_length: None, camera_x: 0.0, camera_y: 0.0,
+              camera_z: 0.0, camera_rotation_x: 0.0, camera_rotation_y: 0.0,
+              camera_rotation_z: 0.0, camera_rotation_angle: 0.0, camera_behaviors: [],
+              camera_cull_x: None, camera_cull_y: None, camera_cull_z: None,
+              teleporter_links: [], music: (before: [], during: [], after: []))",
+            legend, layers
+        );
+        LevelInfo::from_bytes(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn layers_read_the_same_as_codes() {
+        let info = level_info("", &[&[".W.", ". D *"], &["###", "BSE"]]);
+        assert_eq!(info.level, vec![vec![vec![0, 3, 0], vec![0, 11, 13]], vec![vec![1, 1, 1], vec![2, 5, 10]]]);
+    }
+
+    #[test]
+    fn legend_overrides_the_default_symbols() {
+        let info = level_info("'o': HoldableBlock, '#': AltBlock", &[&["o#."]]);
+        assert_eq!(info.level, vec![vec![vec![2, 8, 0]]]);
+
+        let legend = tile::legend(&info.legend);
+        assert_eq!(legend.get(&'o'), Some(&Tile::HoldableBlock));
+        assert_eq!(legend.get(&'B'), Some(&Tile::HoldableBlock));
+    }
+
+    #[test]
+    fn unknown_symbols_are_errors() {
+        let mut info = level_info("", &[&["..."]]);
+        info.level.clear();
+        info.layers = vec![vec![".?.".to_string()]];
+        assert!(info.read_layers().is_err());
+    }
+
+    #[test]
+    fn level_and_layers_cant_both_be_used() {
+        let mut info = level_info("", &[&["..."]]);
+        assert!(info.read_layers().is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    audio::{LevelMusic, MusicPiece},
+    camera::CameraBehavior,
+    game_rng::GameRng,
+    grid::GridCoord,
+    level::{Level, LevelInfo, LevelText, LevelsAsset, Palette},
+    level_pack, level_validation,
+    path_find::PathFinder,
+    tile::Tile,
+};
+
+// Endless mode makes levels up as they're reached. Each stage is generated
+// from the run's seed so the same seed gives the same levels, and nothing
+// is handed out until PathFinder says the dude can get to the flag and to
+// everywhere food can show up.
+#[derive(Default)]
+pub struct Endless {
+    pub active: bool,
+    pub seed: u64,
+}
+
+impl Endless {
+    // stage 0 is where the game sits before the first level, the same way
+    // the title screen level is for the shipped levels
+    pub fn start(&mut self, seed: u64) -> LevelsAsset {
+        self.active = true;
+        self.seed = seed;
+
+        LevelsAsset {
+            start_level: 0,
+            palette: palettes().remove(0),
+            levels: vec![self.stage(0), self.stage(1)],
+            level_files: vec![],
+            level_handles: vec![],
+            last_level: None,
+            lost_score_level: None,
+            versus_levels: vec![],
+        }
+    }
+
+    pub fn stage(&self, stage: usize) -> LevelInfo {
+        generate(stage, &mut GameRng::new(self.seed.wrapping_add(stage as u64)))
+    }
+}
+
+// keeps a level ready after the one being played so there's always a next one
+pub fn extend_endless_levels(
+    endless: Res<Endless>,
+    level: Res<Level>,
+    mut level_packs: ResMut<level_pack::LevelPacks>,
+) {
+    if !endless.active {
+        return;
+    }
+
+    if let Some(levels) = level_packs.selected.as_mut() {
+        while levels.levels.len() < level.current_level + 2 {
+            let stage = levels.levels.len();
+            println!("Generating endless stage {}", stage);
+            levels.levels.push(endless.stage(stage));
+        }
+    }
+}
+
+pub struct Difficulty {
+    pub snake_speed: f32,
+    pub snakes: usize,
+    pub electric_snakes: usize,
+    pub minimum_food: usize,
+}
+
+impl Difficulty {
+    pub fn for_stage(stage: usize) -> Self {
+        Difficulty {
+            // seconds per snake move, lower is faster
+            snake_speed: (0.5 - stage as f32 * 0.025).max(0.2),
+            snakes: (1 + stage / 3).min(4),
+            electric_snakes: (stage / 5).min(2),
+            minimum_food: (2 + stage).min(15),
+        }
+    }
+}
+
+// terrain goes up to y 2, the rest is room for things on top of it
+const LAYERS: usize = 5;
+const ATTEMPTS: usize = 50;
+
+pub fn generate(stage: usize, rng: &mut impl Rng) -> LevelInfo {
+    let difficulty = Difficulty::for_stage(stage);
+    for _ in 0..ATTEMPTS {
+        let info = build(stage, &difficulty, true, rng);
+        if is_playable(&info) {
+            return info;
+        }
+    }
+
+    // a flat level always works
+    println!("Couldn't generate endless stage {}, using a flat one", stage);
+    build(stage, &difficulty, false, rng)
+}
+
+pub fn is_playable(info: &LevelInfo) -> bool {
+    if !level_validation::validate_level(1, info).is_empty() {
+        return false;
+    }
+
+    let level = Level::from_level_info(info.clone());
+    let mut path_finder = PathFinder::new();
+    path_finder.load_level(&level);
+    path_finder.update_all(&level);
+
+    let mut dude = None;
+    let mut targets = vec![];
+    for position in level.positions() {
+        let tile = level.get_tile(position).unwrap_or(Tile::Empty);
+        if tile == Tile::Dude {
+            dude = Some(position);
+        }
+
+        // random food goes on any empty spot that can be stood on
+        let random_food_spot = info.is_food_random
+            && level.is_standable(position)
+            && level.is_type(position, None);
+        if tile == Tile::WinFlag || tile.is_food() || random_food_spot {
+            targets.push(position);
+        }
+    }
+
+    match dude {
+        Some(dude) => targets
+            .iter()
+            .all(|target| path_finder.is_reachable(&dude, target)),
+        None => false,
+    }
+}
+
+fn build(stage: usize, difficulty: &Difficulty, with_terrain: bool, rng: &mut impl Rng) -> LevelInfo {
+    let size = 8 + stage.min(12) / 2;
+    let width = size + rng.gen_range(0..3);
+    let length = size + rng.gen_range(0..3);
+
+    // [x][y][z] like Level, flipped into the file layout at the end
+    let mut tiles = vec![vec![vec![Tile::Empty; length]; LAYERS]; width];
+    // the first empty y of each column
+    let mut heights = vec![vec![1; length]; width];
+    for column in tiles.iter_mut() {
+        column[0] = vec![Tile::Block; length];
+    }
+
+    if with_terrain {
+        for _ in 0..rng.gen_range(2..(5 + stage.min(6))) {
+            let (platform_width, platform_length) = (rng.gen_range(2..5), rng.gen_range(2..5));
+            let x_start = rng.gen_range(0..=(width - platform_width));
+            let z_start = rng.gen_range(0..=(length - platform_length));
+            let block = *[Tile::Block, Tile::AltBlock].choose(rng).unwrap();
+            for x in x_start..(x_start + platform_width) {
+                for z in z_start..(z_start + platform_length) {
+                    if heights[x][z] < 3 {
+                        tiles[x][heights[x][z]][z] = block;
+                        heights[x][z] += 1;
+                    }
+                }
+            }
+        }
+
+        // the dude can only climb one block, so knock down anything taller
+        // than what's next to it
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for x in 0..width {
+                for z in 0..length {
+                    let lowest_neighbor = neighbors(x, z, width, length)
+                        .map(|(x, z)| heights[x][z])
+                        .min()
+                        .unwrap_or(heights[x][z]);
+                    if heights[x][z] > lowest_neighbor + 1 {
+                        heights[x][z] -= 1;
+                        tiles[x][heights[x][z]][z] = Tile::Empty;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut spots: Vec<(usize, usize)> = (0..width)
+        .flat_map(|x| (0..length).map(move |z| (x, z)))
+        .collect();
+    spots.shuffle(rng);
+
+    // the camera looks down +x so the dude starts close and the flag is far
+    let dude = take_spot(&mut spots, |(x, _)| x < width / 3);
+    let flag = take_spot(&mut spots, |(x, _)| x >= width * 2 / 3);
+    place(&mut tiles, &heights, dude, Tile::Dude);
+    place(&mut tiles, &heights, flag, Tile::WinFlag);
+
+    // snakes start with their body one spot up from their head
+    let snake_spot = |(x, z): (usize, usize)| {
+        let (dude_x, dude_z) = dude.unwrap_or((0, 0));
+        let far_from_dude = (x as i32 - dude_x as i32).abs() + (z as i32 - dude_z as i32).abs() >= 5;
+        far_from_dude && x + 1 < width && heights[x + 1][z] <= heights[x][z]
+    };
+    for _ in 0..difficulty.snakes {
+        place(&mut tiles, &heights, take_spot(&mut spots, snake_spot), Tile::Snake);
+    }
+    for _ in 0..difficulty.electric_snakes {
+        place(&mut tiles, &heights, take_spot(&mut spots, snake_spot), Tile::ElectricSnake);
+    }
+
+    for _ in 0..2 {
+        place(&mut tiles, &heights, take_spot(&mut spots, |_| true), Tile::Food);
+    }
+
+    if with_terrain {
+        // blocks go on the ground so the dude can always climb onto them
+        for _ in 0..rng.gen_range(1..4) {
+            let spot = take_spot(&mut spots, |(x, z)| heights[x][z] == 1);
+            place(&mut tiles, &heights, spot, Tile::HoldableBlock);
+        }
+        // spots the snakes won't go
+        for _ in 0..rng.gen_range(0..3) {
+            place(&mut tiles, &heights, take_spot(&mut spots, |_| true), Tile::PathfindIgnore);
+        }
+    }
+
+    let mut level = vec![vec![vec![0; length]; width]; LAYERS];
+    for x in 0..width {
+        for y in 0..LAYERS {
+            for z in 0..length {
+                let position = IVec3::new(x as i32, y as i32, z as i32);
+                let (file_y, file_x, file_z) = position.to_file_index(width, LAYERS, length).unwrap();
+                level[file_y][file_x][file_z] = tiles[x][y][z].code();
+            }
+        }
+    }
+
+    let music = [
+        MusicPiece::LevelOne,
+        MusicPiece::LevelOne8Bit,
+        MusicPiece::Space,
+        MusicPiece::Qwerty,
+        MusicPiece::Organ,
+        MusicPiece::TickTock,
+        MusicPiece::Hurry,
+    ];
+    let palettes = palettes();
+    let extra = size.saturating_sub(12) as f32;
+
+    LevelInfo {
+        title: format!("endless\nstage {}", stage),
+        level,
+        legend: Default::default(),
+        layers: vec![],
+        score_text: vec![LevelText::ScoreBeforeAfter("your score is".to_string(), "".to_string())],
+        level_text: vec![LevelText::JustText(format!("stage {}", stage))],
+        is_food_random: true,
+        minimum_food: difficulty.minimum_food,
+        palette: Some(palettes[stage % palettes.len()].clone()),
+        snake_speed: Some(difficulty.snake_speed),
+        snake_target: None,
+        snake_min_length: None,
+        snakes: vec![],
+        snake_rules: Default::default(),
+        camera_x: -5.0 - extra * 0.5,
+        camera_y: 11.0 + extra * 0.8,
+        camera_z: (length as f32 - 1.0) / 2.0,
+        camera_rotation_x: -0.3478674,
+        camera_rotation_y: -0.8704345,
+        camera_rotation_z: -0.34832737,
+        camera_rotation_angle: 1.7104243,
+        camera_behaviors: vec![CameraBehavior::Static],
+        camera_cull_x: None,
+        camera_cull_y: None,
+        camera_cull_z: None,
+        teleporter_links: vec![],
+        music: LevelMusic {
+            before: vec![],
+            during: vec![music[stage % music.len()]],
+            after: vec![],
+        },
+    }
+}
+
+fn neighbors(x: usize, z: usize, width: usize, length: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (x, z) = (x as i32, z as i32);
+    [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+        .into_iter()
+        .filter(move |(x, z)| *x >= 0 && *z >= 0 && (*x as usize) < width && (*z as usize) < length)
+        .map(|(x, z)| (x as usize, z as usize))
+}
+
+fn take_spot(
+    spots: &mut Vec<(usize, usize)>,
+    allowed: impl Fn((usize, usize)) -> bool,
+) -> Option<(usize, usize)> {
+    let index = spots.iter().position(|spot| allowed(*spot))?;
+    Some(spots.remove(index))
+}
+
+fn place(
+    tiles: &mut [Vec<Vec<Tile>>],
+    heights: &[Vec<usize>],
+    spot: Option<(usize, usize)>,
+    tile: Tile,
+) {
+    if let Some((x, z)) = spot {
+        tiles[x][heights[x][z]][z] = tile;
+    }
+}
+
+pub fn palettes() -> Vec<Palette> {
+    let palette = |colors: [&str; 8]| Palette {
+        base: colors[0].to_string(),
+        ground_1: colors[1].to_string(),
+        ground_2: colors[2].to_string(),
+        dude: colors[3].to_string(),
+        enemy: colors[4].to_string(),
+        block: colors[5].to_string(),
+        flag: colors[6].to_string(),
+        food: colors[7].to_string(),
+    };
+
+    vec![
+        palette(["b7b7a4", "463c5e", "6b705c", "f3a787", "ff4f69", "d8e2dc", "fdfe89", "fdfe89"]),
+        palette(["585563", "CEA2AC", "585563", "F6D0B1", "ff4f69", "BBBBBB", "fdfe89", "FFFD82"]),
+        palette(["BBBBBB", "BBBBBB", "0D1F2D", "F6D0B1", "ff4f69", "F28123", "fdfe89", "FFFD82"]),
+        palette(["0D1F2D", "0D1F2D", "BBBBBB", "f3a787", "ff4f69", "F28123", "fdfe89", "FFFD82"]),
+    ]
+}
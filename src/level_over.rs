@@ -1,4 +1,4 @@
-use crate::{credits, dude, environment, game_controller, cleanup, level, moveable, snake::Enemy, AppState, title_screen::MenuAction, title_screen, ui::text_size, ui::text_display, menus, assets, audio};
+use crate::{versus, credits, dude, environment, game_controller, cleanup, level, moveable, snake::Enemy, AppState, title_screen::MenuAction, title_screen, ui::text_size, ui::text_display, menus, assets, audio};
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
@@ -134,8 +134,10 @@ pub fn level_over_check(
     mut snakes: Query<&mut Enemy>,
     mut credits_delay: ResMut<credits::CreditsDelay>,
     mut credits_event_writer: EventWriter<crate::credits::CreditsEvent>,
+    versus: Res<versus::Versus>,
 ) {
-    if level_over_events.iter().count() > 0 {
+    // versus::end_round decides what happens after a versus round
+    if level_over_events.iter().count() > 0 && !versus.active {
         println!("LEVEL IS OVER!");
         if level.is_last_level() {
             for mut text in query.iter_mut() {
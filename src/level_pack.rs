@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use crate::{level::LevelsAsset, level_validation, user_data};
+
+// Extra levels players drop into <user data>/level_packs. A pack is either a
+// single .custom file or a folder with a levels.custom manifest in it. Packs
+// play like the shipped levels: start_level is where the game sits before the
+// first level and play starts on the level after it.
+pub struct LevelPack {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Default)]
+pub struct LevelPacks {
+    pub packs: Vec<LevelPack>,
+    // the pack being played, the shipped levels are used when this is None
+    pub selected: Option<LevelsAsset>,
+}
+
+impl LevelPacks {
+    pub fn directory() -> Option<PathBuf> {
+        user_data::directory().map(|directory| directory.join("level_packs"))
+    }
+
+    // looks for packs again so new ones show up without restarting
+    pub fn refresh(&mut self) {
+        self.packs = vec![];
+
+        let entries = match LevelPacks::directory().map(std::fs::read_dir) {
+            Some(Ok(entries)) => entries,
+            _ => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let path = if path.is_dir() {
+                path.join("levels.custom")
+            } else {
+                path
+            };
+
+            if path.is_file() && path.extension().map_or(false, |e| e == "custom") {
+                let name = if path.file_name().map_or(false, |f| f == "levels.custom") {
+                    path.parent().and_then(|p| p.file_name())
+                } else {
+                    path.file_stem()
+                };
+
+                self.packs.push(LevelPack {
+                    name: name.map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                    path,
+                });
+            }
+        }
+
+        self.packs.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn load(&self, index: usize) -> Result<LevelsAsset, anyhow::Error> {
+        let pack = self
+            .packs
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("there's no level pack {}", index))?;
+        let levels = LevelsAsset::from_file(&pack.path)?;
+        for error in level_validation::validate_levels(&levels) {
+            println!("Level problem in {}: {}", pack.name, error);
+        }
+
+        Ok(levels)
+    }
+}
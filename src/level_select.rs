@@ -0,0 +1,245 @@
+use crate::{
+    assets::GameAssets, audio::GameAudio, cleanup, game_controller, level, menus, save_game, score,
+    title_screen, title_screen::MenuAction, ui::text_size, AppState,
+};
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+// Lists the shipped levels with the bests from the save game so any level
+// that's been reached can be jumped straight into.
+pub struct LevelSelectPlugin;
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelSelectMenu>()
+            .add_system_set(SystemSet::on_enter(AppState::LevelSelect).with_system(setup))
+            .add_system_set(
+                SystemSet::on_update(AppState::LevelSelect)
+                    .with_system(update_menu_buttons.after("handle_input"))
+                    .with_system(
+                        handle_controllers
+                            .label("handle_input")
+                            .after("store_controller_inputs"),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelSelect)
+                    .with_system(title_screen::release_all_presses)
+                    .with_system(cleanup::<CleanupMarker>),
+            );
+    }
+}
+
+#[derive(Component)]
+struct CleanupMarker;
+
+// None is the back button
+#[derive(Component)]
+struct LevelButton(Option<usize>);
+
+#[derive(Default)]
+pub struct LevelSelectMenu {
+    selected_button: usize,
+}
+
+fn setup(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    text_scaler: text_size::TextScaler,
+    level: Res<level::Level>,
+    save_game: Res<save_game::SaveGame>,
+    mut menu: ResMut<LevelSelectMenu>,
+) {
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(CleanupMarker);
+
+    let levels: Vec<usize> = (1..=level.last_level()).collect();
+    let number_of_rows = levels.len() + 1;
+
+    // start on the furthest level that's open
+    menu.selected_button = levels
+        .iter()
+        .rposition(|level| save_game.is_unlocked(*level))
+        .unwrap_or(0);
+
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: text_scaler.scale(menus::LEVEL_SELECT_FONT_SIZE),
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(80.0), Val::Percent(90.0)),
+                position_type: PositionType::Relative,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                margin: Rect::all(Val::Auto),
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(CleanupMarker)
+        .with_children(|parent| {
+            for row in levels.iter().map(|level| Some(*level)).chain(std::iter::once(None)) {
+                let label = match row {
+                    Some(row) => {
+                        let title = level.level_infos()[row].title.replace('\n', " ");
+                        let locked = !save_game.is_unlocked(row);
+                        format!("{:>2}. {:<32} {}", row, title, level_stats(&save_game, row, locked))
+                    }
+                    None => "Back".to_string(),
+                };
+
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Relative,
+                            margin: Rect::all(Val::Auto),
+                            size: Size::new(
+                                Val::Percent(100.0),
+                                Val::Percent(90.0 / number_of_rows as f32),
+                            ),
+                            justify_content: JustifyContent::FlexStart,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: menus::NORMAL_BUTTON.into(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(label, text_style.clone(), Default::default()),
+                            ..Default::default()
+                        });
+                    })
+                    .insert(LevelButton(row));
+            }
+        });
+}
+
+fn level_stats(save_game: &save_game::SaveGame, level: usize, locked: bool) -> String {
+    if locked {
+        return "locked".to_string();
+    }
+
+    match save_game.levels.get(&level) {
+        Some(record) => format!(
+            "food {:>3}  bonus {:>2}  deaths {:>3}  time {}",
+            record.best_food,
+            record.best_bonus,
+            record
+                .fewest_deaths
+                .map_or("-".to_string(), |deaths| deaths.to_string()),
+            record.best_time.map_or("-".to_string(), format_time),
+        ),
+        None => "not beaten yet".to_string(),
+    }
+}
+
+// minutes:seconds with tenths
+pub fn format_time(seconds: f32) -> String {
+    let tenths = (seconds.max(0.0) * 10.0) as usize;
+    format!("{}:{:02}.{}", tenths / 600, (tenths / 10) % 60, tenths % 10)
+}
+
+fn update_menu_buttons(
+    mut menu: ResMut<LevelSelectMenu>,
+    buttons: Query<(Entity, &LevelButton)>,
+    mut button_colors: Query<&mut UiColor, With<LevelButton>>,
+    interaction_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<LevelButton>)>,
+    action_state: Query<&ActionState<MenuAction>>,
+    game_assets: Res<GameAssets>,
+    mut audio: GameAudio,
+    mut app_state: ResMut<State<AppState>>,
+    save_game: Res<save_game::SaveGame>,
+    mut score: ResMut<score::Score>,
+    mut game_modes: title_screen::GameModes,
+) {
+    let action_state = action_state.single();
+    let number_of_buttons = buttons.iter().count();
+    if number_of_buttons == 0 {
+        return;
+    }
+    let mut pressed_button = action_state.just_pressed(MenuAction::Select);
+
+    if action_state.just_pressed(MenuAction::Up) {
+        audio.play_sfx(&game_assets.blip);
+        menu.selected_button = menu
+            .selected_button
+            .checked_sub(1)
+            .unwrap_or(number_of_buttons - 1);
+    }
+    if action_state.just_pressed(MenuAction::Down) {
+        audio.play_sfx(&game_assets.blip);
+        menu.selected_button = (menu.selected_button + 1) % number_of_buttons;
+    }
+
+    // mouse
+    for (button_entity, interaction) in interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => pressed_button = true,
+            Interaction::Hovered => {
+                if let Some(i) = buttons.iter().position(|(entity, _)| entity == button_entity) {
+                    menu.selected_button = i;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for (i, mut color) in button_colors.iter_mut().enumerate() {
+        if i == menu.selected_button {
+            *color = menus::HOVERED_BUTTON.into();
+        } else {
+            *color = menus::NORMAL_BUTTON.into();
+        }
+    }
+
+    if !pressed_button {
+        return;
+    }
+
+    match buttons.iter().nth(menu.selected_button) {
+        Some((_, LevelButton(Some(level)))) => {
+            if save_game.is_unlocked(*level) {
+                audio.play_sfx(&game_assets.blip);
+                game_modes.start_at(*level);
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+        }
+        Some((_, LevelButton(None))) => {
+            audio.play_sfx(&game_assets.blip);
+            app_state.set(AppState::MainMenu).unwrap();
+        }
+        None => (),
+    }
+}
+
+fn handle_controllers(
+    controllers: Res<game_controller::GameController>,
+    mut players: Query<(Entity, &mut ActionState<MenuAction>)>,
+) {
+    for (_, mut action_state) in players.iter_mut() {
+        for (_, just_pressed) in controllers.just_pressed.iter() {
+            if just_pressed.contains(&game_controller::GameButton::Up) {
+                action_state.release(MenuAction::Up);
+                action_state.press(MenuAction::Up);
+            }
+            if just_pressed.contains(&game_controller::GameButton::Down) {
+                action_state.release(MenuAction::Down);
+                action_state.press(MenuAction::Down);
+            }
+            if just_pressed.contains(&game_controller::GameButton::ActionDown)
+                || just_pressed.contains(&game_controller::GameButton::Start)
+            {
+                action_state.release(MenuAction::Select);
+                action_state.press(MenuAction::Select);
+            }
+        }
+    }
+}
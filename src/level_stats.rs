@@ -0,0 +1,374 @@
+use crate::{
+    assets::GameAssets, classic, cleanup, dude, environment, grid::GridCoord, holdable,
+    level_select, menus, score::Score, snake, ui::text_size, versus, AppState, Dude, Position,
+};
+use bevy::prelude::*;
+
+// What the player got up to on the level, shown under the score once it's
+// beaten. Starts over whenever a level is loaded, deaths don't clear it.
+pub struct LevelStatsPlugin;
+impl Plugin for LevelStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStats>()
+            // events can go out right as the state changes, so these always run
+            .add_system(count_deaths)
+            .add_system(count_snake_deaths)
+            .add_system_set(
+                SystemSet::on_enter(AppState::ChangingLevel).with_system(reset_stats),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(count_lifts)
+                    .with_system(count_distance_walked)
+                    .with_system(count_near_misses),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::ScoreDisplay).with_system(setup),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::ScoreDisplay).with_system(animate_stats),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::ScoreDisplay).with_system(cleanup::<CleanupMarker>),
+            );
+    }
+}
+
+#[derive(Component)]
+struct CleanupMarker;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LevelStats {
+    pub foods: usize,
+    pub bonus_foods: usize,
+    // seconds
+    pub time: f32,
+    pub falls: usize,
+    pub eaten: usize,
+    pub electrocuted: usize,
+    pub snake_deaths: usize,
+    pub blocks_lifted: usize,
+    // in tiles
+    pub distance_walked: usize,
+    // a snake's head came right up next to the dude and then left without eating him
+    pub near_misses: usize,
+}
+
+impl LevelStats {
+    pub fn record_death(&mut self, death_type: dude::DudeDeath) {
+        match death_type {
+            dude::DudeDeath::Fall => self.falls += 1,
+            dude::DudeDeath::Eaten => self.eaten += 1,
+            dude::DudeDeath::Electric => self.electrocuted += 1,
+        }
+    }
+
+    pub fn deaths(&self) -> usize {
+        self.falls + self.eaten + self.electrocuted
+    }
+
+    // Score already keeps count of these
+    pub fn take_score(&mut self, score: &Score) {
+        self.foods = score.current_level;
+        self.bonus_foods = score.current_level_bonus;
+        self.time = score.current_level_time;
+    }
+
+    // label and value of everything worth showing, anything that didn't happen is left out
+    pub fn rows(&self) -> Vec<(&'static str, StatValue)> {
+        vec![
+            ("food", StatValue::Count(self.foods)),
+            ("bonus food", StatValue::Count(self.bonus_foods)),
+            ("time", StatValue::Time(self.time)),
+            ("fell", StatValue::Count(self.falls)),
+            ("got eaten", StatValue::Count(self.eaten)),
+            ("got shocked", StatValue::Count(self.electrocuted)),
+            ("snakes died", StatValue::Count(self.snake_deaths)),
+            ("blocks lifted", StatValue::Count(self.blocks_lifted)),
+            ("tiles walked", StatValue::Count(self.distance_walked)),
+            ("near misses", StatValue::Count(self.near_misses)),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_zero())
+        .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StatValue {
+    Count(usize),
+    Time(f32),
+}
+
+impl StatValue {
+    fn is_zero(&self) -> bool {
+        match self {
+            StatValue::Count(count) => *count == 0,
+            StatValue::Time(time) => *time <= 0.0,
+        }
+    }
+
+    // progress goes 0 to 1 so the number can count up
+    fn print(&self, progress: f32) -> String {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            StatValue::Count(count) => ((*count as f32 * progress).round() as usize).to_string(),
+            StatValue::Time(time) => level_select::format_time(time * progress),
+        }
+    }
+}
+
+fn reset_stats(mut stats: ResMut<LevelStats>) {
+    *stats = LevelStats::default();
+}
+
+fn count_deaths(
+    mut dude_died_events: EventReader<dude::DudeDiedEvent>,
+    mut stats: ResMut<LevelStats>,
+) {
+    for event in dude_died_events.iter() {
+        stats.record_death(event.death_type);
+    }
+}
+
+fn count_snake_deaths(
+    mut kill_snake_events: EventReader<snake::KillSnakeEvent>,
+    mut stats: ResMut<LevelStats>,
+) {
+    // both snakes in a collision can get killed more than once in the same frame
+    let mut killed: Vec<Entity> = kill_snake_events.iter().map(|event| event.0).collect();
+    killed.sort_unstable();
+    killed.dedup();
+    stats.snake_deaths += killed.len();
+}
+
+fn count_lifts(
+    holders: Query<(Entity, &holdable::Holder), (With<Dude>, Changed<holdable::Holder>)>,
+    mut was_holding: Local<Vec<Entity>>,
+    mut stats: ResMut<LevelStats>,
+) {
+    for (entity, holder) in holders.iter() {
+        let is_holding = holder.holding.is_some();
+        let was = was_holding.contains(&entity);
+        if is_holding && !was {
+            stats.blocks_lifted += 1;
+            was_holding.push(entity);
+        } else if !is_holding && was {
+            was_holding.retain(|e| *e != entity);
+        }
+    }
+}
+
+fn count_distance_walked(
+    dudes: Query<(Entity, &Position), (With<Dude>, Changed<Position>)>,
+    mut last_positions: Local<Vec<(Entity, Position)>>,
+    mut stats: ResMut<LevelStats>,
+) {
+    for (entity, position) in dudes.iter() {
+        match last_positions.iter_mut().find(|(e, _)| *e == entity) {
+            Some((_, last)) => {
+                // falling, climbing and respawning don't count
+                let steps = (position.x - last.x).abs() + (position.z - last.z).abs();
+                if steps == 1 && position.y == last.y {
+                    stats.distance_walked += 1;
+                }
+                *last = *position;
+            }
+            None => last_positions.push((entity, *position)),
+        }
+    }
+}
+
+fn count_near_misses(
+    dudes: Query<(&Position, Option<&environment::Shrink>), With<Dude>>,
+    snakes: Query<(Entity, &Position), With<snake::Enemy>>,
+    mut kill_dude_events: EventReader<dude::KillDudeEvent>,
+    mut dude_died_events: EventReader<dude::DudeDiedEvent>,
+    mut adjacent_snakes: Local<Vec<Entity>>,
+    mut stats: ResMut<LevelStats>,
+) {
+    // the dude is getting eaten or died some other way, whatever's next to him didn't miss
+    let is_dying = kill_dude_events.iter().count() > 0
+        || dude_died_events.iter().count() > 0
+        || dudes.iter().any(|(_, shrink)| shrink.is_some());
+    if is_dying {
+        adjacent_snakes.clear();
+        return;
+    }
+
+    let dudes: Vec<Position> = dudes.iter().map(|(position, _)| *position).collect();
+    let heads: Vec<(Entity, Position)> = snakes
+        .iter()
+        .map(|(entity, position)| (entity, *position))
+        .collect();
+
+    stats.near_misses += missed_snakes(&adjacent_snakes, &heads, &dudes);
+    *adjacent_snakes = next_to_dudes(&heads, &dudes);
+}
+
+fn is_next_to_dude(position: &Position, dudes: &[Position]) -> bool {
+    dudes.iter().any(|dude| {
+        dude.neighbours()
+            .iter()
+            .any(|(_, neighbour)| neighbour == position)
+    })
+}
+
+fn next_to_dudes(heads: &[(Entity, Position)], dudes: &[Position]) -> Vec<Entity> {
+    heads
+        .iter()
+        .filter(|(_, head)| is_next_to_dude(head, dudes))
+        .map(|(entity, _)| *entity)
+        .collect()
+}
+
+// snakes that were next to the dude and have moved off somewhere other than onto him
+fn missed_snakes(was_adjacent: &[Entity], heads: &[(Entity, Position)], dudes: &[Position]) -> usize {
+    was_adjacent
+        .iter()
+        .filter(|entity| {
+            // snakes that got despawned with the level aren't misses, the dude died
+            match heads.iter().find(|(head_entity, _)| head_entity == *entity) {
+                Some((_, head)) => !dudes.contains(head) && !is_next_to_dude(head, dudes),
+                None => false,
+            }
+        })
+        .count()
+}
+
+// seconds between each row showing up and how long a row takes to count up
+const ROW_DELAY: f32 = 0.25;
+const ROW_COUNT_TIME: f32 = 0.6;
+
+#[derive(Component)]
+struct StatRow {
+    label: &'static str,
+    value: StatValue,
+    delay: f32,
+    elapsed: f32,
+}
+
+fn setup(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    text_scaler: text_size::TextScaler,
+    score: Res<Score>,
+    versus: Res<versus::Versus>,
+    classic: Res<classic::Classic>,
+    mut stats: ResMut<LevelStats>,
+) {
+    // these modes have score screens of their own
+    if versus.active || classic.active {
+        return;
+    }
+
+    stats.take_score(&score);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(5.0),
+                    right: Val::Percent(5.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(CleanupMarker)
+        .with_children(|parent| {
+            for (i, (label, value)) in stats.rows().into_iter().enumerate() {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "".to_string(),
+                            TextStyle {
+                                font: game_assets.font.clone(),
+                                font_size: text_scaler.scale(menus::STATS_FONT_SIZE),
+                                color: Color::rgba(0.9, 0.9, 0.9, 0.0),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(StatRow {
+                        label,
+                        value,
+                        delay: i as f32 * ROW_DELAY,
+                        elapsed: 0.0,
+                    });
+            }
+        });
+}
+
+fn animate_stats(time: Res<Time>, mut rows: Query<(&mut StatRow, &mut Text)>) {
+    for (mut row, mut text) in rows.iter_mut() {
+        row.elapsed += time.delta_seconds();
+        let progress = (row.elapsed - row.delay) / ROW_COUNT_TIME;
+        if progress < 0.0 {
+            continue;
+        }
+
+        text.sections[0].value = format!("{} {:>8}", row.label, row.value.print(progress));
+        text.sections[0].style.color.set_a(progress.min(1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUDE: Position = Position { x: 2, y: 1, z: 2 };
+
+    fn snake() -> Entity {
+        Entity::from_raw(1)
+    }
+
+    fn at(x: i32, z: i32) -> Vec<(Entity, Position)> {
+        vec![(snake(), Position { x, y: 1, z })]
+    }
+
+    #[test]
+    fn moving_off_is_a_near_miss() {
+        let was_adjacent = next_to_dudes(&at(1, 2), &[DUDE]);
+        assert_eq!(was_adjacent, vec![snake()]);
+        assert_eq!(missed_snakes(&was_adjacent, &at(0, 2), &[DUDE]), 1);
+    }
+
+    #[test]
+    fn eating_the_dude_is_not_a_near_miss() {
+        let was_adjacent = next_to_dudes(&at(1, 2), &[DUDE]);
+        assert_eq!(missed_snakes(&was_adjacent, &at(2, 2), &[DUDE]), 0);
+    }
+
+    #[test]
+    fn staying_next_to_the_dude_is_not_a_near_miss_yet() {
+        let was_adjacent = next_to_dudes(&at(1, 2), &[DUDE]);
+        assert_eq!(missed_snakes(&was_adjacent, &at(2, 3), &[DUDE]), 0);
+    }
+
+    #[test]
+    fn despawned_snakes_are_not_near_misses() {
+        let was_adjacent = next_to_dudes(&at(1, 2), &[DUDE]);
+        assert_eq!(missed_snakes(&was_adjacent, &[], &[DUDE]), 0);
+    }
+
+    #[test]
+    fn rows_leave_out_what_did_not_happen() {
+        let stats = LevelStats {
+            foods: 3,
+            time: 12.5,
+            near_misses: 1,
+            ..Default::default()
+        };
+        let labels: Vec<&str> = stats.rows().iter().map(|(label, _)| *label).collect();
+
+        assert_eq!(labels, vec!["food", "time", "near misses"]);
+        assert!(LevelStats::default().rows().is_empty());
+    }
+}
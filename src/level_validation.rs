@@ -0,0 +1,420 @@
+use bevy::prelude::Color;
+use std::fmt;
+
+use crate::{
+    level::{LevelInfo, LevelsAsset, HEIGHT_BUFFER},
+    grid::{opposite, GridCoord},
+    snake_brain,
+    tile::Tile,
+    Direction, Position,
+};
+
+// Checks levels for the mistakes that otherwise only show up as a panic
+// somewhere in get_level_info or get_random_standable once the level loads.
+// Coordinates are reported in game space (the same space teleporters use),
+// not in the [y][x][z] order the level files are written in.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelError {
+    pub level: usize,
+    pub title: String,
+    pub position: Option<Position>,
+    pub message: String,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "level {}", self.level)?;
+        if !self.title.is_empty() {
+            write!(f, " \"{}\"", self.title)?;
+        }
+        if let Some(p) = &self.position {
+            write!(f, " at ({}, {}, {})", p.x, p.y, p.z)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+pub fn validate_levels(levels: &LevelsAsset) -> Vec<LevelError> {
+    let mut errors = vec![];
+    if levels.start_level >= levels.levels.len() {
+        errors.push(LevelError {
+            level: levels.start_level,
+            title: "".to_string(),
+            position: None,
+            message: format!(
+                "start_level is {} but there are only {} levels",
+                levels.start_level,
+                levels.levels.len()
+            ),
+        });
+    }
+
+    for (i, level) in levels.levels.iter().enumerate() {
+        errors.append(&mut validate_level(i, level));
+    }
+
+    for &i in levels.versus_levels.iter() {
+        let mut error = |message: String| {
+            errors.push(LevelError {
+                level: i,
+                title: levels.levels.get(i).map(|info| info.title.clone()).unwrap_or_default(),
+                position: None,
+                message,
+            });
+        };
+
+        match levels.levels.get(i) {
+            None => error(format!("versus level {} but there are only {} levels", i, levels.levels.len())),
+            Some(_) if i <= levels.start_level => error("versus levels have to come after start_level".to_string()),
+            Some(info) => {
+                // one of the players needs a snake to play
+                let has_snake_tile = info.level.iter().flatten().flatten().any(|code| {
+                    matches!(Tile::from_code(*code), Some(Tile::Snake) | Some(Tile::ElectricSnake))
+                });
+                if !has_snake_tile && info.snakes.is_empty() {
+                    error("versus levels need a snake".to_string());
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+pub fn validate_level(index: usize, info: &LevelInfo) -> Vec<LevelError> {
+    let mut errors = vec![];
+    let mut error = |position: Option<Position>, message: String| {
+        errors.push(LevelError {
+            level: index,
+            title: info.title.clone(),
+            position,
+            message,
+        });
+    };
+
+    let height = info.level.len();
+    let width = info.level.first().map(|layer| layer.len()).unwrap_or(0);
+    let length = info
+        .level
+        .first()
+        .and_then(|layer| layer.first())
+        .map(|row| row.len())
+        .unwrap_or(0);
+
+    if height == 0 || width == 0 || length == 0 {
+        error(None, "level has no tiles".to_string());
+        return errors;
+    }
+
+    // everything after this assumes the grid is a box
+    let mut is_box = true;
+    for (layer_index, layer) in info.level.iter().enumerate() {
+        if layer.len() != width {
+            error(
+                None,
+                format!(
+                    "layer {} has {} rows but the first layer has {}",
+                    layer_index,
+                    layer.len(),
+                    width
+                ),
+            );
+            is_box = false;
+        }
+        for (row_index, row) in layer.iter().enumerate() {
+            if row.len() != length {
+                error(
+                    None,
+                    format!(
+                        "layer {} row {} has {} tiles but the first row has {}",
+                        layer_index,
+                        row_index,
+                        row.len(),
+                        length
+                    ),
+                );
+                is_box = false;
+            }
+        }
+    }
+    if !is_box {
+        return errors;
+    }
+
+    let position = |x: usize, y: usize, z: usize| Position {
+        x: x as i32,
+        y: y as i32,
+        z: z as i32,
+    };
+    // same as Level::get_level_info, the buffer on top is empty
+    let code = |x: usize, y: usize, z: usize| -> usize {
+        match position(x, y, z).to_file_index(width, height, length) {
+            Some((y, x, z)) => info.level[y][x][z],
+            None => 0,
+        }
+    };
+
+    let mut dudes = vec![];
+    let mut snakes = vec![];
+    let mut flags = 0;
+    let mut food = 0;
+    let mut has_standable = false;
+    for x in 0..width {
+        for y in 0..height + HEIGHT_BUFFER {
+            for z in 0..length {
+                match Tile::from_code(code(x, y, z)) {
+                    Some(Tile::WinFlag) => flags += 1,
+                    Some(Tile::Dude) => dudes.push(position(x, y, z)),
+                    Some(Tile::Snake) | Some(Tile::ElectricSnake) => snakes.push(position(x, y, z)),
+                    Some(tile) if tile.is_food() => food += 1,
+                    Some(Tile::Empty) => {
+                        // an empty spot on top of something solid is where random food can go
+                        if y > 0 && Tile::from_code(code(x, y - 1, z)).map_or(false, Tile::is_standable) {
+                            has_standable = true;
+                        }
+                    }
+                    Some(_) => (),
+                    None => error(
+                        Some(position(x, y, z)),
+                        format!("unknown tile {}", code(x, y, z)),
+                    ),
+                }
+            }
+        }
+    }
+
+    match dudes.len() {
+        1 => (),
+        0 => error(None, "there is no dude spawn".to_string()),
+        _ => {
+            for dude in dudes {
+                error(Some(dude), "there is more than one dude spawn".to_string());
+            }
+        }
+    }
+
+    // level 0 is the title screen backdrop, nobody has to win it
+    if flags == 0 && index != 0 {
+        error(None, "there is no win flag".to_string());
+    }
+
+    if info.is_food_random {
+        if !has_standable {
+            error(
+                None,
+                "food is random but there is nowhere for it to spawn".to_string(),
+            );
+        }
+    } else if food < info.minimum_food {
+        error(
+            None,
+            format!(
+                "minimum_food is {} but the level only has {} food and food isn't random",
+                info.minimum_food, food
+            ),
+        );
+    }
+
+    let in_bounds = |p: &Position| {
+        p.x >= 0
+            && p.y >= 0
+            && p.z >= 0
+            && (p.x as usize) < width
+            && (p.y as usize) < height + HEIGHT_BUFFER
+            && (p.z as usize) < length
+    };
+    for (i, teleporter) in info.teleporter_links.iter().enumerate() {
+        for (name, p) in [
+            ("position", &teleporter.position),
+            ("target", &teleporter.target),
+            ("move_to", &teleporter.move_to),
+        ] {
+            if !in_bounds(p) {
+                error(
+                    Some(*p),
+                    format!("teleporter {} {} is out of bounds", i, name),
+                );
+            }
+        }
+    }
+
+    // the first body part goes right behind the head, so that spot has to be empty
+    let has_room_behind = |p: &Position, facing: Direction| {
+        let behind = p.neighbour(opposite(facing));
+        in_bounds(&behind)
+            && Tile::from_code(code(behind.x as usize, behind.y as usize, behind.z as usize))
+                .map_or(false, Tile::is_open)
+    };
+    for p in snakes.iter() {
+        // snakes with an entry are checked below, facing whichever way it says
+        if !info.snakes.iter().any(|spawn| spawn.position == *p) && !has_room_behind(p, Direction::Down) {
+            error(Some(*p), "snake has no room behind it for its body".to_string());
+        }
+    }
+
+    for (i, spawn) in info.snakes.iter().enumerate() {
+        let p = &spawn.position;
+        let tile = if in_bounds(p) {
+            Tile::from_code(code(p.x as usize, p.y as usize, p.z as usize))
+        } else {
+            None
+        };
+        match tile {
+            Some(Tile::Snake) | Some(Tile::ElectricSnake) | Some(Tile::Empty) => (),
+            Some(_) => error(Some(*p), format!("snakes entry {} is on top of another tile", i)),
+            None => error(Some(*p), format!("snakes entry {} is out of bounds", i)),
+        }
+
+        let facing = spawn.facing.unwrap_or(Direction::Down);
+        if matches!(facing, Direction::Above | Direction::Beneath) {
+            error(Some(*p), format!("snakes entry {} can only face up, down, left or right", i));
+        }
+        if !has_room_behind(p, facing) {
+            error(Some(*p), format!("snakes entry {} has no room behind it for its body", i));
+        }
+
+        if spawn.length == Some(0) {
+            error(Some(*p), format!("snakes entry {} needs a length of at least 1", i));
+        }
+        if let Some(speed) = spawn.speed {
+            if !speed.is_finite() || speed <= 0.0 {
+                error(Some(*p), format!("snakes entry {} speed {} has to be more than 0", i, speed));
+            }
+        }
+        if let Some(color) = &spawn.color {
+            if Color::hex(color).is_err() {
+                error(Some(*p), format!("snakes entry {} color \"{}\" isn't a hex color", i, color));
+            }
+        }
+
+        if let Some(snake_brain::BrainKind::Patrol(waypoints)) = &spawn.brain {
+            for waypoint in waypoints.iter().filter(|waypoint| !in_bounds(waypoint)) {
+                error(
+                    Some(*waypoint),
+                    format!("snakes entry {} has a patrol waypoint out of bounds", i),
+                );
+            }
+        }
+    }
+
+    for (axis, cull) in [
+        ("camera_cull_x", info.camera_cull_x),
+        ("camera_cull_y", info.camera_cull_y),
+        ("camera_cull_z", info.camera_cull_z),
+    ] {
+        if let Some((min, max)) = cull {
+            if !min.is_finite() || !max.is_finite() {
+                error(None, format!("{} ({}, {}) isn't a number", axis, min, max));
+            } else if min + max <= 0.0 {
+                // the visible range is camera - min to camera + max
+                error(
+                    None,
+                    format!("{} ({}, {}) would hide everything", axis, min, max),
+                );
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::tests::level_info;
+    use std::path::Path;
+
+    fn messages(errors: &[LevelError]) -> Vec<&str> {
+        errors.iter().map(|error| error.message.as_str()).collect()
+    }
+
+    fn shipped_levels() -> LevelsAsset {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/data/levels.custom");
+        LevelsAsset::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn shipped_levels_are_fine() {
+        let levels = shipped_levels();
+        let errors: Vec<String> = validate_levels(&levels).iter().map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn a_playable_level_is_fine() {
+        let info = level_info("", &[&["D.W"], &["###"]]);
+        assert!(validate_level(1, &info).is_empty());
+    }
+
+    #[test]
+    fn missing_spawns_are_errors() {
+        let info = level_info("", &[&["..."], &["###"]]);
+        assert_eq!(
+            messages(&validate_level(1, &info)),
+            vec!["there is no dude spawn", "there is no win flag"]
+        );
+
+        // the title screen doesn't need a flag
+        assert_eq!(messages(&validate_level(0, &info)), vec!["there is no dude spawn"]);
+    }
+
+    #[test]
+    fn more_than_one_dude_is_reported_where_they_are() {
+        let info = level_info("", &[&["DDW"], &["###"]]);
+        let errors = validate_level(1, &info);
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| error.position.is_some()));
+    }
+
+    #[test]
+    fn snakes_need_room_behind_them() {
+        // snakes face down, so the row above is behind them
+        let info = level_info("", &[&["#..", "S.W", "D.."], &["###", "###", "###"]]);
+        assert_eq!(
+            messages(&validate_level(1, &info)),
+            vec!["snake has no room behind it for its body"]
+        );
+
+        let info = level_info("", &[&["_..", "S.W", "D.."], &["###", "###", "###"]]);
+        assert!(validate_level(1, &info).is_empty());
+    }
+
+    #[test]
+    fn rows_have_to_be_the_same_length() {
+        let mut info = level_info("", &[&["D.W"], &["###"]]);
+        info.level[1][0].pop();
+        assert_eq!(
+            messages(&validate_level(1, &info)),
+            vec!["layer 1 row 0 has 2 tiles but the first row has 3"]
+        );
+    }
+
+    #[test]
+    fn not_enough_food() {
+        let mut info = level_info("", &[&["DFW"], &["###"]]);
+        info.minimum_food = 2;
+        assert_eq!(
+            messages(&validate_level(1, &info)),
+            vec!["minimum_food is 2 but the level only has 1 food and food isn't random"]
+        );
+    }
+
+    #[test]
+    fn start_level_and_versus_levels_have_to_exist() {
+        let mut levels = shipped_levels();
+        levels.levels = vec![level_info("", &[&["D.W"], &["###"]])];
+        levels.start_level = 1;
+        levels.versus_levels = vec![0, 3];
+
+        assert_eq!(
+            messages(&validate_levels(&levels)),
+            vec![
+                "start_level is 1 but there are only 1 levels",
+                "versus levels have to come after start_level",
+                "versus level 3 but there are only 1 levels",
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+
+use crate::level::{LevelInfo, LevelsAsset};
+
+// Writes levels back out as RON that reads like the hand written files. Past
+// the depth limit ron's pretty printer stops breaking lines, so each row of
+// a `level` grid ends up on a line of its own instead of one tile per line:
+//
+//    level: [
+//        [
+//            [0,0,0,0],
+//            [11,1,0,0],
+//        ],
+//        ...
+//    ],
+//
+// Levels written with `layers` keep their layers and leave `level` out.
+pub fn write_levels(levels: &LevelsAsset) -> Result<String, anyhow::Error> {
+    let mut levels = levels.clone();
+    if !levels.level_files.is_empty() {
+        // a manifest's levels live in their own files
+        levels.levels.clear();
+    }
+
+    levels.levels.iter_mut().for_each(drop_read_grid);
+    // the struct, levels, a level, its grid and a layer come before the rows
+    write_pretty(&levels, 5)
+}
+
+pub fn write_level(info: &LevelInfo) -> Result<String, anyhow::Error> {
+    let mut info = info.clone();
+    drop_read_grid(&mut info);
+    // the struct, its grid and a layer come before the rows
+    write_pretty(&info, 3)
+}
+
+// `level` gets filled in from `layers` when it's read, the layers are what's kept
+fn drop_read_grid(info: &mut LevelInfo) {
+    if !info.layers.is_empty() {
+        info.level.clear();
+    }
+}
+
+fn write_pretty<T: Serialize>(value: &T, depth_limit: usize) -> Result<String, anyhow::Error> {
+    let config = PrettyConfig::default().with_depth_limit(depth_limit);
+    Ok(ron::ser::to_string_pretty(value, config)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn shipped_levels() -> LevelsAsset {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/data/levels.custom");
+        let mut levels = LevelsAsset::from_file(&path).unwrap();
+        // everything inline so the whole thing goes through write_levels
+        levels.level_files.clear();
+        levels
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        let levels = shipped_levels();
+        let written = write_levels(&levels).unwrap();
+        let read = LevelsAsset::from_bytes(written.as_bytes()).unwrap();
+
+        assert_eq!(read.levels.len(), levels.levels.len());
+        for (read, level) in read.levels.iter().zip(levels.levels.iter()) {
+            assert_eq!(read.title, level.title);
+            assert_eq!(read.level, level.level);
+            assert_eq!(read.layers, level.layers);
+        }
+        assert_eq!(write_levels(&read).unwrap(), written);
+    }
+
+    #[test]
+    fn level_round_trip() {
+        for level in shipped_levels().levels.iter() {
+            let written = write_level(level).unwrap();
+            let read = LevelInfo::from_bytes(written.as_bytes()).unwrap();
+
+            assert_eq!(read.level, level.level);
+            assert_eq!(write_level(&read).unwrap(), written);
+        }
+    }
+
+    #[test]
+    fn rows_are_one_line_each() {
+        let level = &shipped_levels().levels[0];
+        let written = write_level(level).unwrap();
+        let row = level.level[0][0]
+            .iter()
+            .map(|code| code.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        assert!(written.contains(&format!("[{}],", row)));
+    }
+}
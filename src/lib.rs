@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::window::WindowMode;
+use serde::{Deserialize, Serialize};
+
+pub mod asset_loading;
+pub mod audio;
+pub mod assets;
+pub mod camera;
+pub mod direction;
+pub mod game_controller;
+pub mod game_rng;
+pub mod game_time;
+pub mod grid;
+pub mod level_over;
+pub mod level_select;
+pub mod level_stats;
+pub mod menus;
+pub mod pause;
+pub mod replay;
+pub mod save_game;
+pub mod score;
+pub mod simulation;
+pub mod speedrun;
+pub mod splash;
+pub mod title_screen;
+pub mod ui;
+pub mod user_data;
+
+pub mod block;
+pub mod classic;
+pub mod collectable;
+pub mod credits;
+pub mod dude;
+pub mod dust;
+pub mod editor;
+pub mod environment;
+pub mod facing;
+pub mod fallable;
+pub mod food;
+pub mod holdable;
+pub mod level;
+pub mod level_generator;
+pub mod level_pack;
+pub mod level_writer;
+pub mod level_validation;
+pub mod moveable;
+pub mod path_find;
+pub mod snake;
+pub mod snake_brain;
+pub mod snake_control;
+pub mod teleporter;
+pub mod tile;
+pub mod versus;
+pub mod win_flag;
+
+use dude::*;
+
+pub static COLOR_BLACK: &str = "000000";
+pub const FONT: &str = "fonts/monogram.ttf";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AppState {
+    MainMenu,
+    Loading,
+    Pause,
+    InGame,
+    ScoreDisplay,
+    LevelTitle,
+    ChangingLevel,
+    ResetLevel,
+    RestartLevel,
+    Splash,
+    Credits,
+    Editor,
+    LevelSelect,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GameObject {
+    pub entity: Entity,
+    pub entity_type: EntityType,
+}
+
+impl GameObject {
+    pub fn new(entity: Entity, entity_type: EntityType) -> Self {
+        GameObject {
+            entity,
+            entity_type,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub enum EntityType {
+    Block,
+    UnstandableBlock,
+    Dude,
+    Enemy,
+    EnemyHead,
+    Platform,
+    WinFlag,
+    Food,
+    PathfindIgnore,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "939adc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Beneath,
+    Above,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, TypeUuid, Component)]
+#[uuid = "93cadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+impl Position {
+    pub fn from_vec(v: Vec3) -> Position {
+        Position {
+            x: v.x as i32,
+            y: v.y as i32,
+            z: v.z as i32,
+        }
+    }
+    pub fn to_vec(&self) -> Vec3 {
+        Vec3::new(self.x as f32, self.y as f32, self.z as f32)
+    }
+    pub fn update_from_vec(&mut self, v: Vec3) {
+        self.x = v.x as i32;
+        self.y = v.y as i32;
+        self.z = v.z as i32;
+    }
+    pub fn matches(&self, v: Vec3) -> bool {
+        v.x as i32 == self.x && v.y as i32 == self.y && v.z as i32 == self.z
+    }
+}
+
+pub fn fullscreen_app(mut windows: ResMut<Windows>) {
+    let window = windows.get_primary_mut().unwrap();
+    println!("Setting fullscreen...");
+    window.set_maximized(true);
+    window.set_mode(WindowMode::BorderlessFullscreen);
+}
+
+pub fn cleanup<T: Component>(mut commands: Commands, entities: Query<Entity, With<T>>) {
+    for entity in entities.iter() {
+        commands.get_or_spawn(entity).despawn_recursive();
+    }
+}
+
+
+pub fn despawn_everything(
+    mut commands: Commands,
+    entities: Query<Entity>,
+) {
+    for entity in entities.iter() {
+        commands.get_or_spawn(entity).despawn_recursive();
+    }
+}
@@ -1,70 +1,21 @@
 use bevy::app::AppExit;
 use bevy::ecs::event::Events;
 use bevy::prelude::*;
-use bevy::reflect::TypeUuid;
 use bevy::window::{PresentMode, WindowMode};
-use serde::Deserialize;
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 
-mod asset_loading;
-mod audio;
-mod assets;
-mod camera;
-mod direction;
-mod game_controller;
-mod level_over;
-mod menus;
-mod pause;
-mod score;
-mod splash;
-mod title_screen;
-mod ui;
-
-pub mod block;
-pub mod collectable;
-pub mod credits;
-pub mod dude;
-pub mod dust;
-pub mod environment;
-pub mod facing;
-pub mod fallable;
-pub mod food;
-pub mod holdable;
-pub mod level;
-pub mod moveable;
-pub mod path_find;
-pub mod snake;
-pub mod teleporter;
-pub mod win_flag;
-
-use dude::*;
-use environment::*;
-
-pub static COLOR_BLACK: &str = "000000";
-pub const FONT: &str = "fonts/monogram.ttf";
-pub const LAST_LEVEL: usize = 16;
-pub const LOST_SCORE_LEVEL: usize = 14;
-
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub enum AppState {
-    MainMenu,
-    Loading,
-    Pause,
-    InGame,
-    ScoreDisplay,
-    LevelTitle,
-    ChangingLevel,
-    ResetLevel,
-    RestartLevel,
-    Splash,
-    Credits,
-}
+use not_snake::*;
+use not_snake::dude::*;
+use not_snake::environment::*;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_event::<credits::CreditsEvent>()
         .add_state(AppState::Loading)
+        .add_plugin(game_time::GameTimePlugin)
+        .insert_resource(replay::ReplayMode::from_args())
+        .add_plugin(replay::ReplayPlugin)
         .add_plugin(assets::AssetsPlugin)
         .add_plugin(asset_loading::AssetLoadingPlugin)
         .insert_resource(Msaa { samples: 1 })
@@ -77,15 +28,23 @@ fn main() {
             present_mode: PresentMode::Fifo,
             ..default()
         })
+        .add_plugin(classic::ClassicPlugin)
         .add_plugin(DudePlugin)
+        .add_plugin(editor::EditorPlugin)
         .add_plugin(EnvironmentPlugin)
         .add_plugin(audio::GameAudioPlugin)
         .add_plugin(game_controller::GameControllerPlugin)
         .add_plugin(level_over::LevelOverPlugin)
+        .add_plugin(level_select::LevelSelectPlugin)
+        .add_plugin(level_stats::LevelStatsPlugin)
         .add_plugin(pause::PausePlugin)
+        .add_plugin(save_game::SaveGamePlugin)
         .add_plugin(score::ScorePlugin)
+        .add_plugin(snake_control::SnakeControlPlugin)
+        .add_plugin(speedrun::SpeedrunPlugin)
         .add_plugin(splash::SplashPlugin)
         .add_plugin(title_screen::TitlePlugin)
+        .add_plugin(versus::VersusPlugin)
         .add_plugin(ui::text_size::TextSizePlugin)
 //      .add_plugin(LogDiagnosticsPlugin::default())
 //      .add_plugin(FrameTimeDiagnosticsPlugin::default())
@@ -100,7 +59,8 @@ fn main() {
                 .with_system(environment::set_clear_color.after("loading_level"))
                 .with_system(environment::load_level_into_path_finder.after("loading_level")),
         )
-        .add_system_set(
+        .add_system_set_to_stage(
+            game_time::FixedUpdateStage,
             SystemSet::on_update(AppState::MainMenu)
                 .with_system(holdable::lift_holdable.label("handle_lift_events"))
                 .with_system(holdable::update_held.before("handle_lift_events"))
@@ -110,17 +70,20 @@ fn main() {
                 .with_system(snake::update_enemy.after(path_find::update_path))
                 .with_system(snake::handle_food_eaten)
                 .with_system(score::handle_food_eaten)
-                .with_system(food::animate_food)
-                .with_system(food::animate_spawn_particles)
                 .with_system(food::update_food)
                 .with_system(food::handle_food_eaten)
                 .with_system(snake::add_body_parts)
                 .with_system(snake::update_following.after(snake::update_enemy))
                 .with_system(snake::handle_kill_snake.after(snake::update_following))
-                .with_system(path_find::update_graph.label("graph_update"))
+                .with_system(path_find::update_graph.label("graph_update").after(level::broadcast_changes))
                 .with_system(path_find::update_path.after("graph_update"))
                 .with_system(level::broadcast_changes.after("handle_moveables"))
         )
+        .add_system_set(
+            SystemSet::on_update(AppState::MainMenu)
+                .with_system(food::animate_food)
+                .with_system(food::animate_spawn_particles)
+        )
         .add_system_set(
             SystemSet::on_exit(AppState::MainMenu)
                 .with_system(cleanup::<environment::CleanupMarker>)
@@ -150,6 +113,8 @@ fn main() {
         .init_resource::<level::LevelAssetState>()
         .add_asset::<level::LevelsAsset>()
         .init_asset_loader::<level::LevelsAssetLoader>()
+        .add_asset::<level::LevelInfo>()
+        .init_asset_loader::<level::LevelInfoLoader>()
         //.add_startup_system(setup)
         //.add_system(print_on_load)
         .add_system(exit)
@@ -161,93 +126,3 @@ fn exit(keys: Res<Input<KeyCode>>, mut exit: ResMut<Events<AppExit>>) {
         exit.send(AppExit);
     }
 }
-
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub struct GameObject {
-    pub entity: Entity,
-    pub entity_type: EntityType,
-}
-
-impl GameObject {
-    pub fn new(entity: Entity, entity_type: EntityType) -> Self {
-        GameObject {
-            entity,
-            entity_type,
-        }
-    }
-}
-
-#[derive(Copy, Clone, PartialEq, Debug, Component)]
-pub enum EntityType {
-    Block,
-    UnstandableBlock,
-    Dude,
-    Enemy,
-    EnemyHead,
-    Platform,
-    WinFlag,
-    Food,
-    PathfindIgnore,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Deserialize, TypeUuid)]
-#[uuid = "939adc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-    Beneath,
-    Above,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Deserialize, TypeUuid, Component)]
-#[uuid = "93cadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
-    pub z: i32,
-}
-impl Position {
-    pub fn from_vec(v: Vec3) -> Position {
-        Position {
-            x: v.x as i32,
-            y: v.y as i32,
-            z: v.z as i32,
-        }
-    }
-    pub fn to_vec(&self) -> Vec3 {
-        Vec3::new(self.x as f32, self.y as f32, self.z as f32)
-    }
-    pub fn update_from_vec(&mut self, v: Vec3) {
-        self.x = v.x as i32;
-        self.y = v.y as i32;
-        self.z = v.z as i32;
-    }
-    pub fn matches(&self, v: Vec3) -> bool {
-        v.x as i32 == self.x && v.y as i32 == self.y && v.z as i32 == self.z
-    }
-}
-
-pub fn fullscreen_app(mut windows: ResMut<Windows>) {
-    let window = windows.get_primary_mut().unwrap();
-    println!("Setting fullscreen...");
-    window.set_maximized(true);
-    window.set_mode(WindowMode::BorderlessFullscreen);
-}
-
-pub fn cleanup<T: Component>(mut commands: Commands, entities: Query<Entity, With<T>>) {
-    for entity in entities.iter() {
-        commands.get_or_spawn(entity).despawn_recursive();
-    }
-}
-
-
-pub fn despawn_everything(
-    mut commands: Commands,
-    entities: Query<Entity>,
-) {
-    for entity in entities.iter() {
-        commands.get_or_spawn(entity).despawn_recursive();
-    }
-}
@@ -7,3 +7,5 @@ pub const BY_LINE_FONT_SIZE: f32 = 45.0;
 pub const FOLLOW_FONT_SIZE: f32 = 40.0;
 pub const BUTTON_LABEL_FONT_SIZE: f32 = 40.0;
 pub const SCORE_FONT_SIZE: f32 = 84.0;
+pub const LEVEL_SELECT_FONT_SIZE: f32 = 26.0;
+pub const STATS_FONT_SIZE: f32 = 32.0;
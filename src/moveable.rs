@@ -1,6 +1,6 @@
 use crate::{
     dude, dust, facing::Facing, level::Level, snake, audio, teleporter, Direction, EntityType,
-    GameObject, Position, assets::GameAssets,
+    GameObject, Position, assets::GameAssets, game_time::GameTime, grid::GridCoord,
 };
 use bevy::prelude::*;
 
@@ -89,7 +89,7 @@ pub fn update_moveable(
     mut audio: audio::GameAudio,
     teleporters: Query<&teleporter::Teleporter>,
     enemies: Query<&snake::Enemy>,
-    time: Res<Time>,
+    time: Res<GameTime>,
 ) {
     for (
         entity,
@@ -104,23 +104,19 @@ pub fn update_moveable(
     {
         if let Some(target_position) = &mut moveable.target_position {
             // if the spot this object moved from is the same object then clear it
-            if let Some(game_object) = level.get_with_vec(transform.translation) {
+            if let Some(game_object) = level.get(transform.translation) {
                 if game_object.entity == entity {
-                    level.set_with_vec(transform.translation, None);
+                    level.set(transform.translation, None);
                 }
             }
 
             if target_position.1 >= target_position.2 {
                 //  check if the target is still valid
-                if level.is_enterable_with_vec(target_position.0) {
+                if level.is_vacant(target_position.0) {
                     transform.translation = target_position.0;
 
                     if target_position.3 == Direction::Beneath
-                        && level.is_standable(
-                            target_position.0.x as i32,
-                            target_position.0.y as i32,
-                            target_position.0.z as i32,
-                        )
+                        && level.is_standable(target_position.0)
                     {
                         create_dust_event_writer.send(dust::CreateDustEvent {
                             position: Position::from_vec(transform.translation),
@@ -153,7 +149,7 @@ pub fn update_moveable(
                                 }
                             }
 
-                            level.set_with_vec(
+                            level.set(
                                 transform.translation,
                                 Some(GameObject::new(entity, *entity_type)),
                             );
@@ -234,7 +230,7 @@ pub fn update_moveable(
             }
 
             // need to update level here
-            level.set_with_vec(
+            level.set(
                 transform.translation,
                 Some(GameObject::new(entity, *entity_type)),
             );
@@ -257,19 +253,9 @@ pub fn update_moveable(
                         movement and reset the current_movement_timer.
                     */
                     let mut ignore_movement = false;
-                    let target_position = match queued_movement.0 {
-                        Direction::Up => (position.x + 1, position.y, position.z),
-                        Direction::Down => (position.x - 1, position.y, position.z),
-                        Direction::Right => (position.x, position.y, position.z + 1),
-                        Direction::Left => (position.x, position.y, position.z - 1),
-                        Direction::Beneath => (position.x, position.y - 1, position.z),
-                        Direction::Above => (position.x, position.y + 1, position.z),
-                    };
-                    let target_position =
-                        IVec3::new(target_position.0, target_position.1, target_position.2)
-                            .as_vec3();
-
-                    let target_is_enterable = level.is_enterable_with_vec(target_position);
+                    let target_position = position.neighbour(queued_movement.0).to_vec();
+
+                    let target_is_enterable = level.is_vacant(target_position);
                     if let Some(mut facing) = maybe_facing {
                         let previous_facing = facing.direction;
                         facing.direction = match queued_movement.0 {
@@ -336,7 +322,7 @@ pub fn update_moveable(
                         };
 
                         // if we're currently not facing a wall/cliff then just turn toward it
-                        let below_target_is_enterable = level.is_enterable_with_vec(Vec3::new(
+                        let below_target_is_enterable = level.is_vacant(Vec3::new(
                             target_position.x,
                             target_position.y - 1.0,
                             target_position.z,
@@ -364,16 +350,15 @@ pub fn update_moveable(
                                 MovementType::Step,
                             ));
                         } else {
-                            let above_moveable =
-                                IVec3::new(position.x, position.y + 1, position.z).as_vec3();
+                            let above_moveable = position.neighbour(Direction::Above).to_vec();
                             let above_target = Vec3::new(
                                 target_position.x,
                                 target_position.y + 1.0,
                                 target_position.z,
                             );
 
-                            if level.is_enterable_with_vec(above_moveable)
-                                && level.is_enterable_with_vec(above_target)
+                            if level.is_vacant(above_moveable)
+                                && level.is_vacant(above_target)
                             {
                                 for enemy in enemies.iter() {
                                     if enemy.is_electric && enemy.is_in_vec(target_position) {
@@ -439,32 +424,9 @@ pub fn update_moveable(
                     let mut keep_going = true;
                     let mut number_of_steps = 0;
                     while keep_going {
-                        let next_position = match queued_movement.0 {
-                            Direction::Up => {
-                                (target_position.x + 1, target_position.y, target_position.z)
-                            }
-                            Direction::Down => {
-                                (target_position.x - 1, target_position.y, target_position.z)
-                            }
-                            Direction::Right => {
-                                (target_position.x, target_position.y, target_position.z + 1)
-                            }
-                            Direction::Left => {
-                                (target_position.x, target_position.y, target_position.z - 1)
-                            }
-                            Direction::Beneath => {
-                                (target_position.x, target_position.y - 1, target_position.z)
-                            }
-                            Direction::Above => {
-                                (target_position.x, target_position.y + 1, target_position.z)
-                            }
-                        };
-                        if level.is_enterable(next_position.0, next_position.1, next_position.2) {
-                            target_position = Position {
-                                x: next_position.0,
-                                y: next_position.1,
-                                z: next_position.2,
-                            };
+                        let next_position = target_position.neighbour(queued_movement.0);
+                        if level.is_enterable(next_position) {
+                            target_position = next_position;
                             audio.play_sfx(&game_assets.slide_handle);
                             number_of_steps += 1;
                         } else {
@@ -472,9 +434,7 @@ pub fn update_moveable(
                         }
                     }
 
-                    let target_position =
-                        IVec3::new(target_position.x, target_position.y, target_position.z)
-                            .as_vec3();
+                    let target_position = target_position.to_vec();
                     moveable.target_position = Some((
                         target_position,
                         0.0,
@@ -493,7 +453,7 @@ pub fn update_moveable(
 
         // for gravity
         if !moveable.is_climbing //&& moveable.target_position.is_none()
-        && level.is_enterable_with_vec(IVec3::new(position.x, position.y - 1, position.z).as_vec3())
+        && level.is_vacant(position.neighbour(Direction::Beneath))
         {
             moveable.set_movement(Direction::Beneath, MovementType::Step);
         }
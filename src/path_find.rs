@@ -1,13 +1,23 @@
 use crate::{
-    dude::Dude, environment::LevelReady, food::Food, level::Level,
-    level::PositionChangeEvent, snake, EntityType, Position,
+    dude::Dude, environment::LevelReady, facing::Facing, food::Food, level::Level,
+    grid::{opposite, GridCoord, DIRECTIONS},
+    level::PositionChangeEvent, snake, EntityType, Position, game_time::GameTime,
+    game_rng::GameRng, Direction, win_flag::WinFlag,
+    snake_brain::{Brain, Goal, Seen, SeenSnake, Senses},
+    snake_control::PlayerSnake,
 };
 use bevy::prelude::*;
-use petgraph::algo::astar;
-use petgraph::algo::has_path_connecting;
-use petgraph::{graph::EdgeIndex, graph::NodeIndex, Graph};
+use bevy::utils::HashMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 //use bevy_prototype_debug_lines::*;
 
+// a spot in the level, as an index into PathFinder's per-spot buffers
+pub type Node = usize;
+
+// extra cost for going through a spot another snake already plans to use
+const CLAIMED_COST: u32 = 99;
+
 /*
     everything should start with edges pointing down
     then, every block should modify the edges of the space above it
@@ -16,75 +26,184 @@ use petgraph::{graph::EdgeIndex, graph::NodeIndex, Graph};
     into each cardinal direction space around it. That is, each
     block prevents moving into but enables moving "out of" above
     it
+
+    edges are kept per spot as the weight of moving into it from each
+    of its six neighbors, so changing one is just setting a slot
 */
 pub struct PathFinder {
-    indices: Vec<Vec<Vec<NodeIndex<u32>>>>,
-    graph: Graph<(i32, i32, i32), u32>,
+    width: usize,
+    height: usize,
+    length: usize,
+    entries: Vec<[Option<u32>; 6]>,
+    teleporters: Vec<(Node, Node)>,
+    search: Search,
+    paths: HashMap<Entity, (Node, Vec<Node>)>,
+}
+
+// A* buffers kept between searches. Spots are marked with the id of the
+// search that touched them so nothing has to be cleared
+#[derive(Default)]
+struct Search {
+    id: u32,
+    cost: Vec<u32>,
+    came_from: Vec<Node>,
+    seen: Vec<u32>,
+    closed: Vec<u32>,
+    claimed: Vec<u32>,
+    open: BinaryHeap<Reverse<(u32, Node)>>,
+    moves: Vec<(Node, u32)>,
+}
+
+impl Search {
+    fn reset(&mut self, size: usize) {
+        *self = Search {
+            cost: vec![0; size],
+            came_from: vec![0; size],
+            seen: vec![0; size],
+            closed: vec![0; size],
+            claimed: vec![0; size],
+            ..Default::default()
+        };
+    }
+
+    fn next_id(&mut self) -> u32 {
+        if self.id == u32::MAX {
+            let size = self.cost.len();
+            self.reset(size);
+        }
+        self.id += 1;
+        self.open.clear();
+        self.id
+    }
+}
+
+fn index(direction: Direction) -> usize {
+    match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+        Direction::Above => 4,
+        Direction::Beneath => 5,
+    }
+}
+
+fn distance(a: &Position, b: &Position) -> u32 {
+    ((a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()) as u32
 }
 
 impl PathFinder {
     pub fn load_level(&mut self, level: &Level) {
-        let length = level.length();
-        let width = level.width();
-        let height = level.height();
-        let mut indices: Vec<Vec<Vec<NodeIndex<u32>>>> =
-            vec![vec![vec![NodeIndex::new(0); length]; height]; width];
-        let mut graph = Graph::<(i32, i32, i32), u32>::new();
-        for x in 0..width {
-            for y in 0..height {
-                for z in 0..length {
-                    indices[x][y][z] = graph.add_node((x as i32, y as i32, z as i32));
-                }
-            }
-        }
+        self.width = level.width();
+        self.height = level.height();
+        self.length = level.length();
+
+        let size = self.width * self.height * self.length;
+        self.entries = vec![[None; 6]; size];
+        self.teleporters = vec![];
+        self.search.reset(size);
+        self.paths.clear();
 
         // set everything to connect to the node underneath it
-        for x in 0..width {
-            for y in 0..height {
-                for z in 0..length {
-                    if y > 0 {
-                        graph.add_edge(indices[x][y][z], indices[x][y - 1][z], 1);
-                    }
-                }
+        for node in 0..size {
+            let position = self.get_position(node);
+            if position.y + 1 < self.height as i32 {
+                self.entries[node][index(Direction::Above)] = Some(1);
             }
         }
-        self.indices = indices;
-        self.graph = graph;
     }
 
     pub fn new() -> Self {
         PathFinder {
-            indices: vec![],
-            graph: Graph::<(i32, i32, i32), u32>::new(),
+            width: 0,
+            height: 0,
+            length: 0,
+            entries: vec![],
+            teleporters: vec![],
+            search: Search::default(),
+            paths: HashMap::default(),
+        }
+    }
+
+    fn is_inbounds(&self, position: &Position) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.z >= 0
+            && (position.x as usize) < self.width
+            && (position.y as usize) < self.height
+            && (position.z as usize) < self.length
+    }
+
+    // None for spots outside the level
+    pub fn node(&self, position: &Position) -> Option<Node> {
+        if !self.is_inbounds(position) {
+            return None;
+        }
+
+        Some((position.x as usize * self.height + position.y as usize) * self.length + position.z as usize)
+    }
+
+    pub fn get_position(&self, node: Node) -> Position {
+        Position {
+            x: (node / (self.length * self.height)) as i32,
+            y: (node / self.length % self.height) as i32,
+            z: (node % self.length) as i32,
         }
     }
 
-    fn get_edge(
-        &self,
-        position_a: &Position,
-        position_b: &Position,
-        level: &Res<Level>,
-    ) -> Option<EdgeIndex<u32>> {
-        if level.is_inbounds(position_a.x, position_a.y, position_a.z)
-            && level.is_inbounds(position_b.x, position_b.y, position_b.z)
-        {
-            self.graph.find_edge(
-                self.indices[position_a.x as usize][position_a.y as usize][position_a.z as usize],
-                self.indices[position_b.x as usize][position_b.y as usize][position_b.z as usize],
+    fn neighbor(&self, node: Node, direction: Direction) -> Option<Node> {
+        self.node(&self.get_position(node).neighbour(direction))
+    }
+
+    // every move out of a spot along with what it costs
+    fn moves(&self, node: Node) -> impl Iterator<Item = (Node, u32)> + '_ {
+        DIRECTIONS
+            .into_iter()
+            .filter_map(move |direction| {
+                let next = self.neighbor(node, direction)?;
+                // moving toward next means coming into it from the opposite side
+                let weight = self.entries[next][index(opposite(direction))]?;
+                Some((next, weight))
+            })
+            .chain(
+                self.teleporters
+                    .iter()
+                    .filter(move |(from, _)| *from == node)
+                    .map(|(_, to)| (*to, 1)),
             )
-        } else {
-            None
+    }
+
+    fn edge_weight(&self, from: Node, to: Node) -> Option<u32> {
+        self.moves(from)
+            .filter(|(next, _)| *next == to)
+            .map(|(_, weight)| weight)
+            .min()
+    }
+
+    // whether a snake at `from` can step straight to `to`
+    pub fn can_move(&self, from: &Position, to: &Position) -> bool {
+        match (self.node(from), self.node(to)) {
+            (Some(from), Some(to)) => self.edge_weight(from, to).is_some(),
+            _ => false,
         }
     }
 
+    // None if any step of the path can't be taken anymore
+    pub fn path_cost(&self, path: &[Node]) -> Option<u32> {
+        path.iter()
+            .zip(path.iter().skip(1))
+            .map(|(current, next)| self.edge_weight(*current, *next))
+            .sum()
+    }
+
     pub fn get_any_connected_position(&self, pos: &Position) -> Option<Position> {
-        let start_index = self.indices[pos.x as usize][pos.y as usize][pos.z as usize];
+        let start_index = self.node(pos)?;
 
-        let all_nodes = self.graph.node_indices();
-        for node in all_nodes {
+        for node in 0..self.entries.len() {
             if node != start_index {
-                if has_path_connecting(&self.graph, node, start_index, None) {
-                    return Some(self.get_position(node));
+                let position = self.get_position(node);
+                if self.is_reachable(&position, pos) {
+                    return Some(position);
                 }
             }
         }
@@ -93,92 +212,16 @@ impl PathFinder {
     }
 
     // this should just get called for everything
-    fn update_position_in_graph(&mut self, position: &Position, level: &Res<Level>) {
-        if !level.is_inbounds(position.x, position.y, position.z) {
-            return;
-        }
-
-        let (x, y, z) = (
-            position.x as usize,
-            position.y as usize,
-            position.z as usize,
-        );
-
-        // remove everything entering into this spot?
-        // above
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x,
-                y: position.y + 1,
-                z: position.z,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
-        // below
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x,
-                y: position.y - 1,
-                z: position.z,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
-        // up
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x + 1,
-                y: position.y,
-                z: position.z,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
-        // down
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x - 1,
-                y: position.y,
-                z: position.z,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
-        // left
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x,
-                y: position.y,
-                z: position.z - 1,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
-        // right
-        if let Some(edge) = self.get_edge(
-            &Position {
-                x: position.x,
-                y: position.y,
-                z: position.z + 1,
-            },
-            &position,
-            &level,
-        ) {
-            self.graph.remove_edge(edge);
-        }
+    fn update_position_in_graph(&mut self, position: &Position, level: &Level) {
+        let node = match self.node(position) {
+            Some(node) => node,
+            None => return,
+        };
+        // remove everything entering into this spot
+        self.entries[node] = [None; 6];
 
-        let weight = match level.get(x as i32, y as i32 - 1, z as i32) {
+        let below = position.neighbour(Direction::Beneath);
+        let weight = match level.get(below) {
             Some(game_object) => {
                 match game_object.entity_type {
                     EntityType::EnemyHead => 99,
@@ -187,7 +230,7 @@ impl PathFinder {
                 }
             }
             _ => {
-                if level.is_inbounds(x as i32, y as i32 - 1, z as i32) {
+                if level.is_inbounds(below) {
                     2 // try to prevent snakes from floating over empty spaces
                 } else {
                     1 // the position must be the bottom of the map so just return 1
@@ -195,377 +238,316 @@ impl PathFinder {
             }
         };
 
-        let mut handle_general_case = || {
-            if level.is_position_enterable(*position) || level.is_position_entity(position) {
-                let under_position = Position {
-                    x: position.x,
-                    y: position.y - 1,
-                    z: position.z,
-                };
-                let position_is_above_ignore =
-                    level.is_position_type(under_position, Some(EntityType::PathfindIgnore));
-                if level.is_position_standable(*position) || position_is_above_ignore {
-                    // up
-                    if level.is_inbounds(position.x + 1, position.y, position.z)
-                        && (level.is_enterable(position.x + 1, position.y, position.z)
-                            || level.is_entity(position.x + 1, position.y, position.z))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x + 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // down
-                    if level.is_inbounds(position.x - 1, position.y, position.z)
-                        && (level.is_enterable(position.x - 1, position.y, position.z)
-                            || level.is_entity(position.x - 1, position.y, position.z))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x - 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // left
-                    if level.is_inbounds(position.x, position.y, position.z - 1)
-                        && (level.is_enterable(position.x, position.y, position.z - 1)
-                            || level.is_entity(position.x, position.y, position.z - 1))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y][z - 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // right
-                    if level.is_inbounds(position.x, position.y, position.z + 1)
-                        && (level.is_enterable(position.x, position.y, position.z + 1)
-                            || level.is_entity(position.x, position.y, position.z + 1))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y][z + 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
+        let is_inbounds = |direction| level.is_inbounds(position.neighbour(direction));
+        let is_enterable = |direction| {
+            let p = position.neighbour(direction);
+            level.is_inbounds(p) && (level.is_enterable(p) || level.is_entity(p))
+        };
+        let is_standable = |direction| {
+            let p = position.neighbour(direction);
+            level.is_standable(p)
+                || level.is_type(p.neighbour(Direction::Beneath), Some(EntityType::PathfindIgnore))
+        };
+        let sides = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
 
-                    // need to add this if enemy or something
-                    // Below
-                    if level.is_inbounds(position.x, position.y - 1, position.z)
-                        && (level.is_type(
-                            position.x,
-                            position.y - 1,
-                            position.z,
-                            Some(EntityType::Enemy),
-                        ) || level.is_type(
-                            position.x,
-                            position.y - 1,
-                            position.z,
-                            Some(EntityType::EnemyHead),
-                        ))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y - 1][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                } else {
-                    let up_is_standable =
-                        level.is_standable(position.x + 1, position.y, position.z)
-                            || level.is_type(
-                                position.x + 1,
-                                position.y - 1,
-                                position.z,
-                                Some(EntityType::PathfindIgnore),
-                            );
-                    let down_is_standable =
-                        level.is_standable(position.x - 1, position.y, position.z)
-                            || level.is_type(
-                                position.x - 1,
-                                position.y - 1,
-                                position.z,
-                                Some(EntityType::PathfindIgnore),
-                            );
-                    let left_is_standable =
-                        level.is_standable(position.x, position.y, position.z - 1)
-                            || level.is_type(
-                                position.x,
-                                position.y - 1,
-                                position.z - 1,
-                                Some(EntityType::PathfindIgnore),
-                            );
-                    let right_is_standable =
-                        level.is_standable(position.x, position.y, position.z + 1)
-                            || level.is_type(
-                                position.x,
-                                position.y - 1,
-                                position.z + 1,
-                                Some(EntityType::PathfindIgnore),
-                            );
-
-                    // Below
-                    if level.is_inbounds(position.x, position.y - 1, position.z)
-                        && (up_is_standable
-                            || down_is_standable
-                            || right_is_standable
-                            || left_is_standable)
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y - 1][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
+        let mut entries = [None; 6];
+        let mut enter_from = |direction| entries[index(direction)] = Some(weight);
 
-                    // need to make connections to up/down/left/right if any of those are standable
-
-                    // up
-                    if level.is_inbounds(position.x + 1, position.y, position.z)
-                        && up_is_standable
-                        && (level.is_enterable(position.x + 1, position.y, position.z)
-                            || level.is_entity(position.x + 1, position.y, position.z))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x + 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // down
-                    if level.is_inbounds(position.x - 1, position.y, position.z)
-                        && down_is_standable
-                        && (level.is_enterable(position.x - 1, position.y, position.z)
-                            || level.is_entity(position.x - 1, position.y, position.z))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x - 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
+        match level.get(*position).map(|object| object.entity_type) {
+            Some(EntityType::Dude) => {
+                for direction in DIRECTIONS {
+                    if is_inbounds(direction) {
+                        enter_from(direction);
                     }
-                    // left
-                    if level.is_inbounds(position.x, position.y, position.z - 1)
-                        && left_is_standable
-                        && (level.is_enterable(position.x, position.y, position.z - 1)
-                            || level.is_entity(position.x, position.y, position.z - 1))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y][z - 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
+                }
+            }
+            Some(EntityType::PathfindIgnore)
+            | Some(EntityType::Block)
+            | Some(EntityType::Enemy)
+            | Some(EntityType::EnemyHead) => (),
+            // food can sit on top of a spot snakes ignore
+            _ if level.is_type(*position, Some(EntityType::PathfindIgnore)) => (),
+            _ => {
+                if level.is_vacant(*position) || level.is_entity(*position) {
+                    let position_is_above_ignore =
+                        level.is_type(below, Some(EntityType::PathfindIgnore));
+                    if level.is_standable(*position) || position_is_above_ignore {
+                        for direction in sides {
+                            if is_enterable(direction) {
+                                enter_from(direction);
+                            }
+                        }
+
+                        // snakes can climb up out of other snakes
+                        if level.is_type(below, Some(EntityType::Enemy))
+                            || level.is_type(below, Some(EntityType::EnemyHead))
+                        {
+                            enter_from(Direction::Beneath);
+                        }
+                    } else {
+                        // need to make connections to up/down/left/right if any of those are standable
+                        if is_inbounds(Direction::Beneath) && sides.into_iter().any(is_standable) {
+                            enter_from(Direction::Beneath);
+                        }
+
+                        for direction in sides {
+                            if is_standable(direction) && is_enterable(direction) {
+                                enter_from(direction);
+                            }
+                        }
                     }
-                    // right
-                    if level.is_inbounds(position.x, position.y, position.z + 1)
-                        && right_is_standable
-                        && (level.is_enterable(position.x, position.y, position.z + 1)
-                            || level.is_entity(position.x, position.y, position.z + 1))
-                    {
-                        self.graph.update_edge(
-                            self.indices[x][y][z + 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
+
+                    // falling in from above
+                    if is_enterable(Direction::Above) {
+                        enter_from(Direction::Above);
                     }
                 }
+            }
+        }
+
+        self.entries[node] = entries;
+    }
 
-                // Above
-                if level.is_enterable(position.x, position.y + 1, position.z)
-                    || level.is_entity(position.x, position.y + 1, position.z)
-                {
-                    self.graph.update_edge(
-                        self.indices[x][y + 1][z],
-                        self.indices[x][y][z],
-                        weight,
-                    );
+    pub fn update_all(&mut self, level: &Level) {
+        for x in 0..level.width() {
+            for y in 0..level.height() {
+                for z in 0..level.length() {
+                    let p = Position {
+                        x: x as i32,
+                        y: y as i32,
+                        z: z as i32,
+                    };
+                    self.update_position_in_graph(&p, level);
                 }
             }
+        }
+
+        self.update_teleporters(level);
+    }
+
+    // what can move into a spot depends on the spot, the spots next to it and
+    // the spots under those, so a change reaches one further out and one up
+    pub fn update_around(&mut self, position: &Position, level: &Level) {
+        let offsets = [
+            (0, 0, 0),
+            (1, 0, 0),
+            (-1, 0, 0),
+            (0, 0, 1),
+            (0, 0, -1),
+            (0, 1, 0),
+            (0, -1, 0),
+            (1, 1, 0),
+            (-1, 1, 0),
+            (0, 1, 1),
+            (0, 1, -1),
+        ];
+
+        for (x, y, z) in offsets {
+            let around = Position {
+                x: position.x + x,
+                y: position.y + y,
+                z: position.z + z,
+            };
+            self.update_position_in_graph(&around, level);
+        }
+    }
+
+    pub fn update_teleporters(&mut self, level: &Level) {
+        self.teleporters = level
+            .get_teleporters()
+            .iter()
+            .filter_map(|teleporter| Some((self.node(&teleporter.position)?, self.node(&teleporter.target)?)))
+            .collect();
+    }
+
+    pub fn is_reachable(&self, from: &Position, to: &Position) -> bool {
+        let (from, to) = match (self.node(from), self.node(to)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return false,
         };
+        let mut visited = vec![false; self.entries.len()];
+        let mut stack = vec![from];
+        visited[from] = true;
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
 
-        if let Some(object) = level.get_with_position(*position) {
-            match object.entity_type {
-                EntityType::Dude => {
-                    // up
-                    if level.is_inbounds(position.x + 1, position.y, position.z) {
-                        self.graph.update_edge(
-                            self.indices[x + 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // down
-                    if level.is_inbounds(position.x - 1, position.y, position.z) {
-                        self.graph.update_edge(
-                            self.indices[x - 1][y][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // left
-                    if level.is_inbounds(position.x, position.y, position.z - 1) {
-                        self.graph.update_edge(
-                            self.indices[x][y][z - 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // right
-                    if level.is_inbounds(position.x, position.y, position.z + 1) {
-                        self.graph.update_edge(
-                            self.indices[x][y][z + 1],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // above
-                    if level.is_inbounds(position.x, position.y + 1, position.z) {
-                        self.graph.update_edge(
-                            self.indices[x][y + 1][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
-                    // below
-                    if level.is_inbounds(position.x, position.y - 1, position.z) {
-                        self.graph.update_edge(
-                            self.indices[x][y - 1][z],
-                            self.indices[x][y][z],
-                            weight,
-                        );
-                    }
+            for (next, _) in self.moves(node) {
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        false
+    }
+
+    // Manhattan distance, or less if a teleporter could make it shorter
+    fn estimate(&self, node: Node, goal: &Position) -> u32 {
+        let position = self.get_position(node);
+        self.teleporters
+            .iter()
+            .map(|(from, to)| {
+                distance(&position, &self.get_position(*from)) + 1 + distance(&self.get_position(*to), goal)
+            })
+            .fold(distance(&position, goal), u32::min)
+    }
+
+    fn astar(&mut self, start: Node, goal: Node, claimed_nodes: &[Node]) -> Option<(u32, Vec<Node>)> {
+        let id = self.search.next_id();
+        for claimed in claimed_nodes {
+            self.search.claimed[*claimed] = id;
+        }
+
+        let goal_position = self.get_position(goal);
+        self.search.cost[start] = 0;
+        self.search.seen[start] = id;
+        self.search.open.push(Reverse((self.estimate(start, &goal_position), start)));
+
+        while let Some(Reverse((_, node))) = self.search.open.pop() {
+            if self.search.closed[node] == id {
+                continue;
+            }
+            self.search.closed[node] = id;
+
+            if node == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while current != start {
+                    current = self.search.came_from[current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some((self.search.cost[goal], path));
+            }
+
+            let mut moves = std::mem::take(&mut self.search.moves);
+            moves.clear();
+            moves.extend(self.moves(node));
+            for (next, weight) in moves.iter().copied() {
+                let mut cost = self.search.cost[node] + weight;
+                if self.search.claimed[next] == id {
+                    cost += CLAIMED_COST;
+                }
+
+                if self.search.seen[next] != id || cost < self.search.cost[next] {
+                    self.search.seen[next] = id;
+                    self.search.cost[next] = cost;
+                    self.search.came_from[next] = node;
+                    let estimate = cost + self.estimate(next, &goal_position);
+                    self.search.open.push(Reverse((estimate, next)));
                 }
-                EntityType::PathfindIgnore
-                | EntityType::Block
-                | EntityType::Enemy
-                | EntityType::EnemyHead => (),
-                _ => handle_general_case(),
             }
-        } else {
-            handle_general_case()
+            self.search.moves = moves;
         }
+
+        None
     }
 
     pub fn update_path(
         &mut self,
-        claimed_nodes: &Vec<NodeIndex<u32>>,
+        claimed_nodes: &[Node],
         requesting_entity: Entity, // probably a snake
-        level: &Res<Level>,
         start: &Position,
         goal: &Position,
-        kill_snake_event_writer: &mut EventWriter<snake::KillSnakeEvent>,
-    ) -> Option<(u32, Vec<NodeIndex<u32>>)> {
-        let start_index = self.indices[start.x as usize][start.y as usize][start.z as usize];
-        let goal_index = self.indices[goal.x as usize][goal.y as usize][goal.z as usize];
-        let mut path = astar(
-            &self.graph,
-            start_index,
-            |finish| finish == goal_index,
-            |e| *e.weight(),
-            |n| {
-                if claimed_nodes.iter().any(|claimed| *claimed == n) {
-                    99
-                } else {
-                    0
+    ) -> Option<(u32, Vec<Node>)> {
+        let start_index = self.node(start)?;
+        let goal_index = self.node(goal)?;
+
+        // keep going on the last path if it's to the same place and still works
+        if let Some((cached_goal, cached_path)) = self.paths.get(&requesting_entity) {
+            if *cached_goal == goal_index {
+                if let Some(at) = cached_path.iter().position(|node| *node == start_index) {
+                    let rest = &cached_path[at..];
+                    // another snake may have claimed part of it since
+                    let is_claimed = rest[1..].iter().any(|node| claimed_nodes.contains(node));
+                    if let (Some(cost), false) = (self.path_cost(rest), is_claimed) {
+                        return Some((cost, rest.to_vec()));
+                    }
                 }
-            },
-        );
-
-        // just move one spot if possible
-        if path.is_none() {
-            let mut new_spot = start.clone();
-            if level.is_enterable(start.x, start.y + 1, start.z) 
-                && !level.is_type(start.x, start.y + 1, start.z, Some(EntityType::PathfindIgnore)) {
-                // above
-                new_spot = Position { x: start.x, y: start.y + 1, z: start.z };
-            } else if level.is_enterable(start.x, start.y - 1, start.z) 
-                && !level.is_type(start.x, start.y - 1, start.z, Some(EntityType::PathfindIgnore)) {
-                // below
-                new_spot = Position { x: start.x, y: start.y - 1, z: start.z };
-            } else if level.is_enterable(start.x + 1, start.y, start.z) 
-                && !level.is_type(start.x + 1, start.y, start.z, Some(EntityType::PathfindIgnore)) {
-                // front
-                new_spot = Position { x: start.x + 1, y: start.y, z: start.z };
-            } else if level.is_enterable(start.x - 1, start.y, start.z) 
-                && !level.is_type(start.x - 1, start.y, start.z, Some(EntityType::PathfindIgnore)) {
-                // back
-                new_spot = Position { x: start.x - 1, y: start.y, z: start.z };
-            } else if level.is_enterable(start.x, start.y, start.z + 1) 
-                && !level.is_type(start.x, start.y, start.z + 1, Some(EntityType::PathfindIgnore)) {
-                // right
-                new_spot = Position { x: start.x, y: start.y, z: start.z + 1 };
-            } else if level.is_enterable(start.x, start.y, start.z - 1) 
-                && !level.is_type(start.x, start.y, start.z - 1, Some(EntityType::PathfindIgnore)) {
-                // left
-                new_spot = Position { x: start.x, y: start.y, z: start.z - 1 };
-            } else {
-                return None;
             }
-
-            let goal_index = self.indices[new_spot.x as usize][new_spot.y as usize][new_spot.z as usize];
-            path = astar(
-                &self.graph,
-                start_index,
-                |finish| finish == goal_index,
-                |e| *e.weight(),
-                |n| {
-                    if claimed_nodes.iter().any(|claimed| *claimed == n) {
-                        99
-                    } else {
-                        0
-                    }
-                },
-            );
         }
 
+        let path = self.astar(start_index, goal_index, claimed_nodes);
+        self.remember_path(requesting_entity, &path);
         path
     }
 
-    //  pub fn get_weight(&self, position: &Position) -> Position {
-    //      let weight =
-    //          *self.graph.node_weight(self.indices[position.x as usize][position.z as usize])
-    //                     .expect("Node doesn't exist");
-    //      Position { x: weight.0, y: weight.1, z: weight.2 }
-    //  }
+    // for when none of a snake's goals can be reached, just move one spot if possible
+    pub fn step_anywhere(
+        &mut self,
+        claimed_nodes: &[Node],
+        requesting_entity: Entity,
+        level: &Level,
+        start: &Position,
+    ) -> Option<(u32, Vec<Node>)> {
+        let start_index = self.node(start)?;
+        let path = [
+            Direction::Above,
+            Direction::Beneath,
+            Direction::Up,
+            Direction::Down,
+            Direction::Right,
+            Direction::Left,
+        ]
+        .into_iter()
+        .map(|direction| start.neighbour(direction))
+        .filter(|spot| {
+            level.is_enterable(*spot) && !level.is_type(*spot, Some(EntityType::PathfindIgnore))
+        })
+        // empty air above isn't somewhere to go, so take the first spot with a path
+        .find_map(|new_spot| self.astar(start_index, self.node(&new_spot)?, claimed_nodes));
+
+        self.remember_path(requesting_entity, &path);
+        path
+    }
 
-    pub fn get_position(&self, index: NodeIndex<u32>) -> Position {
-        let weight = *self.graph.node_weight(index).expect("Node doesn't exist");
-        Position {
-            x: weight.0,
-            y: weight.1,
-            z: weight.2,
+    // tries each goal in order and takes the first one there's a path to
+    pub fn path_to_any(
+        &mut self,
+        claimed_nodes: &[Node],
+        requesting_entity: Entity,
+        level: &Level,
+        start: &Position,
+        goals: impl IntoIterator<Item = Position>,
+    ) -> Option<(u32, Vec<Node>)> {
+        for goal in goals {
+            if !level.is_inbounds(goal) {
+                continue;
+            }
+
+            let path = self.update_path(claimed_nodes, requesting_entity, start, &goal);
+            if path.is_some() {
+                return path;
+            }
+        }
+
+        self.step_anywhere(claimed_nodes, requesting_entity, level, start)
+    }
+
+    fn remember_path(&mut self, requesting_entity: Entity, path: &Option<(u32, Vec<Node>)>) {
+        match path {
+            Some((_, nodes)) => {
+                let goal = *nodes.last().unwrap();
+                self.paths.insert(requesting_entity, (goal, nodes.clone()));
+            }
+            None => {
+                self.paths.remove(&requesting_entity);
+            }
         }
     }
 
+    // drops the paths of snakes that aren't pathing anymore
+    pub fn retain_paths(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        self.paths.retain(|entity, _| keep(*entity));
+    }
+
     pub fn get_edges(&self) -> Vec<(Position, Position)> {
-        self.graph
-            .node_indices()
-            .flat_map(|n| {
-                self.graph
-                    .neighbors_directed(n, petgraph::Direction::Outgoing)
-                    .map(move |neighbor| (n, neighbor))
-            })
-            .map(|(node, neighbor)| {
-                (
-                    self.graph.node_weight(node).unwrap(),
-                    self.graph.node_weight(neighbor).unwrap(),
-                )
-            })
-            .map(|(node, neighbor)| {
-                (
-                    Position {
-                        x: node.0,
-                        y: node.1,
-                        z: node.2,
-                    },
-                    Position {
-                        x: neighbor.0,
-                        y: neighbor.1,
-                        z: neighbor.2,
-                    },
-                )
-            })
+        (0..self.entries.len())
+            .flat_map(|node| self.moves(node).map(move |(next, _)| (node, next)))
+            .map(|(node, next)| (self.get_position(node), self.get_position(next)))
             .collect()
     }
 }
@@ -591,8 +573,8 @@ pub fn show_path(
                 for x in 0..level.width() {
                     for y in 0..level.height() {
                         for z in 0..level.length() {
-                            let current = path_find.indices[x][y][z];
-                            if path.1.contains(&current) {
+                            let current = path_find.node(&Position { x: x as i32, y: y as i32, z: z as i32 });
+                            if current.map_or(false, |current| path.1.contains(&current)) {
                                 let start = Vec3::new(0.1 + x as f32, y as f32, z as f32);
                                 let end = Vec3::new(x as f32, 1.0 + y as f32, z as f32);
                                 let thickness = 0.01;
@@ -614,22 +596,17 @@ pub fn show_path(
 pub fn update_graph(
     mut changes: EventReader<PositionChangeEvent>,
     mut path_finder: ResMut<PathFinder>,
-    mut initial_iteration_completed: Local<bool>,
     level: Res<Level>,
 ) {
-    /*
-        if !*initial_iteration_completed || changes.iter().count() > 0 {
-            *initial_iteration_completed = true;
-            for x in 0..level.width {
-                for y in 0..level.height{
-                    for z in 0..level.length {
-                        let p = Position { x: x as i32, y: y as i32, z: z as i32 };
-                        path_finder.update_position_in_graph(&p, &level);
-                    }
-                }
-            }
-        }
-    */
+    let mut changed = false;
+    for PositionChangeEvent(position, _) in changes.iter() {
+        path_finder.update_around(position, &level);
+        changed = true;
+    }
+
+    if changed {
+        path_finder.update_teleporters(&level);
+    }
 }
 
 /*
@@ -674,13 +651,25 @@ pub fn draw_edges(
 pub fn update_path(
     mut time: Local<f32>,
     level: Res<Level>,
-    timer: Res<Time>,
+    timer: Res<GameTime>,
     mut path_find: ResMut<PathFinder>,
-    mut snake: Query<(Entity, &mut snake::Enemy, &Position, &Transform), Without<Dude>>,
-    dude: Query<(&Transform, &Position), (With<Dude>, Without<snake::Enemy>)>,
+    mut snake: Query<
+        (
+            Entity,
+            &mut snake::Enemy,
+            &Position,
+            &Transform,
+            &mut Brain,
+            Option<&PlayerSnake>,
+        ),
+        Without<Dude>,
+    >,
+    dude: Query<(&Transform, &Position, &Facing), (With<Dude>, Without<snake::Enemy>)>,
     food: Query<(&Position, &Transform), With<Food>>,
+    flag: Query<&Position, With<WinFlag>>,
     mut kill_snake_event_writer: EventWriter<snake::KillSnakeEvent>,
     level_ready: Res<LevelReady>,
+    mut rng: ResMut<GameRng>,
 ) {
     if !level_ready.0 || dude.iter().count() == 0 {
         return;
@@ -688,52 +677,61 @@ pub fn update_path(
     *time += timer.delta_seconds();
 
     if *time > 0.0 {
-        for x in 0..level.width() {
-            for y in 0..level.height() {
-                for z in 0..level.length() {
-                    let p = Position {
-                        x: x as i32,
-                        y: y as i32,
-                        z: z as i32,
-                    };
-                    path_find.update_position_in_graph(&p, &level);
-                }
-            }
-        }
-
-        for teleporter in level.get_teleporters() {
-            let position = path_find.indices[teleporter.position.x as usize]
-                [teleporter.position.y as usize][teleporter.position.z as usize];
-            let target = path_find.indices[teleporter.target.x as usize]
-                [teleporter.target.y as usize][teleporter.target.z as usize];
-            path_find.graph.update_edge(position, target, 1);
-        }
-
-        let snake_speed = level.snake_speed();
-        let (seek_food, seek_dude, mut seek_random) = match level.snake_target() {
-            snake::SnakeTarget::Normal => (true, true, true),
-            snake::SnakeTarget::OnlyFood => (true, false, false),
-            snake::SnakeTarget::OnlyRandom => (false, false, true),
-            snake::SnakeTarget::OnlyDude => (false, true, false),
-        };
+        // dead and despawned snakes would otherwise keep their paths until the next level
+        path_find.retain_paths(|entity| {
+            snake
+                .get(entity)
+                .map_or(false, |(_, snake, _, _, _, player_snake)| {
+                    !snake.is_dead && player_snake.is_none()
+                })
+        });
+
+        let dude = dude.get_single().ok().map(|(transform, position, facing)| {
+            let seen = Seen {
+                position: *position,
+                translation: transform.translation,
+            };
+            (seen, facing.direction)
+        });
+        let foods: Vec<Seen> = food
+            .iter()
+            .map(|(position, transform)| Seen {
+                position: *position,
+                translation: transform.translation,
+            })
+            .collect();
+        let snakes: Vec<SeenSnake> = snake
+            .iter()
+            .map(|(entity, snake, position, _, _, _)| SeenSnake {
+                entity,
+                head: *position,
+                body: snake
+                    .body_positions
+                    .iter()
+                    .map(|body| Position::from_vec(body.translation))
+                    .collect(),
+            })
+            .collect();
+        let flag = flag.iter().next().copied();
 
         let mut claimed_nodes = vec![];
         let mut claimed_targets = vec![];
-        for (entity, mut snake, snake_position, snake_transform) in snake.iter_mut() {
+        for (entity, mut snake, snake_position, snake_transform, mut brain, player_snake) in snake.iter_mut() {
             // try to stop snakes from moving backward
             snake.body_positions.iter().for_each(|body_position| {
                 let body = Position::from_vec(body_position.translation);
-                claimed_nodes
-                    .push(path_find.indices[body.x as usize][body.y as usize][body.z as usize]);
+                claimed_nodes.extend(path_find.node(&body));
             });
 
+            // the player steers this one
+            if player_snake.is_some() {
+                continue;
+            }
+
             if !snake.is_dead {
                 if let Some((_, current_path)) = &snake.current_path {
-                    let is_still_valid = current_path
-                        .iter()
-                        .skip(1)
-                        .zip(current_path.iter().skip(2))
-                        .all(|(current, next)| path_find.graph.contains_edge(*current, *next));
+                    let is_still_valid = current_path.len() < 2
+                        || path_find.path_cost(&current_path[1..]).is_some();
                     let path_contains_snake_position = current_path
                         .iter()
                         .any(|p| path_find.get_position(*p) == *snake_position);
@@ -749,87 +747,34 @@ pub fn update_path(
                     //                  println!("Path no longer valid {} {} {}", is_still_valid, current_path.len(), path_contains_snake_position);
                 }
                 snake.current_path = None;
-                snake.speed = snake_speed;
-
-                if seek_dude {
-                    if let Ok((dude_transform, dude_position)) = dude.get_single() {
-                        if claimed_targets
-                            .iter()
-                            .any(|x| path_find.get_position(*x) == *dude_position)
-                        {
-                            // dude is claimed by another snake, so just seek random for a step
-                            seek_random = true;
-                        }
-
-                        if (!seek_food && !seek_random)
-                            || dude_transform
-                                .translation
-                                .distance(snake_transform.translation)
-                                <= 1.5
-                        {
-                            // snake.speed -= 0.1; // maybe it's better to leave this commented?
-                            snake.current_path = path_find.update_path(
-                                &claimed_nodes,
-                                entity,
-                                &level,
-                                snake_position,
-                                dude_position,
-                                &mut kill_snake_event_writer,
-                            );
-                        }
-                    }
-                }
-
-                if seek_food && snake.current_path.is_none() {
-                    let mut closest_food: Option<(&Position, &Transform)> = None;
-                    for (food_position, food_transform) in food.iter() {
-                        if !claimed_targets
-                            .iter()
-                            .any(|x| path_find.get_position(*x) == *food_position)
-                        {
-                            if closest_food.is_none()
-                                || closest_food
-                                    .unwrap()
-                                    .1
-                                    .translation
-                                    .distance(snake_transform.translation)
-                                    > food_transform
-                                        .translation
-                                        .distance(snake_transform.translation)
-                            {
-                                closest_food = Some((food_position, food_transform));
-                            }
-                        } else {
-                            // go to next food but potentially seek random to fallback on if no food
-                            // is available
-                            seek_random = true;
-                        }
-                    }
-
-                    if let Some((food_position, _food_transform)) = closest_food {
-                        snake.current_path = path_find.update_path(
-                            &claimed_nodes,
-                            entity,
-                            &level,
-                            snake_position,
-                            food_position,
-                            &mut kill_snake_event_writer,
-                        );
-                    }
-                }
+                snake.speed = snake.base_speed;
+
+                let claimed: Vec<Position> = claimed_targets
+                    .iter()
+                    .map(|node| path_find.get_position(*node))
+                    .collect();
+                let senses = Senses {
+                    level: &level,
+                    entity,
+                    snake: Seen {
+                        position: *snake_position,
+                        translation: snake_transform.translation,
+                    },
+                    dude: dude.map(|(seen, _)| seen),
+                    dude_facing: dude.map(|(_, facing)| facing),
+                    foods: &foods,
+                    flag,
+                    snakes: &snakes,
+                    claimed: &claimed,
+                };
 
-                if seek_random && snake.current_path.is_none() {
-                    let random_goal = level.get_random_standable(&None, true);
-
-                    snake.current_path = path_find.update_path(
-                        &claimed_nodes,
-                        entity,
-                        &level,
-                        snake_position,
-                        &random_goal,
-                        &mut kill_snake_event_writer,
-                    );
-                }
+                // goals are only picked as they're needed so Anywhere doesn't roll for nothing
+                let goals = brain.0.goals(&senses).into_iter().map(|goal| match goal {
+                    Goal::At(position) => position,
+                    Goal::Anywhere => level.get_random_standable(&None, true, &mut *rng),
+                });
+                snake.current_path =
+                    path_find.path_to_any(&claimed_nodes, entity, &level, snake_position, goals);
 
                 // TODO: might be better to just check if the snake can move in at least
                 //       one unit in a "forward" direction?
@@ -844,7 +789,6 @@ pub fn update_path(
 //                          &level,
 //                          snake_position,
 //                          &position,
-//                          &mut kill_snake_event_writer,
 //                      );
 //                  }
 
@@ -872,3 +816,144 @@ pub fn update_path(
         *time = 0.0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::tests::level_info;
+
+    // three rows of five on a floor
+    fn floor() -> (PathFinder, Level) {
+        let level = Level::from_level_info(level_info(
+            "",
+            &[&[".....", ".....", "....."], &["#####", "#####", "#####"]],
+        ));
+        let mut path_find = PathFinder::new();
+        path_find.load_level(&level);
+        path_find.update_all(&level);
+        (path_find, level)
+    }
+
+    fn at(x: i32, z: i32) -> Position {
+        Position { x, y: 1, z }
+    }
+
+    #[test]
+    fn straight_across_the_floor() {
+        let (mut path_find, _) = floor();
+        let (start, goal) = (path_find.node(&at(0, 0)).unwrap(), path_find.node(&at(0, 4)).unwrap());
+        let (cost, path) = path_find.astar(start, goal, &[]).unwrap();
+
+        assert_eq!(cost, 4);
+        assert_eq!(path_find.path_cost(&path), Some(cost));
+        let positions: Vec<Position> = path.iter().map(|node| path_find.get_position(*node)).collect();
+        assert_eq!(positions, (0..5).map(|z| at(0, z)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn goes_around_claimed_spots() {
+        let (mut path_find, _) = floor();
+        let claimed = path_find.node(&at(0, 2)).unwrap();
+        let (start, goal) = (path_find.node(&at(0, 0)).unwrap(), path_find.node(&at(0, 4)).unwrap());
+        let (cost, path) = path_find.astar(start, goal, &[claimed]).unwrap();
+
+        assert_eq!(cost, 6);
+        assert!(!path.contains(&claimed));
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        for step in path.windows(2) {
+            let (a, b) = (path_find.get_position(step[0]), path_find.get_position(step[1]));
+            assert_eq!(distance(&a, &b), 1);
+        }
+    }
+
+    #[test]
+    fn blocks_cant_be_reached() {
+        let (mut path_find, _) = floor();
+        let block = Position { x: 0, y: 0, z: 4 };
+
+        let (start, goal) = (path_find.node(&at(0, 0)).unwrap(), path_find.node(&block).unwrap());
+        assert!(path_find.astar(start, goal, &[]).is_none());
+        assert!(!path_find.can_move(&at(0, 4), &block));
+        assert!(path_find.can_move(&at(0, 3), &at(0, 4)));
+    }
+
+    #[test]
+    fn cached_paths_are_picked_up_partway() {
+        let (mut path_find, _) = floor();
+        let snake = Entity::from_raw(1);
+        let (_, path) = path_find.update_path(&[], snake, &at(0, 0), &at(2, 4)).unwrap();
+
+        // one step along, the rest of the same path comes back
+        let next = path_find.get_position(path[1]);
+        let (cost, rest) = path_find.update_path(&[], snake, &next, &at(2, 4)).unwrap();
+        assert_eq!(rest, path[1..].to_vec());
+        assert_eq!(cost, path.len() as u32 - 2);
+
+        path_find.retain_paths(|_| false);
+        assert!(path_find.paths.is_empty());
+    }
+
+    #[test]
+    fn cached_paths_give_way_to_claimed_spots() {
+        let (mut path_find, _) = floor();
+        let snake = Entity::from_raw(1);
+        let (_, path) = path_find.update_path(&[], snake, &at(0, 0), &at(0, 4)).unwrap();
+
+        let claimed = path[2];
+        let (cost, rest) = path_find.update_path(&[claimed], snake, &at(0, 0), &at(0, 4)).unwrap();
+        assert!(!rest.contains(&claimed));
+        assert_eq!(cost, 6);
+    }
+
+    #[test]
+    fn spots_outside_the_level_have_no_node() {
+        let (mut path_find, _) = floor();
+        let snake = Entity::from_raw(1);
+        let outside = Position { x: 3, y: 1, z: 0 };
+
+        assert!(path_find.node(&outside).is_none());
+        assert!(path_find.node(&Position { x: -1, y: 1, z: 0 }).is_none());
+        assert!(path_find.update_path(&[], snake, &at(0, 0), &outside).is_none());
+        assert!(path_find.update_path(&[], snake, &outside, &at(0, 0)).is_none());
+        assert!(!path_find.is_reachable(&at(0, 0), &outside));
+    }
+
+    // the far corner is boxed in by blocks on every side and above
+    fn walled_corner() -> (PathFinder, Level) {
+        let level = Level::from_level_info(level_info(
+            "",
+            &[
+                &[".....", ".....", "....#"],
+                &[".....", "....#", "...#."],
+                &["#####", "#####", "#####"],
+            ],
+        ));
+        let mut path_find = PathFinder::new();
+        path_find.load_level(&level);
+        path_find.update_all(&level);
+        (path_find, level)
+    }
+
+    #[test]
+    fn unreachable_goals_are_skipped() {
+        let (mut path_find, level) = walled_corner();
+        let snake = Entity::from_raw(1);
+        assert!(path_find.update_path(&[], snake, &at(0, 0), &at(0, 4)).is_none());
+
+        let goals = vec![at(0, 4), at(2, 0)];
+        let (_, path) = path_find.path_to_any(&[], snake, &level, &at(0, 0), goals).unwrap();
+        assert_eq!(path_find.get_position(*path.last().unwrap()), at(2, 0));
+    }
+
+    #[test]
+    fn steps_anywhere_when_nothing_can_be_reached() {
+        let (mut path_find, level) = walled_corner();
+        let snake = Entity::from_raw(1);
+        let goals = vec![at(0, 4)];
+        let (cost, path) = path_find.path_to_any(&[], snake, &level, &at(0, 0), goals).unwrap();
+
+        assert_eq!(cost, 1);
+        assert_eq!(path.len(), 2);
+    }
+}
@@ -0,0 +1,349 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    asset_loading, dude, dude::PlayerAction, game_rng::GameRng, game_time, level::Level, AppState,
+};
+
+// Records what the player did on every fixed tick so a run can be played back
+// exactly. Start the game with `--record run.ron` to write run.0.ron, run.1.ron..
+// for every attempt at a level, or `--replay run.3.ron` to jump straight into
+// the recorded level and play it back instead of taking live input. Quitting
+// partway through a level still saves what was recorded of that attempt.
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayMode>()
+            .add_startup_system(setup_replay)
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(
+                    start_level
+                        .after("load_levels_from_asset")
+                        .before("loading_level"),
+                ),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(finish_level))
+            // after everything that could have asked to quit this frame
+            .add_system_to_stage(CoreStage::Last, save_on_exit)
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        replay_input
+                            .label("replay_input")
+                            .before(dude::player_input),
+                    )
+                    .with_system(
+                        record_input
+                            .after("replay_input")
+                            .before(dude::player_input),
+                    ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TickInput {
+    pub pressed: Vec<PlayerAction>,
+    pub presses: Vec<PlayerAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub level: usize,
+    pub seed: u64,
+    // consecutive identical ticks are stored once with a count
+    pub inputs: Vec<(usize, TickInput)>,
+}
+
+impl Recording {
+    pub fn new(level: usize, seed: u64) -> Self {
+        Recording {
+            level,
+            seed,
+            inputs: vec![],
+        }
+    }
+
+    pub fn push(&mut self, input: TickInput) {
+        match self.inputs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.inputs.push((1, input)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.iter().map(|(count, _)| count).sum()
+    }
+
+    pub fn get(&self, tick: usize) -> Option<&TickInput> {
+        let mut start = 0;
+        for (count, input) in self.inputs.iter() {
+            if tick < start + count {
+                return Some(input);
+            }
+            start += count;
+        }
+
+        None
+    }
+
+    // the input for the tick at the cursor, moving the cursor on to the next one
+    pub fn next(&self, cursor: &mut (usize, usize)) -> Option<&TickInput> {
+        let (index, offset) = cursor;
+        let (count, input) = self.inputs.get(*index)?;
+        *offset += 1;
+        if *offset == *count {
+            *index += 1;
+            *offset = 0;
+        }
+
+        Some(input)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let bytes = std::fs::read(path)?;
+        Ok(ron::de::from_bytes::<Recording>(&bytes)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+pub enum ReplayMode {
+    Off,
+    Recording {
+        path: PathBuf,
+        attempt: usize,
+        recording: Option<Recording>,
+    },
+    Replaying {
+        recording: Recording,
+        // which run of inputs is playing and how many of its ticks are done
+        cursor: (usize, usize),
+    },
+}
+
+impl Default for ReplayMode {
+    fn default() -> Self {
+        ReplayMode::Off
+    }
+}
+
+impl ReplayMode {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        for (i, arg) in args.iter().enumerate() {
+            match (arg.as_str(), args.get(i + 1)) {
+                ("--record", Some(path)) => {
+                    return ReplayMode::Recording {
+                        path: PathBuf::from(path),
+                        attempt: 0,
+                        recording: None,
+                    };
+                }
+                ("--replay", Some(path)) => match Recording::load(Path::new(path)) {
+                    Ok(recording) => return ReplayMode::replay(recording),
+                    Err(e) => println!("Couldn't load replay {}: {}", path, e),
+                },
+                _ => (),
+            }
+        }
+
+        ReplayMode::Off
+    }
+
+    pub fn replay(recording: Recording) -> Self {
+        ReplayMode::Replaying {
+            recording,
+            cursor: (0, 0),
+        }
+    }
+
+    // writes out the attempt in progress, if there is one
+    pub fn save_recording(&mut self) {
+        if let ReplayMode::Recording {
+            path,
+            attempt,
+            recording,
+        } = self
+        {
+            if let Some(recording) = recording.take() {
+                let path = path.with_extension(format!("{}.ron", attempt));
+                match recording.save(&path) {
+                    Ok(()) => println!("Saved recording to {:?}", path),
+                    Err(e) => println!("Couldn't save recording to {:?}: {}", path, e),
+                }
+                *attempt += 1;
+            }
+        }
+    }
+}
+
+fn setup_replay(
+    replay_mode: Res<ReplayMode>,
+    mut level: ResMut<Level>,
+    mut next_state: ResMut<asset_loading::NextState>,
+) {
+    if let ReplayMode::Replaying { recording, .. } = &*replay_mode {
+        println!("Replaying level {} with seed {}", recording.level, recording.seed);
+        level.current_level = recording.level;
+        next_state.state = AppState::InGame;
+    }
+}
+
+fn start_level(mut replay_mode: ResMut<ReplayMode>, level: Res<Level>, mut rng: ResMut<GameRng>) {
+    match &mut *replay_mode {
+        ReplayMode::Recording { recording, .. } => {
+            let seed = rng.gen();
+            rng.reseed(seed);
+            *recording = Some(Recording::new(level.current_level, seed));
+        }
+        ReplayMode::Replaying { recording, cursor } => {
+            rng.reseed(recording.seed);
+            *cursor = (0, 0);
+        }
+        ReplayMode::Off => (),
+    }
+}
+
+fn finish_level(mut replay_mode: ResMut<ReplayMode>) {
+    match &mut *replay_mode {
+        ReplayMode::Recording { .. } => replay_mode.save_recording(),
+        ReplayMode::Replaying { .. } => {
+            println!("Replay finished");
+            *replay_mode = ReplayMode::Off;
+        }
+        ReplayMode::Off => (),
+    }
+}
+
+fn save_on_exit(mut exits: EventReader<AppExit>, mut replay_mode: ResMut<ReplayMode>) {
+    if exits.iter().count() > 0 {
+        replay_mode.save_recording();
+    }
+}
+
+pub fn record_input(
+    mut replay_mode: ResMut<ReplayMode>,
+    action_state: Query<&ActionState<PlayerAction>, With<dude::Dude>>,
+    buffered_presses: Res<dude::BufferedPresses>,
+) {
+    if let ReplayMode::Recording {
+        recording: Some(recording),
+        ..
+    } = &mut *replay_mode
+    {
+        let mut input = TickInput {
+            pressed: vec![],
+            presses: buffered_presses.0.clone(),
+        };
+        if let Ok(action_state) = action_state.get_single() {
+            for action in PlayerAction::DIRECTIONS {
+                if action_state.pressed(action) {
+                    input.pressed.push(action);
+                }
+            }
+        }
+
+        recording.push(input);
+    }
+}
+
+pub fn replay_input(
+    mut replay_mode: ResMut<ReplayMode>,
+    mut action_state: Query<&mut ActionState<PlayerAction>, With<dude::Dude>>,
+    mut buffered_presses: ResMut<dude::BufferedPresses>,
+) {
+    if let ReplayMode::Replaying { recording, cursor } = &mut *replay_mode {
+        let input = recording.next(cursor).cloned().unwrap_or_default();
+
+        if let Ok(mut action_state) = action_state.get_single_mut() {
+            for action in PlayerAction::DIRECTIONS {
+                if input.pressed.contains(&action) {
+                    action_state.press(action);
+                } else {
+                    action_state.release(action);
+                }
+            }
+        }
+        buffered_presses.0 = input.presses;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(pressed: &[PlayerAction]) -> TickInput {
+        TickInput {
+            pressed: pressed.to_vec(),
+            presses: vec![],
+        }
+    }
+
+    #[test]
+    fn plays_back_what_was_recorded() {
+        let ticks = vec![
+            input(&[]),
+            input(&[]),
+            input(&[PlayerAction::Up]),
+            input(&[PlayerAction::Up, PlayerAction::ActionDown]),
+            input(&[]),
+        ];
+        let mut recording = Recording::new(3, 99);
+        for tick in ticks.iter() {
+            recording.push(tick.clone());
+        }
+
+        assert_eq!(recording.len(), ticks.len());
+        for (i, tick) in ticks.iter().enumerate() {
+            assert_eq!(recording.get(i), Some(tick));
+        }
+        assert_eq!(recording.get(ticks.len()), None);
+    }
+
+    #[test]
+    fn cursor_steps_through_every_tick() {
+        let mut recording = Recording::new(1, 0);
+        for pressed in [&[][..], &[], &[PlayerAction::Up], &[], &[], &[]] {
+            recording.push(input(pressed));
+        }
+
+        let mut cursor = (0, 0);
+        for tick in 0..recording.len() {
+            assert_eq!(recording.next(&mut cursor), recording.get(tick));
+        }
+        assert_eq!(recording.next(&mut cursor), None);
+        assert_eq!(recording.next(&mut cursor), None);
+    }
+
+    #[test]
+    fn repeated_ticks_are_stored_once() {
+        let mut recording = Recording::new(1, 0);
+        for _ in 0..100 {
+            recording.push(input(&[PlayerAction::Left]));
+        }
+        recording.push(input(&[]));
+
+        assert_eq!(recording.inputs.len(), 2);
+        assert_eq!(recording.len(), 101);
+    }
+
+    #[test]
+    fn survives_being_written_out() {
+        let mut recording = Recording::new(2, 12345);
+        recording.push(input(&[PlayerAction::Down]));
+        recording.push(input(&[]));
+
+        let text = ron::ser::to_string_pretty(&recording, ron::ser::PrettyConfig::default()).unwrap();
+        assert_eq!(ron::de::from_str::<Recording>(&text).unwrap(), recording);
+    }
+}
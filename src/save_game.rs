@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::Level, level_over::LevelOverEvent, level_pack::LevelPacks, score::Score,
+    snake_control::SnakeMode, versus::Versus, AppState,
+};
+
+// Progress through the shipped levels. Natively it's a file in the user data
+// directory, the web build keeps it in the browser's localStorage. Levels are
+// recorded by index, so it stays good as long as levels only get added on the
+// end of levels.custom.
+pub struct SaveGamePlugin;
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveGame::load()).add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(record_level_over),
+        );
+    }
+}
+
+const SAVE_FILE: &str = "save.ron";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    // the furthest level that's been reached, 0 before any have been beaten
+    #[serde(default)]
+    pub unlocked_level: usize,
+    #[serde(default)]
+    pub levels: BTreeMap<usize, LevelRecord>,
+    // splits of the fastest full run, see speedrun
+    #[serde(default)]
+    pub best_run: Vec<f32>,
+}
+
+// the best of every time the level was beaten, each one on its own
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    #[serde(default)]
+    pub best_food: usize,
+    #[serde(default)]
+    pub best_bonus: usize,
+    #[serde(default)]
+    pub fewest_deaths: Option<usize>,
+    // seconds
+    #[serde(default)]
+    pub best_time: Option<f32>,
+}
+
+impl SaveGame {
+    // a missing or unreadable save is the same as a new game
+    pub fn load() -> Self {
+        storage::read(SAVE_FILE)
+            .and_then(|text| ron::de::from_str::<SaveGame>(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        storage::write(SAVE_FILE, &text)
+    }
+
+    // level 0 is the title screen, the first real level is always open
+    pub fn is_unlocked(&self, level: usize) -> bool {
+        level > 0 && level <= self.unlocked_level.max(1)
+    }
+
+    pub fn has_progress(&self) -> bool {
+        self.unlocked_level > 1
+    }
+
+    pub fn record(&mut self, level: usize, score: &Score, is_last_level: bool) {
+        let record = self.levels.entry(level).or_default();
+        record.best_food = record.best_food.max(score.current_level);
+        record.best_bonus = record.best_bonus.max(score.current_level_bonus);
+        record.fewest_deaths = Some(
+            record
+                .fewest_deaths
+                .map_or(score.current_death_count, |deaths| deaths.min(score.current_death_count)),
+        );
+        record.best_time = Some(
+            record
+                .best_time
+                .map_or(score.current_level_time, |time| time.min(score.current_level_time)),
+        );
+
+        if !is_last_level {
+            self.unlocked_level = self.unlocked_level.max(level + 1);
+        }
+    }
+}
+
+pub fn record_level_over(
+    mut level_over_events: EventReader<LevelOverEvent>,
+    level: Res<Level>,
+    score: Res<Score>,
+    level_packs: Res<LevelPacks>,
+    snake_mode: Res<SnakeMode>,
+    versus: Res<Versus>,
+    mut save_game: ResMut<SaveGame>,
+) {
+    if level_over_events.iter().count() == 0 {
+        return;
+    }
+
+    // endless and classic count as packs, they swap in levels of their own
+    if level_packs.selected.is_some() || snake_mode.active || versus.active {
+        return;
+    }
+
+    save_game.record(level.current_level, &score, level.is_last_level());
+    if let Err(e) = save_game.save() {
+        println!("Couldn't save the game: {}", e);
+    }
+}
+
+// files by name, in the user data directory or under a localStorage key
+#[cfg(not(target_arch = "wasm32"))]
+pub mod storage {
+    use crate::user_data;
+    use std::path::PathBuf;
+
+    fn path(name: &str) -> Option<PathBuf> {
+        user_data::directory().map(|directory| directory.join(name))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(path(name)?).ok()
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), anyhow::Error> {
+        let path = path(name).ok_or_else(|| anyhow::anyhow!("there's no user data directory"))?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod storage {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("not_snake_{}", name)
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), anyhow::Error> {
+        local_storage()
+            .ok_or_else(|| anyhow::anyhow!("there's no localStorage"))?
+            .set_item(&key(name), text)
+            .map_err(|_| anyhow::anyhow!("couldn't write to localStorage"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(food: usize, bonus: usize, deaths: usize, time: f32) -> Score {
+        let mut score = Score::new();
+        score.current_level = food;
+        score.current_level_bonus = bonus;
+        score.current_death_count = deaths;
+        score.current_level_time = time;
+        score
+    }
+
+    #[test]
+    fn keeps_the_best_of_each() {
+        let mut save_game = SaveGame::default();
+        save_game.record(1, &score(5, 0, 3, 40.0), false);
+        save_game.record(1, &score(3, 2, 1, 55.0), false);
+
+        assert_eq!(
+            save_game.levels[&1],
+            LevelRecord {
+                best_food: 5,
+                best_bonus: 2,
+                fewest_deaths: Some(1),
+                best_time: Some(40.0),
+            }
+        );
+    }
+
+    #[test]
+    fn beating_a_level_unlocks_the_next() {
+        let mut save_game = SaveGame::default();
+        assert!(save_game.is_unlocked(1));
+        assert!(!save_game.is_unlocked(0));
+        assert!(!save_game.is_unlocked(2));
+
+        save_game.record(1, &score(1, 0, 0, 1.0), false);
+        assert!(save_game.is_unlocked(2));
+        assert!(save_game.has_progress());
+
+        // replaying an earlier level doesn't lock anything again
+        save_game.record(3, &score(1, 0, 0, 1.0), false);
+        save_game.record(1, &score(1, 0, 0, 1.0), false);
+        assert_eq!(save_game.unlocked_level, 4);
+
+        // there's nothing after the last level
+        save_game.record(4, &score(1, 0, 0, 1.0), true);
+        assert_eq!(save_game.unlocked_level, 4);
+    }
+
+    #[test]
+    fn old_saves_still_load() {
+        let save_game = ron::de::from_str::<SaveGame>("(unlocked_level: 3)").unwrap();
+        assert_eq!(save_game.unlocked_level, 3);
+        assert!(save_game.levels.is_empty());
+        assert!(save_game.best_run.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{AppState, dude, food::FoodEatenEvent, game_controller, level, level_over, audio, Dude, assets::GameAssets,
-    title_screen::MenuAction, environment, cleanup, title_screen, ui::text_display, ui::text_size, assets
+    title_screen::MenuAction, environment, cleanup, title_screen, ui::text_display, ui::text_size, assets,
+    versus, classic, game_time,
 };
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
@@ -22,6 +23,10 @@ impl Plugin for ScorePlugin {
                         .after("store_controller_inputs"),
                 ),
         )
+        .add_system_set_to_stage(
+            game_time::FixedUpdateStage,
+            SystemSet::on_update(AppState::InGame).with_system(count_level_time),
+        )
         .init_resource::<ControllerBuffer>()
         .add_system_set(
             SystemSet::on_exit(AppState::ScoreDisplay)
@@ -47,6 +52,12 @@ pub struct Score {
     pub current_level: usize,
     pub current_level_bonus: usize,
     pub current_death_count: usize,
+    // seconds spent on the level, deaths included
+    pub current_level_time: f32,
+    // rounds each side has taken in versus mode
+    pub dude_rounds: usize,
+    pub snake_rounds: usize,
+    pub last_round_winner: Option<versus::Side>,
 }
 
 impl Score {
@@ -57,7 +68,19 @@ impl Score {
             current_level: 0,
             current_level_bonus: 0,
             current_death_count: 0,
+            current_level_time: 0.0,
+            dude_rounds: 0,
+            snake_rounds: 0,
+            last_round_winner: None,
+        }
+    }
+
+    pub fn record_round(&mut self, winner: versus::Side) {
+        match winner {
+            versus::Side::Dude => self.dude_rounds += 1,
+            versus::Side::Snake => self.snake_rounds += 1,
         }
+        self.last_round_winner = Some(winner);
     }
 }
 
@@ -135,7 +158,9 @@ fn displaying_score(
     mut state: ResMut<State<crate::AppState>>,
     mut query: Query<&mut Text, Without<ContinueText>>,
     mut score: ResMut<Score>,
-    level: Res<level::Level>,
+    mut level: ResMut<level::Level>,
+    mut versus: ResMut<versus::Versus>,
+    mut classic: ResMut<classic::Classic>,
     mut text_set: Local<bool>,
     mut continue_text: Query<&mut Text, With<ContinueText>>,
     mut text_blink: Local<bool>,
@@ -153,7 +178,16 @@ fn displaying_score(
         *score_added = true;
     }
 
-    let score_texts = level.get_score_text();
+    let mode_texts;
+    let score_texts = if versus.active {
+        mode_texts = vec![level::LevelText::JustText(versus.score_text(&score))];
+        &mode_texts
+    } else if classic.active {
+        mode_texts = vec![level::LevelText::JustText(classic.score_text())];
+        &mode_texts
+    } else {
+        level.get_score_text()
+    };
     if !*text_set {
         for mut text in query.iter_mut() {
             if let Some(score_text) = &score_texts.get(*text_counter) {
@@ -199,14 +233,25 @@ fn displaying_score(
     }
 
     if *text_counter >= score_texts.len() {
-        state.set(crate::AppState::LevelTitle).unwrap();
+        if versus.active {
+            state.set(versus.next_round(&score, &mut level)).unwrap();
+        } else if classic.active {
+            state.set(classic.next_state(&mut level)).unwrap();
+        } else {
+            state.set(crate::AppState::LevelTitle).unwrap();
+        }
         *text_set = false;
         *text_counter = 0;
         *score_added = false;
         score.current_death_count = 0;
+        score.current_level_time = 0.0;
     }
 }
 
+pub fn count_level_time(mut score: ResMut<Score>, time: Res<game_time::GameTime>) {
+    score.current_level_time += time.delta_seconds();
+}
+
 pub fn increase_death_count(
     mut score: ResMut<Score>,
 ) {
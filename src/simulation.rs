@@ -0,0 +1,225 @@
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+use bevy::transform::TransformPlugin;
+
+use crate::{
+    assets::GameAssets, collectable, dude, dust, environment, food, game_rng::GameRng, game_time,
+    game_time::GameTime, holdable, level, level::Level, level_over, moveable, path_find,
+    path_find::PathFinder, replay, score, snake, win_flag, AppState,
+};
+
+// Runs the level rules without a window, renderer or audio so levels can be
+// simulated on machines without a GPU. Expects MinimalPlugins to be added first.
+//
+//    let mut simulation = Simulation::from_file("assets/data/levels.custom", 1, seed)?;
+//    simulation.step_for(10.0);
+//    assert_eq!(simulation.outcome(), SimulationOutcome::Running);
+pub struct SimulationPlugin;
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AssetPlugin)
+            .add_plugin(TransformPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_state(AppState::InGame)
+            .insert_resource(GameTime::manual())
+            .add_plugin(game_time::GameTimePlugin)
+            .insert_resource(Level::new())
+            .insert_resource(PathFinder::new())
+            .insert_resource(environment::LevelReady(false))
+            .insert_resource(environment::GameOver(false))
+            .insert_resource(score::Score::new())
+            .insert_resource(GameAssets {
+                // the sfx systems index into these, so they can't be empty
+                pickup_handle: vec![Handle::default(); 5],
+                bite_handle: vec![Handle::default(); 4],
+                ..Default::default()
+            })
+            .init_resource::<GameRng>()
+            .init_resource::<SimulationOutcome>()
+            .init_resource::<dude::BufferedPresses>()
+            .init_resource::<replay::ReplayMode>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<dude::DudeMeshes>()
+            .init_resource::<snake::EnemyMeshes>()
+            .init_resource::<win_flag::WinFlagMeshes>()
+            .add_event::<holdable::LiftHoldableEvent>()
+            .add_event::<level::PositionChangeEvent>()
+            .add_event::<level_over::LevelOverEvent>()
+            .add_event::<snake::AddBodyPartEvent>()
+            .add_event::<snake::KillSnakeEvent>()
+            .add_event::<snake::SnakeCollisionEvent>()
+            .add_event::<snake::LoseBodyPartsEvent>()
+            .add_event::<dude::KillDudeEvent>()
+            .add_event::<dude::DudeDiedEvent>()
+            .add_event::<dust::CreateDustEvent>()
+            .add_event::<food::FoodEatenEvent>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(environment::load_level.label("loading_level"))
+                    .with_system(environment::load_level_into_path_finder.after("loading_level"))
+                    .with_system(environment::reset_score),
+            )
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(replay::replay_input.before(dude::player_input))
+                    .with_system(
+                        replay::record_input
+                            .after(replay::replay_input)
+                            .before(dude::player_input),
+                    )
+                    .with_system(dude::player_input)
+                    .with_system(dude::push_block)
+                    .with_system(holdable::lift_holdable.label("handle_lift_events"))
+                    .with_system(holdable::update_held.before("handle_lift_events"))
+                    .with_system(moveable::update_moveable.label("handle_moveables"))
+                    .with_system(collectable::check_collected)
+                    .with_system(snake::update_enemy.after(path_find::update_path))
+                    .with_system(snake::handle_food_eaten)
+                    .with_system(score::handle_food_eaten)
+                    .with_system(food::update_food)
+                    .with_system(food::handle_food_eaten)
+                    .with_system(snake::add_body_parts)
+                    .with_system(snake::add_body_to_reach_level_min)
+                    .with_system(snake::update_following.after(snake::update_enemy))
+                    .with_system(snake::handle_kill_snake.after(snake::update_following))
+                    .with_system(snake::detect_snake_collisions.after(snake::update_enemy))
+                    .with_system(snake::handle_snake_collisions.after(snake::detect_snake_collisions))
+                    .with_system(
+                        snake::lose_body_parts
+                            .after(snake::handle_snake_collisions)
+                            .before(snake::update_following),
+                    )
+                    .with_system(dude::handle_kill_dude)
+                    .with_system(dude::handle_snake_escapes)
+                    .with_system(path_find::update_graph.label("graph_update").after(level::broadcast_changes))
+                    .with_system(path_find::update_path.after("graph_update"))
+                    .with_system(level::broadcast_changes.after("handle_moveables"))
+                    .with_system(snake::detect_dude_on_electric_snake)
+                    .with_system(dust::handle_create_dust_event)
+                    .with_system(record_outcome),
+            );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SimulationOutcome {
+    Running,
+    LevelComplete,
+    DudeDied(dude::DudeDeath),
+}
+impl Default for SimulationOutcome {
+    fn default() -> Self {
+        SimulationOutcome::Running
+    }
+}
+
+fn record_outcome(
+    mut outcome: ResMut<SimulationOutcome>,
+    mut level_over_events: EventReader<level_over::LevelOverEvent>,
+    mut dude_died_events: EventReader<dude::DudeDiedEvent>,
+) {
+    if *outcome != SimulationOutcome::Running {
+        return;
+    }
+
+    if level_over_events.iter().count() > 0 {
+        *outcome = SimulationOutcome::LevelComplete;
+    }
+
+    if let Some(event) = dude_died_events.iter().next() {
+        *outcome = SimulationOutcome::DudeDied(event.death_type);
+    }
+}
+
+pub struct Simulation {
+    pub app: App,
+}
+
+impl Simulation {
+    pub fn new(levels: level::LevelsAsset, level_index: usize, seed: u64) -> Self {
+        Simulation::build(levels, level_index, seed, replay::ReplayMode::Off)
+    }
+
+    // plays the recorded inputs back from the first tick of the level
+    pub fn from_recording(levels: level::LevelsAsset, recording: replay::Recording) -> Self {
+        let (level_index, seed) = (recording.level, recording.seed);
+        Simulation::build(levels, level_index, seed, replay::ReplayMode::replay(recording))
+    }
+
+    // keeps every tick's input so the run can be played back with from_recording
+    pub fn recording(levels: level::LevelsAsset, level_index: usize, seed: u64) -> Self {
+        let replay_mode = replay::ReplayMode::Recording {
+            path: std::path::PathBuf::new(),
+            attempt: 0,
+            recording: Some(replay::Recording::new(level_index, seed)),
+        };
+        Simulation::build(levels, level_index, seed, replay_mode)
+    }
+
+    fn build(
+        levels: level::LevelsAsset,
+        level_index: usize,
+        seed: u64,
+        replay_mode: replay::ReplayMode,
+    ) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(SimulationPlugin)
+            .insert_resource(GameRng::new(seed))
+            .insert_resource(replay_mode);
+
+        {
+            let mut level = app.world.resource_mut::<Level>();
+            level.current_level = level_index;
+            level.load_stored_levels(levels);
+        }
+
+        // first update runs the on_enter systems that spawn the level
+        // along with the first tick
+        app.update();
+
+        Simulation { app }
+    }
+
+    pub fn from_file(path: &str, level_index: usize, seed: u64) -> Result<Self, anyhow::Error> {
+        Ok(Simulation::new(load_levels(path)?, level_index, seed))
+    }
+
+    // runs exactly one game_time::TICK
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    pub fn step_for(&mut self, seconds: f32) {
+        let steps = (seconds / game_time::TICK.as_secs_f32()).ceil() as usize;
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.app.world.resource::<GameTime>().ticks()
+    }
+
+    pub fn outcome(&self) -> SimulationOutcome {
+        *self.app.world.resource::<SimulationOutcome>()
+    }
+
+    pub fn level(&self) -> &Level {
+        self.app.world.resource::<Level>()
+    }
+
+    // what's been recorded so far, for simulations started with recording
+    pub fn take_recording(&mut self) -> Option<replay::Recording> {
+        match &mut *self.app.world.resource_mut::<replay::ReplayMode>() {
+            replay::ReplayMode::Recording { recording, .. } => recording.take(),
+            _ => None,
+        }
+    }
+}
+
+pub fn load_levels(path: &str) -> Result<level::LevelsAsset, anyhow::Error> {
+    level::LevelsAsset::from_file(std::path::Path::new(path))
+}
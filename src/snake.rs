@@ -1,10 +1,10 @@
 use crate::{
-    dude, food::FoodEatenEvent, level::Level, path_find::PathFinder, audio, teleporter, Direction,
-    EntityType, GameObject, Position, assets::GameAssets, environment, level
+    dude, food::FoodEatenEvent, level::Level, path_find::{Node, PathFinder}, audio, teleporter, Direction,
+    EntityType, GameObject, Position, assets::GameAssets, environment, level, grid::{self, GridCoord},
+    game_time::GameTime, snake_brain, snake_control::{self, PlayerSnake}, tile::Tile,
 };
 use bevy::prelude::*;
 use bevy::reflect::TypeUuid;
-use petgraph::graph::NodeIndex;
 use serde::{Serialize, Deserialize};
 
 #[derive(Default)]
@@ -25,10 +25,56 @@ pub enum SnakeTarget {
     OnlyRandom,
 }
 
+// Level file settings for the snake at `position`. If there's a snake tile
+// there these change that snake, otherwise a snake is spawned just for this.
+// Anything left out comes from the level wide settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnakeSpawn {
+    pub position: Position,
+    // which way the head points, the body starts out behind it
+    #[serde(default)]
+    pub facing: Option<Direction>,
+    // body parts, not counting the head
+    #[serde(default)]
+    pub length: Option<usize>,
+    #[serde(default)]
+    pub speed: Option<f32>,
+    #[serde(default)]
+    pub is_electric: Option<bool>,
+    #[serde(default)]
+    pub brain: Option<snake_brain::BrainKind>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+// What happens when snakes run into each other, set per level. It's all off
+// by default so snakes just get in each other's way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnakeRules {
+    // two heads meeting kills both snakes
+    #[serde(default)]
+    pub head_on_kills_both: bool,
+    // biting into another snake takes everything from the bite back to its tail
+    #[serde(default)]
+    pub tail_biting_steals: bool,
+    // running into a shorter snake eats the whole thing
+    #[serde(default)]
+    pub longer_eats_shorter: bool,
+}
+
+impl SnakeRules {
+    pub fn is_off(&self) -> bool {
+        *self == SnakeRules::default()
+    }
+}
+
 #[derive(Debug, Clone, Component)]
 pub struct BodyPosition {
     pub translation: Vec3,
     rotation: Quat,
+    // the head went through a teleporter to get here from the spot before,
+    // so body parts pop out of the teleporter's target instead of sliding over
+    pub through_teleporter: bool,
 }
 
 #[derive(Clone, Component)]
@@ -36,13 +82,18 @@ pub struct Enemy {
     body_parts: Vec<Entity>,
     pub body_positions: Vec<BodyPosition>,
     pub speed: f32,
+    // what speed goes back to whenever the snake picks a new path
+    pub base_speed: f32,
     movement: Option<SnakeMovement>,
     pub is_dead: bool,
     pub death_count: usize,
-    up: Vec3,
-    forward: Vec3,
+    pub up: Vec3,
+    pub forward: Vec3,
     pub is_electric: bool,
-    pub current_path: Option<(u32, Vec<NodeIndex<u32>>)>,
+    pub current_path: Option<(u32, Vec<Node>)>,
+    material: Handle<StandardMaterial>,
+    // marks the next body position as coming out of a teleporter
+    came_through_teleporter: bool,
 }
 
 impl Enemy {
@@ -50,6 +101,15 @@ impl Enemy {
         Position::from_vec(self.body_positions[0].translation)
     }
 
+    pub fn is_moving(&self) -> bool {
+        self.movement.is_some()
+    }
+
+    // body parts, not counting the head
+    pub fn length(&self) -> usize {
+        self.body_parts.len()
+    }
+
     pub fn is_in_vec(&self, position: Vec3) -> bool {
         for body_position in self.body_positions.iter() {
             if body_position.translation == position {
@@ -72,6 +132,25 @@ pub struct SnakeVisibleMesh {
     parent: Entity,
 }
 pub struct KillSnakeEvent(pub Entity);
+// `snake` moved into `other`, the level's SnakeRules decide what comes of it
+#[derive(Debug, Copy, Clone)]
+pub struct SnakeCollisionEvent {
+    pub snake: Entity,
+    pub other: Entity,
+    pub collision: SnakeCollision,
+}
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SnakeCollision {
+    HeadOn,
+    // which of the other snake's body parts got bitten, 0 is right behind the head
+    Body(usize),
+}
+// drops the snake's body parts from `from` back to the tail
+#[derive(Copy, Clone)]
+pub struct LoseBodyPartsEvent {
+    pub snake: Entity,
+    pub from: usize,
+}
 #[derive(Clone, Component)]
 struct SnakeMovement {
     target: Vec3,
@@ -83,6 +162,8 @@ struct SnakeMovement {
     target_rotation: Quat,
     current_rotation_time: f32,
     finish_rotation_time: f32,
+    // whether detect_snake_collisions has looked at this move yet
+    collision_checked: bool,
 }
 
 static INNER_MESH_VERTICAL_OFFSET: f32 = 0.0;
@@ -90,6 +171,7 @@ static INNER_MESH_VERTICAL_OFFSET: f32 = 0.0;
 pub fn generate_snake_body<T: Component>(
     commands: &mut Commands,
     meshes: &ResMut<EnemyMeshes>,
+    material: &Handle<StandardMaterial>,
     transform: Transform,
     rotation: Option<Quat>,
     is_electric: bool,
@@ -135,7 +217,7 @@ pub fn generate_snake_body<T: Component>(
                     if is_electric {
                         inner_parent.spawn_bundle(PbrBundle {
                             mesh: meshes.body.clone(),
-                            material: meshes.material.clone(),
+                            material: material.clone(),
                             transform: {
                                 let mut t = Transform::from_translation(Vec3::new(0.0, 0.0, 0.0));
                                 t.rotate(Quat::from_axis_angle(
@@ -152,7 +234,7 @@ pub fn generate_snake_body<T: Component>(
                     inner_parent
                         .spawn_bundle(PbrBundle {
                             mesh: meshes.body.clone(),
-                            material: meshes.material.clone(),
+                            material: material.clone(),
                             transform: {
                                 let mut t = Transform::from_translation(Vec3::new(0.0, 0.0, 0.0));
                                 t.rotate(Quat::from_axis_angle(
@@ -183,6 +265,7 @@ pub fn create_snake(
 pub fn spawn_enemy<T: Component + Clone>(
     commands: &mut Commands,
     meshes: &ResMut<EnemyMeshes>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
     level: &mut ResMut<Level>,
     x: usize,
     y: usize,
@@ -199,9 +282,86 @@ pub fn spawn_enemy<T: Component + Clone>(
         std::f32::consts::FRAC_PI_2,
     ));
 
-    let body_part_entity = generate_snake_body(commands, meshes, transform, None, is_electric, cleanup_marker.clone());
+    let spawn = level.snake_spawn(&Position::from_vec(position)).cloned();
+    let spawn = spawn.as_ref();
+    let material = match spawn.and_then(|spawn| spawn.color.as_ref()) {
+        Some(color) => materials.add(StandardMaterial {
+            base_color: Color::hex(color).unwrap_or(Color::WHITE),
+            perceptual_roughness: 1.0,
+            metallic: 0.4,
+            reflectance: 0.0,
+            ..Default::default()
+        }),
+        None => meshes.material.clone(),
+    };
+    let snake_speed = spawn
+        .and_then(|spawn| spawn.speed)
+        .unwrap_or_else(|| level.snake_speed());
+    let brain = level.snake_brain(&Position::from_vec(position)).build();
+
+    let mut enemy = Enemy {
+        body_parts: vec![],
+        body_positions: vec![],
+        speed: snake_speed,
+        base_speed: snake_speed,
+        is_electric,
+        movement: None,
+        is_dead: false,
+        death_count: 0,
+        up: Vec3::Y,
+        forward: -Vec3::X,
+        current_path: None,
+        material: material.clone(),
+        came_through_teleporter: false,
+    };
+
+    // snakes spawn facing down, anything else is turned to the same way it would be while moving
+    let facing = spawn.and_then(|spawn| spawn.facing).unwrap_or(Direction::Down);
+    let rotation = match facing {
+        Direction::Down => Quat::IDENTITY,
+        Direction::Up => {
+            let turned = calculate_new_rotation(Quat::IDENTITY, Direction::Right, &mut enemy);
+            calculate_new_rotation(turned, Direction::Up, &mut enemy)
+        }
+        _ => calculate_new_rotation(Quat::IDENTITY, facing, &mut enemy),
+    };
+
+    // the body goes in a line behind the head for as long as there's room,
+    // anything left over is piled on the end, or on the head if there's no
+    // room at all, and comes out as the snake moves
+    let length = spawn.and_then(|spawn| spawn.length).unwrap_or(1).max(1);
+    let mut tail = Position::from_vec(position);
+    for _ in 0..length {
+        let behind = tail.neighbour(grid::opposite(facing));
+        let has_room = level.is_inbounds(behind) && level.get_tile(behind).map_or(false, Tile::is_open);
+        if has_room {
+            tail = behind;
+            enemy.body_positions.push(BodyPosition {
+                translation: tail.to_vec(),
+                rotation,
+                through_teleporter: false,
+            });
+        }
+
+        let mut body_transform = transform;
+        body_transform.translation = tail.to_vec();
+        let body_part_entity = generate_snake_body(
+            commands,
+            meshes,
+            &material,
+            body_transform,
+            Some(rotation),
+            is_electric,
+            cleanup_marker.clone(),
+        );
+        enemy.body_parts.push(body_part_entity);
+    }
 
-    let snake_speed = level.snake_speed();
+    let body_positions: Vec<Position> = enemy
+        .body_positions
+        .iter()
+        .map(|body| Position::from_vec(body.translation))
+        .collect();
     let enemy_entity = commands
         .spawn_bundle(PbrBundle {
             transform,
@@ -215,34 +375,21 @@ pub fn spawn_enemy<T: Component + Clone>(
         })
         .insert(EntityType::EnemyHead)
         .insert(Snake)
-        .insert(Enemy {
-            body_parts: vec![body_part_entity],
-            body_positions: vec![BodyPosition {
-                translation: Vec3::new(
-                    transform.translation.x + 1.0,
-                    transform.translation.y,
-                    transform.translation.z,
-                ),
-                rotation: Quat::IDENTITY,
-            }],
-            speed: snake_speed,
-            is_electric,
-            movement: None,
-            is_dead: false,
-            death_count: 0,
-            up: Vec3::Y,
-            forward: -Vec3::X,
-            current_path: None,
-        })
+        .insert(enemy)
+        .insert(snake_brain::Brain(brain))
         .with_children(|parent| {
             let parent_entity = parent.parent_entity();
             parent
                 .spawn_bundle(PbrBundle {
-                    transform: Transform::from_translation(Vec3::new(
-                        0.0,
-                        INNER_MESH_VERTICAL_OFFSET,
-                        0.0,
-                    )),
+                    transform: {
+                        let mut t = Transform::from_translation(Vec3::new(
+                            0.0,
+                            INNER_MESH_VERTICAL_OFFSET,
+                            0.0,
+                        ));
+                        t.rotation = rotation;
+                        t
+                    },
                     ..Default::default()
                 })
                 .insert(SnakeInnerMesh)
@@ -250,7 +397,7 @@ pub fn spawn_enemy<T: Component + Clone>(
                     if is_electric {
                         inner_parent.spawn_bundle(PbrBundle {
                             mesh: meshes.head.clone(),
-                            material: meshes.material.clone(),
+                            material: material.clone(),
                             transform: {
                                 let mut t = Transform::from_translation(Vec3::new(0.0, 0.0, 0.0));
                                 t.scale = Vec3::new(1.1, 1.1, 1.1);
@@ -263,7 +410,7 @@ pub fn spawn_enemy<T: Component + Clone>(
                     inner_parent
                         .spawn_bundle(PbrBundle {
                             mesh: meshes.head.clone(),
-                            material: meshes.material.clone(),
+                            material: material.clone(),
                             ..Default::default()
                         })
                         .insert(SnakeVisibleMesh {
@@ -272,18 +419,10 @@ pub fn spawn_enemy<T: Component + Clone>(
                 });
         })
         .id();
-    level.set(
-        position.x as i32,
-        position.y as i32,
-        position.z as i32,
-        Some(GameObject::new(enemy_entity, EntityType::EnemyHead)),
-    );
-    level.set(
-        position.x as i32 + 1,
-        position.y as i32,
-        position.z as i32,
-        Some(GameObject::new(enemy_entity, EntityType::Enemy)),
-    );
+    level.set(position, Some(GameObject::new(enemy_entity, EntityType::EnemyHead)));
+    for body in body_positions {
+        level.set(body, Some(GameObject::new(enemy_entity, EntityType::Enemy)));
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -314,7 +453,7 @@ pub fn add_body_to_reach_level_min(
     enemies: Query<(Entity, &Enemy)>,
     mut body_part_writer: EventWriter<AddBodyPartEvent>,
     level: Res<Level>,
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut timer: Local<f32>,
 ) {
     if let Some(min_snake_length) = level.min_snake_length() {
@@ -341,18 +480,24 @@ pub fn add_body_parts(
 ) {
     for part_to_add in body_part_reader.iter() {
         if let Ok(mut snake_enemy) = snake_enemies.get_mut(part_to_add.snake) {
-            let last_position = snake_enemy.body_positions.last().unwrap();
+            // nothing to grow from
+            let last_position = match snake_enemy.body_positions.last() {
+                Some(last_position) => last_position,
+                None => continue,
+            };
             let mut transform = Transform::from_translation(last_position.translation);
             transform.rotate(Quat::from_axis_angle(
                 Vec3::new(0.0, 1.0, 0.0),
                 std::f32::consts::FRAC_PI_2,
             ));
             transform.apply_non_uniform_scale(Vec3::new(0.50, 0.50, 0.50));
-            let rotation = snake_enemy.body_positions.last().unwrap().rotation;
+            let rotation = last_position.rotation;
+            let material = snake_enemy.material.clone();
 
             let body_part_entity = generate_snake_body(
                 &mut commands,
                 &meshes,
+                &material,
                 transform,
                 Some(rotation),
                 snake_enemy.is_electric,
@@ -364,282 +509,281 @@ pub fn add_body_parts(
 }
 
 pub fn update_enemy(
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut enemies: Query<
-        (Entity, &mut Enemy, &mut Transform, &mut Position, &Children),
+        (
+            Entity,
+            &mut Enemy,
+            &mut Transform,
+            &mut Position,
+            &Children,
+            Option<&mut PlayerSnake>,
+        ),
         (Without<SnakeBody>, Without<SnakeInnerMesh>),
     >,
     mut inner_meshes: Query<&mut Transform, With<SnakeInnerMesh>>,
     path_find: Res<PathFinder>,
     mut level: ResMut<Level>,
     teleporters: Query<&teleporter::Teleporter>,
-
-    keyboard_input: Res<Input<KeyCode>>,
-    mut is_active: Local<bool>,
-    mut timer: Local<f32>,
 ) {
-    // FOR DEBUGGING
-    //  if *timer > 0.2 && keyboard_input.pressed(KeyCode::L) {
-    //      *is_active = !*is_active;
-    //      *timer = 0.0;
-    //  }
-    //  *timer += time.delta_seconds();
-    // FOR DEBUGGING
-
-    if !*is_active {
-        for (entity, mut enemy, mut transform, mut position, children) in enemies.iter_mut() {
-            if enemy.is_dead {
-                continue;
-            }
-
-            if enemy.movement.is_none() {
-                let is_ai_controlled = true;
-                let mut new_target = None;
-                if is_ai_controlled {
-                    let (_, path) = enemy.current_path.clone().unwrap_or((0, vec![]));
-                    let mut found_next = false;
-                    let mut found_target = None;
-
-                    for p in path.iter() {
-                        if found_next {
-                            found_target = Some(p);
-                            break;
-                        }
+    for (entity, mut enemy, mut transform, mut position, children, player_snake) in enemies.iter_mut() {
+        if enemy.is_dead {
+            continue;
+        }
 
-                        if path_find.get_position(*p).matches(transform.translation) {
-                            found_next = true;
-                        }
+        if enemy.movement.is_none() {
+            let mut new_target = None;
+            if let Some(mut player_snake) = player_snake {
+                let turn = player_snake.queued.take();
+                new_target = snake_control::next_target(&enemy, turn, &position, &path_find);
+            } else {
+                let (_, path) = enemy.current_path.clone().unwrap_or((0, vec![]));
+                let mut found_next = false;
+                let mut found_target = None;
+
+                for p in path.iter() {
+                    if found_next {
+                        found_target = Some(p);
+                        break;
                     }
 
-                    if let Some(target) = found_target {
-                        new_target = Some(path_find.get_position(*target));
-                    }
-                } else {
-                    let mut target = None;
-                    let cur = transform.translation;
-                    if keyboard_input.pressed(KeyCode::W) {
-                        target = Some(Vec3::new(cur.x + 1.0, cur.y, cur.z));
-                    }
-                    if keyboard_input.pressed(KeyCode::S) {
-                        target = Some(Vec3::new(cur.x - 1.0, cur.y, cur.z));
-                    }
-                    if keyboard_input.pressed(KeyCode::A) {
-                        target = Some(Vec3::new(cur.x, cur.y, cur.z - 1.0));
-                    }
-                    if keyboard_input.pressed(KeyCode::D) {
-                        target = Some(Vec3::new(cur.x, cur.y, cur.z + 1.0));
-                    }
-                    if keyboard_input.pressed(KeyCode::E) {
-                        target = Some(Vec3::new(cur.x, cur.y + 1.0, cur.z));
-                    }
-                    if keyboard_input.pressed(KeyCode::C) {
-                        target = Some(Vec3::new(cur.x, cur.y - 1.0, cur.z));
-                    }
-                    if let Some(target) = target {
-                        //                        println!("Enemy Up: {:?} Forward: {:?}", enemy.up, enemy.forward);
-                        //                        println!("Transform: {:?}", transform);
-                        new_target = Some(Position::from_vec(target));
+                    if path_find.get_position(*p).matches(transform.translation) {
+                        found_next = true;
                     }
                 }
 
-                if let Some(target) = new_target {
-                    let current = transform.translation.as_ivec3();
-                    let facing = if target.z > current.z {
-                        Direction::Right
-                    } else if target.z < current.z {
-                        Direction::Left
-                    } else if target.x > current.x {
-                        Direction::Up
-                    } else if target.x < current.x {
-                        Direction::Down
-                    } else if target.y < current.y {
-                        Direction::Beneath
-                    } else {
-                        Direction::Above
-                    };
-                    let starting_from = transform.translation;
-                    let target = Vec3::new(target.x as f32, target.y as f32, target.z as f32);
-
-                    let start_rotation = inner_meshes
-                        .get_mut(*children.iter().last().unwrap())
-                        .unwrap()
-                        .rotation;
-                    let target_rotation =
-                        calculate_new_rotation(start_rotation, facing, &mut enemy);
-
-                    enemy.movement = Some(SnakeMovement {
-                        target,
-                        starting_from,
-                        current_movement_time: 0.0,
-                        finish_movement_time: enemy.speed,
-                        start_rotation,
-                        target_rotation,
-                        current_rotation_time: 0.0,
-                        finish_rotation_time: enemy.speed,
-                    });
-
-                    // pushes a new history state at the front, and pops one off the end
-                    // and updates the level by setting that spot to None
-                    enemy.body_positions.insert(
-                        0,
-                        BodyPosition {
-                            translation: starting_from,
-                            rotation: target_rotation,
-                        },
-                    );
-                    let number_of_body_parts = enemy.body_parts.len();
-                    let last_body_position = enemy.body_positions.last().unwrap();
-                    level.set_with_vec(last_body_position.translation, None);
-                    enemy.body_positions.truncate(number_of_body_parts);
+                if let Some(target) = found_target {
+                    new_target = Some(path_find.get_position(*target));
                 }
             }
 
-            let speed = enemy.speed;
-            if let Some(movement) = &mut enemy.movement {
-                // if the spot the snake moved from is the same object then clear it
-                if let Some(game_object) = level.get_with_vec(transform.translation) {
-                    if game_object.entity == entity {
-                        level.set_with_vec(transform.translation, None);
-                    }
+            if let Some(target) = new_target {
+                let current = transform.translation.as_ivec3();
+                let facing = if target.z > current.z {
+                    Direction::Right
+                } else if target.z < current.z {
+                    Direction::Left
+                } else if target.x > current.x {
+                    Direction::Up
+                } else if target.x < current.x {
+                    Direction::Down
+                } else if target.y < current.y {
+                    Direction::Beneath
+                } else {
+                    Direction::Above
+                };
+                let starting_from = transform.translation;
+                let target = Vec3::new(target.x as f32, target.y as f32, target.z as f32);
+
+                let start_rotation = inner_meshes
+                    .get_mut(*children.iter().last().unwrap())
+                    .unwrap()
+                    .rotation;
+                let target_rotation =
+                    calculate_new_rotation(start_rotation, facing, &mut enemy);
+
+                enemy.movement = Some(SnakeMovement {
+                    target,
+                    starting_from,
+                    current_movement_time: 0.0,
+                    finish_movement_time: enemy.speed,
+                    start_rotation,
+                    target_rotation,
+                    current_rotation_time: 0.0,
+                    finish_rotation_time: enemy.speed,
+                    collision_checked: false,
+                });
+
+                let through_teleporter = std::mem::take(&mut enemy.came_through_teleporter);
+                push_body_position(
+                    &mut enemy,
+                    BodyPosition {
+                        translation: starting_from,
+                        rotation: target_rotation,
+                        through_teleporter,
+                    },
+                    &mut level,
+                    &teleporters,
+                );
+            }
+        }
+
+        let speed = enemy.speed;
+        if let Some(movement) = &mut enemy.movement {
+            // if the spot the snake moved from is the same object then clear it
+            if let Some(game_object) = level.get(transform.translation) {
+                if game_object.entity == entity {
+                    level.set(transform.translation, None);
                 }
+            }
 
-                if movement.current_movement_time >= movement.finish_movement_time {
-                    if level.is_enterable_with_vec(movement.target) {
-                        transform.translation = movement.target;
-                    }
+            if movement.current_movement_time >= movement.finish_movement_time {
+                if level.is_vacant(movement.target) {
+                    transform.translation = movement.target;
+                }
+
+                let mut potential_movement = None;
+                if !teleporters.iter().len() > 0 {
+                    for teleporter in teleporters.iter() {
+                        if teleporter.position == Position::from_vec(movement.target) {
+                            push_body_position(
+                                &mut enemy,
+                                BodyPosition {
+                                    translation: transform.translation,
+                                    rotation: transform.rotation,
+                                    through_teleporter: false,
+                                },
+                                &mut level,
+                                &teleporters,
+                            );
+                            enemy.came_through_teleporter = true;
 
-                    let mut potential_movement = None;
-                    if !teleporters.iter().len() > 0 {
-                        for teleporter in teleporters.iter() {
-                            if teleporter.position == Position::from_vec(movement.target) {
-                                // pushes a new history state at the front, and pops one off the end
-                                // and updates the level by setting that spot to None
-                                enemy.body_positions.insert(
-                                    0,
-                                    BodyPosition {
-                                        translation: transform.translation,
-                                        rotation: transform.rotation,
-                                    },
-                                );
-                                let number_of_body_parts = enemy.body_parts.len();
-                                let last_body_position = enemy.body_positions.last().unwrap();
-                                level.set_with_vec(last_body_position.translation, None);
-                                enemy.body_positions.truncate(number_of_body_parts);
-
-                                transform.translation = Vec3::new(
-                                    teleporter.target.x as f32,
-                                    teleporter.target.y as f32,
-                                    teleporter.target.z as f32,
-                                );
-
-                                let rotation = get_exact_rotation(teleporter.facing);
-
-                                match teleporter.facing {
-                                    Direction::Up => {
-                                        enemy.up = Vec3::Y;
-                                        enemy.forward = Vec3::X;
-                                    }
-                                    Direction::Down => {
-                                        enemy.up = Vec3::Y;
-                                        enemy.forward = -Vec3::X;
-                                    }
-                                    Direction::Right => {
-                                        enemy.up = Vec3::Y;
-                                        enemy.forward = Vec3::Z;
-                                    }
-                                    Direction::Left => {
-                                        enemy.up = Vec3::Y;
-                                        enemy.forward = -Vec3::Z;
-                                    }
-                                    Direction::Above => {
-                                        enemy.up = Vec3::X;
-                                        enemy.forward = Vec3::Y;
-                                    }
-                                    Direction::Beneath => {
-                                        enemy.up = -Vec3::X;
-                                        enemy.forward = -Vec3::Y;
-                                    }
-                                }
+                            transform.translation = Vec3::new(
+                                teleporter.target.x as f32,
+                                teleporter.target.y as f32,
+                                teleporter.target.z as f32,
+                            );
 
-                                potential_movement = Some(SnakeMovement {
-                                    target: Vec3::new(
-                                        teleporter.move_to.x as f32,
-                                        teleporter.move_to.y as f32,
-                                        teleporter.move_to.z as f32,
-                                    ),
-                                    starting_from: transform.translation,
-                                    current_movement_time: 0.0,
-                                    finish_movement_time: speed,
-                                    start_rotation: rotation,
-                                    target_rotation: rotation,
-                                    current_rotation_time: 0.0,
-                                    finish_rotation_time: speed,
-                                });
-                                break;
+                            let rotation = get_exact_rotation(teleporter.facing);
+
+                            match teleporter.facing {
+                                Direction::Up => {
+                                    enemy.up = Vec3::Y;
+                                    enemy.forward = Vec3::X;
+                                }
+                                Direction::Down => {
+                                    enemy.up = Vec3::Y;
+                                    enemy.forward = -Vec3::X;
+                                }
+                                Direction::Right => {
+                                    enemy.up = Vec3::Y;
+                                    enemy.forward = Vec3::Z;
+                                }
+                                Direction::Left => {
+                                    enemy.up = Vec3::Y;
+                                    enemy.forward = -Vec3::Z;
+                                }
+                                Direction::Above => {
+                                    enemy.up = Vec3::X;
+                                    enemy.forward = Vec3::Y;
+                                }
+                                Direction::Beneath => {
+                                    enemy.up = -Vec3::X;
+                                    enemy.forward = -Vec3::Y;
+                                }
                             }
+
+                            potential_movement = Some(SnakeMovement {
+                                target: Vec3::new(
+                                    teleporter.move_to.x as f32,
+                                    teleporter.move_to.y as f32,
+                                    teleporter.move_to.z as f32,
+                                ),
+                                starting_from: transform.translation,
+                                current_movement_time: 0.0,
+                                finish_movement_time: speed,
+                                start_rotation: rotation,
+                                target_rotation: rotation,
+                                current_rotation_time: 0.0,
+                                finish_rotation_time: speed,
+                                collision_checked: false,
+                            });
+                            break;
                         }
                     }
+                }
 
-                    enemy.movement = potential_movement;
-                } else {
-                    // keep moving toward target
-                    movement.current_movement_time += time.delta_seconds();
-                    let new_translation = movement.starting_from.lerp(
-                        movement.target,
-                        movement.current_movement_time / movement.finish_movement_time,
-                    );
-                    if !new_translation.is_nan() {
-                        if transform.translation.distance(movement.target)
-                            < transform.translation.distance(new_translation)
-                        {
-                            transform.translation = movement.target;
-                            movement.current_movement_time = movement.finish_movement_time;
-                        } else {
-                            transform.translation = new_translation;
-                        }
+                enemy.movement = potential_movement;
+            } else {
+                // keep moving toward target
+                movement.current_movement_time += time.delta_seconds();
+                let new_translation = movement.starting_from.lerp(
+                    movement.target,
+                    movement.current_movement_time / movement.finish_movement_time,
+                );
+                if !new_translation.is_nan() {
+                    if transform.translation.distance(movement.target)
+                        < transform.translation.distance(new_translation)
+                    {
+                        transform.translation = movement.target;
+                        movement.current_movement_time = movement.finish_movement_time;
+                    } else {
+                        transform.translation = new_translation;
                     }
+                }
 
-                    for child in children.iter() {
-                        if let Ok(mut inner_mesh) = inner_meshes.get_mut(*child) {
-                            // keep rotating toward target
-                            movement.current_rotation_time += time.delta_seconds();
-                            let new_rotation = movement.start_rotation.lerp(
-                                movement.target_rotation,
-                                movement.current_rotation_time / movement.finish_rotation_time,
-                            );
-                            if !new_rotation.is_nan() {
-                                if inner_mesh.rotation.angle_between(movement.target_rotation)
-                                    < inner_mesh.rotation.angle_between(new_rotation)
-                                {
-                                    inner_mesh.rotation = movement.target_rotation;
-                                    movement.current_rotation_time = movement.finish_rotation_time;
-                                } else {
-                                    inner_mesh.rotation = new_rotation;
-                                }
+                for child in children.iter() {
+                    if let Ok(mut inner_mesh) = inner_meshes.get_mut(*child) {
+                        // keep rotating toward target
+                        movement.current_rotation_time += time.delta_seconds();
+                        let new_rotation = movement.start_rotation.lerp(
+                            movement.target_rotation,
+                            movement.current_rotation_time / movement.finish_rotation_time,
+                        );
+                        if !new_rotation.is_nan() {
+                            if inner_mesh.rotation.angle_between(movement.target_rotation)
+                                < inner_mesh.rotation.angle_between(new_rotation)
+                            {
+                                inner_mesh.rotation = movement.target_rotation;
+                                movement.current_rotation_time = movement.finish_rotation_time;
+                            } else {
+                                inner_mesh.rotation = new_rotation;
                             }
                         }
                     }
                 }
-
-                // need to update level here
-                level.set_with_vec(
-                    transform.translation,
-                    Some(GameObject::new(entity, EntityType::EnemyHead)),
-                );
-                position.update_from_vec(transform.translation);
             }
+
+            // need to update level here
+            level.set(
+                transform.translation,
+                Some(GameObject::new(entity, EntityType::EnemyHead)),
+            );
+            position.update_from_vec(transform.translation);
         }
     }
 }
 
+// pushes a new history state at the front, and pops one off the end
+// and updates the level by setting that spot to None
+fn push_body_position(
+    enemy: &mut Enemy,
+    body_position: BodyPosition,
+    level: &mut Level,
+    teleporters: &Query<&teleporter::Teleporter>,
+) {
+    enemy.body_positions.insert(0, body_position);
+    let number_of_body_parts = enemy.body_parts.len();
+    let last_body_position = enemy.body_positions.last().unwrap();
+    level.set(last_body_position.translation, None);
+    // a body position on a teleporter holds the spot on the other side too
+    if let Some(teleporter) = teleporter_at(last_body_position.translation, teleporters) {
+        level.set(teleporter.target, None);
+    }
+    enemy.body_positions.truncate(number_of_body_parts);
+}
+
+fn teleporter_at<'a>(
+    translation: Vec3,
+    teleporters: &'a Query<&teleporter::Teleporter>,
+) -> Option<&'a teleporter::Teleporter> {
+    let position = Position::from_vec(translation);
+    teleporters.iter().find(|teleporter| teleporter.position == position)
+}
+
+// Body parts follow the spots the head has been. A part that reaches a
+// teleporter is hidden there and shows up again at the teleporter's target
+// once the spot it's following is on the other side, rather than sliding
+// across the gap between them.
 pub fn update_following(
     mut snakes: Query<&mut Enemy>,
     mut body_parts: Query<(Entity, &mut Transform, &Children), (With<SnakeBody>, Without<Enemy>)>,
     mut inner_meshes: Query<
-        &mut Transform,
+        (&mut Transform, &Children),
         (With<SnakeInnerMesh>, Without<SnakeBody>, Without<Enemy>),
     >,
-    time: Res<Time>,
+    mut visibilities: Query<&mut Visibility>,
+    time: Res<GameTime>,
     teleporters: Query<&teleporter::Teleporter>,
     mut level: ResMut<Level>,
 ) {
@@ -650,89 +794,78 @@ pub fn update_following(
         for body_part in body_part_entities.iter() {
             if let Ok((entity, mut transform, children)) = body_parts.get_mut(*body_part) {
                 if let Some(target) = &mut snake.body_positions.get_mut(part_index) {
+                    let is_hidden = !is_body_part_visible(children, &inner_meshes, &visibilities);
+                    if target.through_teleporter
+                        && (is_hidden || transform.translation.distance(target.translation) > 1.5)
+                    {
+                        // still on the near side, so come out of whichever teleporter leads here
+                        let exit = teleporters
+                            .iter()
+                            .filter(|teleporter| teleporter.move_to == Position::from_vec(target.translation))
+                            .min_by(|a, b| {
+                                let a = a.position.to_vec().distance(transform.translation);
+                                let b = b.position.to_vec().distance(transform.translation);
+                                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+                            });
+                        if let Some(teleporter) = exit {
+                            transform.translation = teleporter.target.to_vec();
+                            let rotation = get_exact_rotation(teleporter.facing);
+                            for child in children.iter() {
+                                if let Ok((mut transform, _)) = inner_meshes.get_mut(*child) {
+                                    transform.rotation = rotation;
+                                }
+                            }
+                            set_body_part_visible(children, &inner_meshes, &mut visibilities, true);
+                        }
+                    }
+
                     let rate = snake_speed / 1.0;
                     let distance = transform.translation.distance(target.translation);
                     let new_translation = transform
                         .translation
                         .lerp(target.translation, time.delta_seconds() / (distance * rate));
-                    let mut has_just_teleported = false;
                     if !new_translation.is_nan() {
-                        // if the distance is this far, this body part probably just teleported and is trying
-                        // to float back to where it came from. Just don't let it do anything until it gets
-                        // a new, closer target.
-                        has_just_teleported =
-                            transform.translation.distance(target.translation) > 3.0;
-
-                        if !has_just_teleported {
-                            if transform.translation.distance(target.translation)
-                                < transform.translation.distance(new_translation)
-                            {
-                                transform.translation = target.translation;
-
-                                if !teleporters.iter().len() > 0 {
-                                    for teleporter in teleporters.iter() {
-                                        if teleporter.position
-                                            == Position::from_vec(transform.translation)
-                                        {
-                                            transform.translation = Vec3::new(
-                                                teleporter.target.x as f32,
-                                                teleporter.target.y as f32,
-                                                teleporter.target.z as f32,
-                                            );
-
-                                            let rotation = get_exact_rotation(teleporter.facing);
-                                            for child in children.iter() {
-                                                if let Ok(mut transform) =
-                                                    inner_meshes.get_mut(*child)
-                                                {
-                                                    transform.rotation = rotation;
-                                                }
-                                            }
-                                            break;
-
-                                            //      let new_target = teleporter.target;
-                                            //      level.set_with_vec(transform.translation, None);
-                                            //      println!("Body is at {:?}", transform.translation);
-                                            //      transform.translation = Vec3::new(new_target.x as f32,
-                                            //                                        new_target.y as f32,
-                                            //                                        new_target.z as f32);
-                                            //      println!("Body is at {:?}", transform.translation);
-
-                                            //      // set new target after teleportation
-                                            //      target.translation = Vec3::new(teleporter.move_to.x as f32,
-                                            //                                     teleporter.move_to.y as f32,
-                                            //                                     teleporter.move_to.z as f32);
-                                        }
-                                    }
-                                }
-                            } else {
-                                transform.translation = new_translation;
+                        if transform.translation.distance(target.translation)
+                            < transform.translation.distance(new_translation)
+                        {
+                            transform.translation = target.translation;
+
+                            if teleporter_at(target.translation, &teleporters).is_some() {
+                                set_body_part_visible(children, &inner_meshes, &mut visibilities, false);
                             }
+                        } else {
+                            transform.translation = new_translation;
                         }
                     }
-                    if !has_just_teleported {
-                        level.set_with_vec(
-                            target.translation,
+
+                    level.set(
+                        target.translation,
+                        Some(GameObject::new(entity, EntityType::Enemy)),
+                    );
+                    // nothing gets through the other side while the body is passing through
+                    if let Some(teleporter) = teleporter_at(target.translation, &teleporters) {
+                        level.set(
+                            teleporter.target,
                             Some(GameObject::new(entity, EntityType::Enemy)),
                         );
+                    }
 
-                        for child in children.iter() {
-                            if let Ok(mut transform) = inner_meshes.get_mut(*child) {
-                                let rotation_rate = (snake_speed * 0.60) / 1.0;
-                                let rotation_distance =
-                                    transform.rotation.angle_between(target.rotation);
-                                let new_rotation = transform.rotation.lerp(
-                                    target.rotation,
-                                    time.delta_seconds() / (rotation_distance * rotation_rate),
-                                );
-                                if !new_rotation.is_nan() {
-                                    if transform.rotation.angle_between(target.rotation)
-                                        < transform.rotation.angle_between(new_rotation)
-                                    {
-                                        transform.rotation = target.rotation;
-                                    } else {
-                                        transform.rotation = new_rotation;
-                                    }
+                    for child in children.iter() {
+                        if let Ok((mut transform, _)) = inner_meshes.get_mut(*child) {
+                            let rotation_rate = (snake_speed * 0.60) / 1.0;
+                            let rotation_distance =
+                                transform.rotation.angle_between(target.rotation);
+                            let new_rotation = transform.rotation.lerp(
+                                target.rotation,
+                                time.delta_seconds() / (rotation_distance * rotation_rate),
+                            );
+                            if !new_rotation.is_nan() {
+                                if transform.rotation.angle_between(target.rotation)
+                                    < transform.rotation.angle_between(new_rotation)
+                                {
+                                    transform.rotation = target.rotation;
+                                } else {
+                                    transform.rotation = new_rotation;
                                 }
                             }
                         }
@@ -745,6 +878,43 @@ pub fn update_following(
     }
 }
 
+// the meshes are under the inner mesh, which is what turns
+fn is_body_part_visible(
+    children: &Children,
+    inner_meshes: &Query<
+        (&mut Transform, &Children),
+        (With<SnakeInnerMesh>, Without<SnakeBody>, Without<Enemy>),
+    >,
+    visibilities: &Query<&mut Visibility>,
+) -> bool {
+    children
+        .iter()
+        .filter_map(|child| inner_meshes.get(*child).ok())
+        .flat_map(|(_, meshes)| meshes.iter())
+        .filter_map(|mesh| visibilities.get(*mesh).ok())
+        .any(|visibility| visibility.is_visible)
+}
+
+fn set_body_part_visible(
+    children: &Children,
+    inner_meshes: &Query<
+        (&mut Transform, &Children),
+        (With<SnakeInnerMesh>, Without<SnakeBody>, Without<Enemy>),
+    >,
+    visibilities: &mut Query<&mut Visibility>,
+    is_visible: bool,
+) {
+    for child in children.iter() {
+        if let Ok((_, meshes)) = inner_meshes.get(*child) {
+            for mesh in meshes.iter() {
+                if let Ok(mut visibility) = visibilities.get_mut(*mesh) {
+                    visibility.is_visible = is_visible;
+                }
+            }
+        }
+    }
+}
+
 pub fn handle_food_eaten(
     mut food_eaten_event_reader: EventReader<FoodEatenEvent>,
     mut body_part_writer: EventWriter<AddBodyPartEvent>,
@@ -775,7 +945,7 @@ pub fn handle_kill_snake(
     snake_part_transforms: Query<&Transform, With<SnakeBody>>,
     mut snake_part_meshes: Query<(&SnakeVisibleMesh, &mut Visibility)>,
     mut dying_snakes: Local<Vec<(Entity, u32, Timer, i32, i32, i32)>>, // entity, number of flashes, timer, x, y, z
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut level: ResMut<Level>,
 ) {
     let flash_limit = 5;
@@ -806,7 +976,7 @@ pub fn handle_kill_snake(
         commands.entity(dead_snake.0).despawn_recursive();
 
         println!("setting {} {} {} to None", dead_snake.3, dead_snake.4, dead_snake.5);
-        level.set(dead_snake.3, dead_snake.4, dead_snake.5, None);
+        level.set(IVec3::new(dead_snake.3, dead_snake.4, dead_snake.5), None);
     }
 
     flashing.sort_by_key(|x| x.0);
@@ -829,6 +999,206 @@ pub fn handle_kill_snake(
     *dying_snakes = flashing;
 }
 
+// Paths keep snakes out of each other, so they only meet when one was already
+// headed for a spot another got to first or two go for the same spot at once.
+// Each move is checked once, right when it starts.
+pub fn detect_snake_collisions(
+    mut snakes: Query<(Entity, &mut Enemy, &Position)>,
+    mut snake_collision_event_writer: EventWriter<SnakeCollisionEvent>,
+) {
+    let others: Vec<(Entity, Position, Option<Position>, Vec<Position>)> = snakes
+        .iter()
+        .filter(|(_, enemy, _)| !enemy.is_dead)
+        .map(|(entity, enemy, position)| {
+            let target = enemy
+                .movement
+                .as_ref()
+                .map(|movement| Position::from_vec(movement.target));
+            let body = enemy
+                .body_positions
+                .iter()
+                .map(|body_position| Position::from_vec(body_position.translation))
+                .collect();
+            (entity, *position, target, body)
+        })
+        .collect();
+
+    let mut head_ons: Vec<(Entity, Entity)> = vec![];
+    for (entity, mut enemy, _) in snakes.iter_mut() {
+        if enemy.is_dead {
+            continue;
+        }
+
+        let target = match &mut enemy.movement {
+            Some(movement) if !movement.collision_checked => {
+                movement.collision_checked = true;
+                Position::from_vec(movement.target)
+            }
+            _ => continue,
+        };
+
+        for (other, position, other_target, body) in others.iter() {
+            if *other == entity {
+                continue;
+            }
+
+            // a moving head has already left its spot, which is body now
+            let collision = if *other_target == Some(target)
+                || (other_target.is_none() && *position == target)
+            {
+                Some(SnakeCollision::HeadOn)
+            } else {
+                body.iter()
+                    .position(|part| *part == target)
+                    .map(SnakeCollision::Body)
+            };
+
+            if let Some(collision) = collision {
+                if collision == SnakeCollision::HeadOn {
+                    // both snakes see this one, it only counts once
+                    let pair = (entity.min(*other), entity.max(*other));
+                    if head_ons.contains(&pair) {
+                        continue;
+                    }
+                    head_ons.push(pair);
+                }
+
+                snake_collision_event_writer.send(SnakeCollisionEvent {
+                    snake: entity,
+                    other: *other,
+                    collision,
+                });
+            }
+        }
+    }
+}
+
+pub fn handle_snake_collisions(
+    mut snake_collision_event_reader: EventReader<SnakeCollisionEvent>,
+    mut snakes: Query<(&mut Enemy, &mut Transform, &mut Position, &Children), Without<SnakeInnerMesh>>,
+    mut inner_meshes: Query<&mut Transform, (With<SnakeInnerMesh>, Without<Enemy>)>,
+    mut level: ResMut<Level>,
+    mut kill_snake_event_writer: EventWriter<KillSnakeEvent>,
+    mut body_part_writer: EventWriter<AddBodyPartEvent>,
+    mut lose_body_parts_event_writer: EventWriter<LoseBodyPartsEvent>,
+    game_assets: Res<GameAssets>,
+    mut audio: audio::GameAudio,
+) {
+    let rules = level.snake_rules();
+    // snakes killed here aren't marked dead until handle_kill_snake
+    let mut killed: Vec<Entity> = vec![];
+    // snakes that bit the end off another this tick
+    let mut biters: Vec<Entity> = vec![];
+    for event in snake_collision_event_reader.iter() {
+        if killed.contains(&event.snake) || killed.contains(&event.other) {
+            continue;
+        }
+
+        let (length, other_length) = match (snakes.get(event.snake), snakes.get(event.other)) {
+            (Ok((snake, ..)), Ok((other, ..))) if !snake.is_dead && !other.is_dead => {
+                (snake.length(), other.length())
+            }
+            _ => continue,
+        };
+
+        // (winner, loser, how many parts the winner gets)
+        let eaten = match event.collision {
+            SnakeCollision::HeadOn if rules.head_on_kills_both => {
+                println!("Snakes hit head on");
+                kill_snake_event_writer.send(KillSnakeEvent(event.snake));
+                kill_snake_event_writer.send(KillSnakeEvent(event.other));
+                killed.push(event.snake);
+                killed.push(event.other);
+                None
+            }
+            SnakeCollision::HeadOn if rules.longer_eats_shorter && length > other_length => {
+                Some((event.snake, event.other, other_length))
+            }
+            SnakeCollision::HeadOn if rules.longer_eats_shorter && other_length > length => {
+                Some((event.other, event.snake, length))
+            }
+            SnakeCollision::Body(_) if rules.longer_eats_shorter && length > other_length => {
+                Some((event.snake, event.other, other_length))
+            }
+            // right behind the head there'd be nothing left, so that's the whole snake
+            SnakeCollision::Body(0) if rules.tail_biting_steals => {
+                Some((event.snake, event.other, other_length))
+            }
+            SnakeCollision::Body(part) if rules.tail_biting_steals => {
+                biters.push(event.snake);
+                println!("Snake bit off {} parts", other_length.saturating_sub(part));
+                lose_body_parts_event_writer.send(LoseBodyPartsEvent {
+                    snake: event.other,
+                    from: part,
+                });
+                for _ in part..other_length {
+                    body_part_writer.send(AddBodyPartEvent { snake: event.snake });
+                }
+                None
+            }
+            _ => continue,
+        };
+
+        if let Some((winner, loser, parts)) = eaten {
+            println!("Snake ate a snake");
+            kill_snake_event_writer.send(KillSnakeEvent(loser));
+            killed.push(loser);
+            for _ in 0..parts {
+                body_part_writer.send(AddBodyPartEvent { snake: winner });
+            }
+        }
+        audio.play_sfx(&game_assets.bite_handle[0]);
+    }
+
+    // the bitten spot is free now, so the head goes right in instead of
+    // finishing the move into what was body a moment ago
+    for biter in biters {
+        if let Ok((mut enemy, mut transform, mut position, children)) = snakes.get_mut(biter) {
+            if let Some(movement) = &mut enemy.movement {
+                movement.current_movement_time = movement.finish_movement_time;
+                movement.current_rotation_time = movement.finish_rotation_time;
+                transform.translation = movement.target;
+                position.update_from_vec(movement.target);
+                for child in children.iter() {
+                    if let Ok(mut inner_mesh) = inner_meshes.get_mut(*child) {
+                        inner_mesh.rotation = movement.target_rotation;
+                    }
+                }
+                level.set(movement.target, Some(GameObject::new(biter, EntityType::EnemyHead)));
+            }
+        }
+    }
+}
+
+pub fn lose_body_parts(
+    mut commands: Commands,
+    mut lose_body_parts_event_reader: EventReader<LoseBodyPartsEvent>,
+    mut snakes: Query<&mut Enemy>,
+    teleporters: Query<&teleporter::Teleporter>,
+    mut level: ResMut<Level>,
+) {
+    for event in lose_body_parts_event_reader.iter() {
+        if let Ok(mut enemy) = snakes.get_mut(event.snake) {
+            if event.from >= enemy.body_parts.len() {
+                continue;
+            }
+
+            let lost_parts = enemy.body_parts.split_off(event.from);
+            let from = event.from.min(enemy.body_positions.len());
+            let lost_positions = enemy.body_positions.split_off(from);
+            for (body_part, body_position) in lost_parts.iter().zip(lost_positions.iter()) {
+                level.remove(body_position.translation, *body_part);
+                if let Some(teleporter) = teleporter_at(body_position.translation, &teleporters) {
+                    level.remove(teleporter.target, *body_part);
+                }
+            }
+            for body_part in lost_parts {
+                commands.entity(body_part).despawn_recursive();
+            }
+        }
+    }
+}
+
 pub fn detect_dude_on_electric_snake(
     snakes: Query<&Enemy>,
     dudes: Query<&Transform, With<dude::Dude>>,
@@ -1573,3 +1943,103 @@ pub fn debug_trigger_snake_death(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level::tests::level_info, simulation::Simulation};
+    use std::path::Path;
+
+    fn head_at(simulation: &mut Simulation, at: Position) -> Entity {
+        let mut snakes = simulation.app.world.query_filtered::<(Entity, &Position), With<Enemy>>();
+        snakes
+            .iter(&simulation.app.world)
+            .find(|(_, position)| **position == at)
+            .map(|(entity, _)| entity)
+            .unwrap()
+    }
+
+    #[test]
+    fn biting_moves_the_head_into_the_bitten_spot() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/data/levels.custom");
+        let mut levels = level::LevelsAsset::from_file(&path).unwrap();
+        // one snake heads along the top row toward the other's body, which
+        // lies across it with the head stuck against the bottom edge
+        let mut info = level_info(
+            "",
+            &[&["..S.....", "D.......", "W....S.."], &["########", "########", "########"]],
+        );
+        let biter_start = Position { x: 2, y: 1, z: 2 };
+        let bitten_start = Position { x: 0, y: 1, z: 5 };
+        info.snakes = vec![
+            SnakeSpawn {
+                position: biter_start,
+                facing: Some(Direction::Right),
+                length: Some(1),
+                speed: None,
+                is_electric: None,
+                // patrolling where they are keeps them put until they're taken over
+                brain: Some(snake_brain::BrainKind::Patrol(vec![biter_start])),
+                color: None,
+            },
+            SnakeSpawn {
+                position: bitten_start,
+                facing: Some(Direction::Down),
+                length: Some(2),
+                speed: None,
+                is_electric: None,
+                brain: Some(snake_brain::BrainKind::Patrol(vec![bitten_start])),
+                color: None,
+            },
+        ];
+        info.snake_rules.tail_biting_steals = true;
+        levels.levels[1] = info;
+
+        let mut simulation = Simulation::new(levels, 1, 0);
+        let biter = head_at(&mut simulation, biter_start);
+        let bitten = head_at(&mut simulation, bitten_start);
+        for snake in [biter, bitten] {
+            simulation.app.world.entity_mut(snake).insert(PlayerSnake::default());
+        }
+
+        // the body is in the way, so the biter stops right next to it
+        let next_to_body = Position { x: 2, y: 1, z: 4 };
+        for _ in 0..600 {
+            simulation.step();
+        }
+        assert_eq!(*simulation.app.world.get::<Position>(biter).unwrap(), next_to_body);
+
+        // as if it had started into the spot right as the body got there
+        let bitten_spot = Position { x: 2, y: 1, z: 5 };
+        {
+            let mut enemy = simulation.app.world.get_mut::<Enemy>(biter).unwrap();
+            enemy.movement = Some(SnakeMovement {
+                target: bitten_spot.to_vec(),
+                starting_from: next_to_body.to_vec(),
+                current_movement_time: 0.0,
+                finish_movement_time: enemy.speed,
+                start_rotation: Quat::IDENTITY,
+                target_rotation: Quat::IDENTITY,
+                current_rotation_time: 0.0,
+                finish_rotation_time: enemy.speed,
+                collision_checked: true,
+            });
+        }
+        simulation
+            .app
+            .world
+            .resource_mut::<bevy::ecs::event::Events<SnakeCollisionEvent>>()
+            .send(SnakeCollisionEvent {
+                snake: biter,
+                other: bitten,
+                collision: SnakeCollision::Body(1),
+            });
+        simulation.step();
+
+        assert_eq!(*simulation.app.world.get::<Position>(biter).unwrap(), bitten_spot);
+        let actor = simulation.level().get_cell(bitten_spot).actor.unwrap();
+        assert_eq!(actor.entity, biter);
+        assert_eq!(actor.entity_type, EntityType::EnemyHead);
+        assert_eq!(simulation.app.world.get::<Enemy>(bitten).unwrap().length(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{grid::GridCoord, level::Level, snake::SnakeTarget, Direction, Position};
+
+// How a snake picks where to go. path_find::update_path asks the snake's
+// brain for goals whenever the snake needs a new path and heads for the
+// first one it can find a path to. If none of them can be reached it just
+// moves a spot so it isn't stuck in place.
+pub trait SnakeBrain: Send + Sync {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    At(Position),
+    // a random standable spot, only picked if it's needed
+    Anywhere,
+}
+
+#[derive(Component)]
+pub struct Brain(pub Box<dyn SnakeBrain>);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Seen {
+    pub position: Position,
+    pub translation: Vec3,
+}
+
+pub struct SeenSnake {
+    pub entity: Entity,
+    pub head: Position,
+    pub body: Vec<Position>,
+}
+
+// everything a brain gets to know about the level this tick
+pub struct Senses<'a> {
+    pub level: &'a Level,
+    pub entity: Entity,
+    pub snake: Seen,
+    pub dude: Option<Seen>,
+    pub dude_facing: Option<Direction>,
+    pub foods: &'a [Seen],
+    pub flag: Option<Position>,
+    pub snakes: &'a [SeenSnake],
+    // spots other snakes are about to reach
+    pub claimed: &'a [Position],
+}
+
+impl<'a> Senses<'a> {
+    pub fn is_claimed(&self, position: &Position) -> bool {
+        self.claimed.iter().any(|claimed| claimed == position)
+    }
+
+    // close enough to bite
+    pub fn dude_in_reach(&self) -> Option<Position> {
+        self.dude
+            .filter(|dude| dude.translation.distance(self.snake.translation) <= 1.5)
+            .map(|dude| dude.position)
+    }
+}
+
+// what level files pick for each snake, see snake::SnakeSpawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BrainKind {
+    Normal,
+    OnlyFood,
+    OnlyDude,
+    OnlyRandom,
+    Ambush,
+    Patrol(Vec<Position>),
+    GuardFlag,
+    TailChase,
+}
+
+impl BrainKind {
+    pub fn build(&self) -> Box<dyn SnakeBrain> {
+        match self {
+            BrainKind::Normal => Box::new(Seeker::new(true, true, true)),
+            BrainKind::OnlyFood => Box::new(Seeker::new(true, false, false)),
+            BrainKind::OnlyDude => Box::new(Seeker::new(false, true, false)),
+            BrainKind::OnlyRandom => Box::new(Seeker::new(false, false, true)),
+            BrainKind::Ambush => Box::new(Ambush),
+            BrainKind::Patrol(waypoints) => Box::new(Patrol {
+                waypoints: waypoints.clone(),
+                next: 0,
+            }),
+            BrainKind::GuardFlag => Box::new(GuardFlag { next: 0 }),
+            BrainKind::TailChase => Box::new(TailChase),
+        }
+    }
+}
+
+impl From<SnakeTarget> for BrainKind {
+    fn from(target: SnakeTarget) -> Self {
+        match target {
+            SnakeTarget::Normal => BrainKind::Normal,
+            SnakeTarget::OnlyFood => BrainKind::OnlyFood,
+            SnakeTarget::OnlyDude => BrainKind::OnlyDude,
+            SnakeTarget::OnlyRandom => BrainKind::OnlyRandom,
+        }
+    }
+}
+
+// the original snake targeting: go for the dude when close, otherwise the
+// closest food nobody else is after, otherwise anywhere
+pub struct Seeker {
+    food: bool,
+    dude: bool,
+    random: bool,
+}
+
+impl Seeker {
+    pub fn new(food: bool, dude: bool, random: bool) -> Self {
+        Seeker { food, dude, random }
+    }
+}
+
+impl SnakeBrain for Seeker {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal> {
+        let mut goals = vec![];
+        let mut random = self.random;
+
+        if self.dude {
+            if let Some(dude) = senses.dude {
+                if senses.is_claimed(&dude.position) {
+                    // dude is claimed by another snake, so just seek random for a step
+                    random = true;
+                }
+
+                if (!self.food && !random) || senses.dude_in_reach().is_some() {
+                    goals.push(Goal::At(dude.position));
+                }
+            }
+        }
+
+        if self.food {
+            let mut closest_food: Option<&Seen> = None;
+            for food in senses.foods.iter() {
+                if senses.is_claimed(&food.position) {
+                    // go to next food but potentially seek random to fallback on if no food
+                    // is available
+                    random = true;
+                } else if closest_food.map_or(true, |closest| {
+                    closest.translation.distance(senses.snake.translation)
+                        > food.translation.distance(senses.snake.translation)
+                }) {
+                    closest_food = Some(food);
+                }
+            }
+
+            if let Some(food) = closest_food {
+                goals.push(Goal::At(food.position));
+            }
+        }
+
+        if random {
+            goals.push(Goal::Anywhere);
+        }
+
+        goals
+    }
+}
+
+// heads for the spot the dude is walking toward
+pub struct Ambush;
+
+const AMBUSH_DISTANCE: i32 = 3;
+
+impl SnakeBrain for Ambush {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal> {
+        let mut goals = vec![];
+        if let Some(dude) = senses.dude_in_reach() {
+            goals.push(Goal::At(dude));
+        }
+
+        if let (Some(dude), Some(facing)) = (senses.dude, senses.dude_facing) {
+            // the furthest spot ahead that's still in the level
+            let mut ahead = dude.position;
+            for _ in 0..AMBUSH_DISTANCE {
+                let next = ahead.neighbour(facing);
+                if !senses.level.is_inbounds(next) {
+                    break;
+                }
+                ahead = next;
+            }
+
+            if ahead != dude.position {
+                goals.push(Goal::At(ahead));
+            }
+            goals.push(Goal::At(dude.position));
+        }
+
+        goals.push(Goal::Anywhere);
+        goals
+    }
+}
+
+// walks the waypoints from the level file in order, over and over
+pub struct Patrol {
+    waypoints: Vec<Position>,
+    next: usize,
+}
+
+impl SnakeBrain for Patrol {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal> {
+        let mut goals = vec![];
+        if let Some(dude) = senses.dude_in_reach() {
+            goals.push(Goal::At(dude));
+        }
+
+        if !self.waypoints.is_empty() {
+            if self.waypoints[self.next] == senses.snake.position {
+                self.next = (self.next + 1) % self.waypoints.len();
+            }
+            goals.push(Goal::At(self.waypoints[self.next]));
+        }
+
+        goals.push(Goal::Anywhere);
+        goals
+    }
+}
+
+// circles the win flag and only goes after the dude when they get close to it
+pub struct GuardFlag {
+    next: usize,
+}
+
+const GUARD_RADIUS: i32 = 4;
+
+impl SnakeBrain for GuardFlag {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal> {
+        let flag = match senses.flag {
+            Some(flag) => flag,
+            None => return Seeker::new(true, true, true).goals(senses),
+        };
+
+        let mut goals = vec![];
+        if let Some(dude) = senses.dude {
+            let distance = (dude.position.x - flag.x).abs() + (dude.position.z - flag.z).abs();
+            if distance <= GUARD_RADIUS || senses.dude_in_reach().is_some() {
+                goals.push(Goal::At(dude.position));
+            }
+        }
+
+        let posts: Vec<Position> = [Direction::Up, Direction::Right, Direction::Down, Direction::Left]
+            .into_iter()
+            .map(|direction| flag.neighbour(direction).neighbour(direction))
+            .collect();
+        if posts[self.next] == senses.snake.position {
+            self.next = (self.next + 1) % posts.len();
+        }
+        for i in 0..posts.len() {
+            goals.push(Goal::At(posts[(self.next + i) % posts.len()]));
+        }
+
+        goals.push(Goal::Anywhere);
+        goals
+    }
+}
+
+// follows the end of the closest other snake, or its own when it's alone
+pub struct TailChase;
+
+impl SnakeBrain for TailChase {
+    fn goals(&mut self, senses: &Senses) -> Vec<Goal> {
+        let head = senses.snake.position;
+        let distance = |p: &Position| (p.x - head.x).abs() + (p.y - head.y).abs() + (p.z - head.z).abs();
+        let tail_of = |entity: Entity| {
+            senses
+                .snakes
+                .iter()
+                .find(|snake| snake.entity == entity)
+                .and_then(|snake| snake.body.last().copied())
+        };
+
+        let other_tail = senses
+            .snakes
+            .iter()
+            .filter(|snake| snake.entity != senses.entity)
+            .filter_map(|snake| snake.body.last().copied())
+            .min_by_key(distance);
+
+        let mut goals = vec![];
+        if let Some(tail) = other_tail.or_else(|| tail_of(senses.entity)) {
+            // the tail itself is snake, but the spot on top of it isn't
+            goals.push(Goal::At(tail.neighbour(Direction::Above)));
+        }
+
+        goals.push(Goal::Anywhere);
+        goals
+    }
+}
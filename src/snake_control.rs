@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    dude::{self, Dude, PlayerAction},
+    environment::{LevelReady, Shrink},
+    game_controller, game_time,
+    grid::GridCoord,
+    level::Level,
+    level_over::LevelOverEvent,
+    moveable,
+    path_find::PathFinder,
+    snake::{self, Enemy},
+    versus::{self, Versus},
+    win_flag::WinFlag,
+    AppState, Direction, Position,
+};
+
+// Snake mode hands one of the level's snakes to the player and has the
+// not-snake run away from it instead. Catching the dude ends the level the
+// same way reaching the flag does. Versus uses the same snake but leaves the
+// dude to a second player, see versus.
+pub struct SnakeControlPlugin;
+impl Plugin for SnakeControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnakeMode>()
+            .add_plugin(InputManagerPlugin::<SnakeAction>::default())
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(
+                        handle_controllers
+                            .label("handle_snake_input")
+                            .after("store_controller_inputs"),
+                    )
+                    .with_system(queue_turns.after("handle_snake_input")),
+            )
+            // with the rest of the game's moves so replays and the simulation match
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(take_control.before(snake::update_enemy))
+                    .with_system(flee.before(dude::player_input).before(snake::update_enemy))
+                    .with_system(catch_dude.after(snake::update_enemy)),
+            );
+    }
+}
+
+#[derive(Default)]
+pub struct SnakeMode {
+    pub active: bool,
+}
+
+// Turns are relative to the snake, not the camera. Left and Right turn
+// around the top of its head, Up and Down climb or dive along it.
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum SnakeAction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl SnakeAction {
+    pub const TURNS: [Self; 4] = [
+        SnakeAction::Up,
+        SnakeAction::Down,
+        SnakeAction::Left,
+        SnakeAction::Right,
+    ];
+
+    pub fn default_input_map() -> InputMap<SnakeAction> {
+        use SnakeAction::*;
+        let mut input_map = InputMap::default();
+
+        input_map.set_gamepad(Gamepad(0));
+
+        input_map.insert(Up, KeyCode::Up);
+        input_map.insert(Up, KeyCode::W);
+        input_map.insert(Up, GamepadButtonType::DPadUp);
+
+        input_map.insert(Down, KeyCode::Down);
+        input_map.insert(Down, KeyCode::S);
+        input_map.insert(Down, GamepadButtonType::DPadDown);
+
+        input_map.insert(Left, KeyCode::Left);
+        input_map.insert(Left, KeyCode::A);
+        input_map.insert(Left, GamepadButtonType::DPadLeft);
+
+        input_map.insert(Right, KeyCode::Right);
+        input_map.insert(Right, KeyCode::D);
+        input_map.insert(Right, GamepadButtonType::DPadRight);
+
+        input_map
+    }
+
+    // versus leaves the keyboard to the dude
+    pub fn versus_input_map() -> InputMap<SnakeAction> {
+        use SnakeAction::*;
+        let mut input_map = InputMap::default();
+
+        input_map.set_gamepad(Gamepad(versus::SNAKE_GAMEPAD));
+
+        input_map.insert(Up, GamepadButtonType::DPadUp);
+        input_map.insert(Down, GamepadButtonType::DPadDown);
+        input_map.insert(Left, GamepadButtonType::DPadLeft);
+        input_map.insert(Right, GamepadButtonType::DPadRight);
+
+        input_map
+    }
+}
+
+#[derive(Component, Default)]
+pub struct PlayerSnake {
+    // the last turn pressed, held until the snake finishes its current step
+    pub queued: Option<SnakeAction>,
+}
+
+// the dude while snake mode has it running on its own
+#[derive(Component)]
+pub struct Fleeing;
+
+// Where a player's snake goes next. It takes the queued turn if it can and
+// otherwise keeps going straight; None means it's stuck and waits.
+pub fn next_target(
+    enemy: &Enemy,
+    turn: Option<SnakeAction>,
+    from: &Position,
+    path_find: &PathFinder,
+) -> Option<Position> {
+    let heading = match turn {
+        Some(SnakeAction::Up) => enemy.up,
+        Some(SnakeAction::Down) => -enemy.up,
+        Some(SnakeAction::Left) => enemy.up.cross(enemy.forward),
+        Some(SnakeAction::Right) => enemy.forward.cross(enemy.up),
+        None => enemy.forward,
+    };
+
+    [heading, enemy.forward]
+        .into_iter()
+        .map(|heading| Position::from_vec((from.to_vec() + heading).round()))
+        // the spot right behind the head is the body, so this also stops it reversing
+        .find(|target| !enemy.is_in_vec(target.to_vec()) && path_find.can_move(from, target))
+}
+
+fn take_control(
+    mut commands: Commands,
+    snake_mode: Res<SnakeMode>,
+    versus: Res<Versus>,
+    level_ready: Res<LevelReady>,
+    player_snakes: Query<Entity, With<PlayerSnake>>,
+    snakes: Query<(Entity, &Enemy), Without<PlayerSnake>>,
+    dudes: Query<Entity, (With<Dude>, Without<Fleeing>)>,
+) {
+    if !snake_mode.active || !level_ready.0 {
+        return;
+    }
+
+    if player_snakes.iter().next().is_none() {
+        let snake = snakes
+            .iter()
+            .filter(|(_, enemy)| !enemy.is_dead)
+            .map(|(entity, _)| entity)
+            .min();
+        if let Some(snake) = snake {
+            let input_map = if versus.active {
+                SnakeAction::versus_input_map()
+            } else {
+                SnakeAction::default_input_map()
+            };
+            commands
+                .entity(snake)
+                .insert(PlayerSnake::default())
+                .insert_bundle(InputManagerBundle {
+                    input_map,
+                    action_state: ActionState::default(),
+                });
+        }
+    }
+
+    // in versus somebody else is playing the dude
+    if versus.active {
+        return;
+    }
+
+    for dude in dudes.iter() {
+        // the keys move the snake now, all that's left for the dude is pausing
+        let mut input_map = InputMap::default();
+        input_map.insert(PlayerAction::Pause, KeyCode::Escape);
+        commands.entity(dude).insert(input_map).insert(Fleeing);
+    }
+}
+
+fn handle_controllers(
+    controllers: Res<game_controller::GameController>,
+    versus: Res<Versus>,
+    mut players: Query<&mut ActionState<SnakeAction>, With<PlayerSnake>>,
+) {
+    for mut action_state in players.iter_mut() {
+        for (_, just_pressed) in controllers
+            .just_pressed
+            .iter()
+            .filter(|(gamepad, _)| versus::is_snake_gamepad(&versus, **gamepad))
+        {
+            if just_pressed.contains(&game_controller::GameButton::Left) {
+                action_state.release(SnakeAction::Left);
+                action_state.press(SnakeAction::Left);
+            }
+            if just_pressed.contains(&game_controller::GameButton::Right) {
+                action_state.release(SnakeAction::Right);
+                action_state.press(SnakeAction::Right);
+            }
+            if just_pressed.contains(&game_controller::GameButton::Up) {
+                action_state.release(SnakeAction::Up);
+                action_state.press(SnakeAction::Up);
+            }
+            if just_pressed.contains(&game_controller::GameButton::Down) {
+                action_state.release(SnakeAction::Down);
+                action_state.press(SnakeAction::Down);
+            }
+        }
+    }
+}
+
+fn queue_turns(mut snakes: Query<(&ActionState<SnakeAction>, &mut PlayerSnake)>) {
+    for (action_state, mut player_snake) in snakes.iter_mut() {
+        for action in SnakeAction::TURNS {
+            if action_state.just_pressed(action) {
+                player_snake.queued = Some(action);
+            }
+        }
+    }
+}
+
+// steps the dude to whichever spot gets it furthest from the player's snake,
+// staying away from the flag so the only way the level ends is getting caught
+fn flee(
+    level: Res<Level>,
+    mut dudes: Query<(&Position, &mut ActionState<PlayerAction>), With<Fleeing>>,
+    snakes: Query<&Position, With<PlayerSnake>>,
+    flag: Query<&Position, With<WinFlag>>,
+) {
+    let snake = match snakes.iter().next() {
+        Some(snake) => *snake,
+        None => return,
+    };
+    let flag = flag.iter().next().copied();
+    let distance = |p: &Position| (p.x - snake.x).abs() + (p.y - snake.y).abs() + (p.z - snake.z).abs();
+
+    for (position, mut action_state) in dudes.iter_mut() {
+        for action in PlayerAction::DIRECTIONS {
+            action_state.release(action);
+        }
+
+        let best = PlayerAction::DIRECTIONS
+            .into_iter()
+            .map(|action| {
+                let direction = match action {
+                    PlayerAction::Up => Direction::Up,
+                    PlayerAction::Down => Direction::Down,
+                    PlayerAction::Left => Direction::Left,
+                    _ => Direction::Right,
+                };
+                (action, position.neighbour(direction))
+            })
+            .filter(|(_, next)| {
+                level.is_enterable(*next)
+                    && level.is_standable(*next)
+                    && Some(*next) != flag
+            })
+            .max_by_key(|(_, next)| distance(next));
+
+        if let Some((action, next)) = best {
+            if distance(&next) > distance(position) {
+                action_state.press(action);
+            }
+        }
+    }
+}
+
+fn catch_dude(
+    mut commands: Commands,
+    versus: Res<Versus>,
+    mut level: ResMut<Level>,
+    snakes: Query<&Position, With<PlayerSnake>>,
+    dudes: Query<(Entity, &Position), With<Dude>>,
+    mut level_over_event_writer: EventWriter<LevelOverEvent>,
+    mut round_over_event_writer: EventWriter<versus::RoundOverEvent>,
+) {
+    for snake in snakes.iter() {
+        for (entity, dude) in dudes.iter() {
+            if snake == dude {
+                // taking Dude off and forgetting the head landing on it keeps
+                // this from also counting as a death
+                level.forget_player_death();
+                commands
+                    .entity(entity)
+                    .remove::<moveable::Moveable>()
+                    .remove::<Dude>()
+                    .insert(Shrink);
+                if versus.active {
+                    round_over_event_writer.send(versus::RoundOverEvent {
+                        winner: versus::Side::Snake,
+                    });
+                } else {
+                    level_over_event_writer.send(LevelOverEvent {});
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use bevy::prelude::*;
+
+use crate::{
+    game_time::{self, GameTime},
+    level::Level, level_over::LevelOverEvent, level_pack::LevelPacks, level_select, menus,
+    save_game, save_game::SaveGame, snake_control::SnakeMode, ui::text_size, versus::Versus,
+    AppState,
+};
+
+// Times a full run of the shipped levels, level 1 through the last one. The
+// clock stops on the pause menu, level titles and score screens. Each beaten
+// level is a split, and a finished run that beats the personal best replaces
+// it in the save game. Every finished run gets written out to splits.txt.
+pub struct SpeedrunPlugin;
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Speedrun>()
+            // every state it counts in, so not under on_update
+            .add_system_to_stage(game_time::FixedUpdateStage, tick_run)
+            // the level is over right as the state changes, so this always runs
+            .add_system(record_split)
+            .add_system_set(SystemSet::on_enter(AppState::LevelTitle).with_system(start_run))
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(abandon_run))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(update_timer_text),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct SpeedrunText;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Speedrun {
+    pub running: bool,
+    // seconds since the run started, not counting stopped time
+    pub time: f32,
+    // the run time as each level was beaten, the first is level 1
+    pub splits: Vec<f32>,
+    // the personal best's splits when the run started
+    pub best_run: Vec<f32>,
+}
+
+impl Speedrun {
+    // running, or finished and still on the last level
+    pub fn is_showing(&self) -> bool {
+        self.running || !self.splits.is_empty()
+    }
+
+    pub fn start(&mut self, best_run: &[f32]) {
+        *self = Speedrun {
+            running: true,
+            best_run: best_run.to_vec(),
+            ..Default::default()
+        };
+    }
+
+    // the level being played in the run
+    pub fn current_level(&self) -> usize {
+        self.splits.len() + 1
+    }
+
+    // returns true once the last level's split is in
+    pub fn split(&mut self, level: usize, last_level: usize) -> bool {
+        if !self.running || level != self.current_level() {
+            return false;
+        }
+
+        self.splits.push(self.time);
+        if level == last_level {
+            self.running = false;
+            return true;
+        }
+
+        false
+    }
+
+    // Ahead is negative. Shows how the last split went until the current level
+    // runs past the best's split for it, then counts up live.
+    pub fn delta(&self) -> Option<f32> {
+        let index = self.splits.len();
+        if let Some(best) = self.best_run.get(index) {
+            if self.running && self.time > *best {
+                return Some(self.time - best);
+            }
+        }
+
+        let index = index.checked_sub(1)?;
+        Some(self.splits[index] - self.best_run.get(index)?)
+    }
+
+    pub fn is_personal_best(&self) -> bool {
+        match (self.splits.last(), self.best_run.last()) {
+            // a best from when there were a different number of levels still has to be beaten
+            (Some(time), Some(best)) => time < best,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    // one line per level: level, split, segment, delta and title, tab separated
+    pub fn export(&self, level: &Level) -> String {
+        let mut lines = vec!["# level\tsplit\tsegment\tdelta\ttitle".to_string()];
+        let mut previous = 0.0;
+        for (i, split) in self.splits.iter().enumerate() {
+            let level_number = i + 1;
+            let delta = self
+                .best_run
+                .get(i)
+                .map_or("-".to_string(), |best| format_delta(split - best));
+            let title = level
+                .level_infos()
+                .get(level_number)
+                .map_or(String::new(), |info| info.title.replace('\n', " "));
+
+            lines.push(format!(
+                "{}\t{}\t{}\t{}\t{}",
+                level_number,
+                level_select::format_time(*split),
+                level_select::format_time(split - previous),
+                delta,
+                title
+            ));
+            previous = *split;
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    // next to the save game, so in localStorage on the web
+    pub fn save_export(&self, level: &Level) -> Result<(), anyhow::Error> {
+        save_game::storage::write("splits.txt", &self.export(level))
+    }
+}
+
+pub fn format_delta(delta: f32) -> String {
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    format!("{}{}", sign, level_select::format_time(delta.abs()))
+}
+
+// Starting on level 1 of the shipped levels, from Start or the level select,
+// is the start of a run. Going to LevelTitle from the title screen leaves
+// current_level on the level before the one being started.
+fn start_run(
+    mut speedrun: ResMut<Speedrun>,
+    level: Res<Level>,
+    level_packs: Res<LevelPacks>,
+    snake_mode: Res<SnakeMode>,
+    versus: Res<Versus>,
+    save_game: Res<SaveGame>,
+) {
+    if level.current_level != 0 {
+        return;
+    }
+
+    // endless and classic count as packs, they swap in levels of their own
+    if level_packs.selected.is_some() || snake_mode.active || versus.active {
+        *speedrun = Speedrun::default();
+        return;
+    }
+
+    speedrun.start(&save_game.best_run);
+}
+
+fn abandon_run(mut speedrun: ResMut<Speedrun>) {
+    *speedrun = Speedrun::default();
+}
+
+fn tick_run(mut speedrun: ResMut<Speedrun>, state: Res<State<AppState>>, time: Res<GameTime>) {
+    if !speedrun.running {
+        return;
+    }
+
+    match state.current() {
+        AppState::InGame
+        | AppState::ChangingLevel
+        | AppState::ResetLevel
+        | AppState::RestartLevel => speedrun.time += time.delta_seconds(),
+        _ => (),
+    }
+}
+
+fn record_split(
+    mut level_over_events: EventReader<LevelOverEvent>,
+    mut speedrun: ResMut<Speedrun>,
+    mut save_game: ResMut<SaveGame>,
+    level: Res<Level>,
+) {
+    if level_over_events.iter().count() == 0 {
+        return;
+    }
+
+    if !speedrun.split(level.current_level, level.last_level()) {
+        return;
+    }
+
+    println!("Run finished in {}", level_select::format_time(speedrun.time));
+    if let Err(e) = speedrun.save_export(&level) {
+        println!("Couldn't export splits: {}", e);
+    }
+
+    if speedrun.is_personal_best() {
+        save_game.best_run = speedrun.splits.clone();
+        if let Err(e) = save_game.save() {
+            println!("Couldn't save the game: {}", e);
+        }
+    }
+}
+
+// called from environment::create_hud, only shows up during a run
+pub fn spawn_timer_text(
+    commands: &mut Commands,
+    font: Handle<Font>,
+    text_scaler: &text_size::TextScaler,
+    marker: impl Component,
+) {
+    let style = TextStyle {
+        font,
+        font_size: text_scaler.scale(menus::FOLLOW_FONT_SIZE),
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    right: Val::Px(15.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: "".to_string(),
+                        style,
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(marker)
+        .insert(SpeedrunText);
+}
+
+fn update_timer_text(speedrun: Res<Speedrun>, mut texts: Query<&mut Text, With<SpeedrunText>>) {
+    for mut text in texts.iter_mut() {
+        if !speedrun.is_showing() {
+            text.sections[0].value = "".to_string();
+            text.sections[1].value = "".to_string();
+            continue;
+        }
+
+        text.sections[0].value = level_select::format_time(speedrun.time);
+        match speedrun.delta() {
+            Some(delta) => {
+                text.sections[1].value = format!(" {}", format_delta(delta));
+                text.sections[1].style.color = if delta < 0.0 {
+                    Color::rgb(0.4, 0.9, 0.4)
+                } else {
+                    Color::rgb(0.9, 0.4, 0.4)
+                };
+            }
+            None => text.sections[1].value = "".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with_splits(splits: &[f32], best_run: &[f32]) -> Speedrun {
+        let mut speedrun = Speedrun::default();
+        speedrun.start(best_run);
+        for (i, split) in splits.iter().enumerate() {
+            speedrun.time = *split;
+            speedrun.split(i + 1, splits.len());
+        }
+        speedrun
+    }
+
+    #[test]
+    fn splits_follow_the_levels_in_order() {
+        let mut speedrun = Speedrun::default();
+        speedrun.start(&[]);
+        speedrun.time = 10.0;
+
+        assert!(!speedrun.split(2, 3));
+        assert!(speedrun.splits.is_empty());
+        assert!(!speedrun.split(1, 3));
+        assert_eq!(speedrun.current_level(), 2);
+
+        speedrun.time = 20.0;
+        assert!(!speedrun.split(2, 3));
+        speedrun.time = 30.0;
+        assert!(speedrun.split(3, 3));
+        assert!(!speedrun.running);
+        assert_eq!(speedrun.splits, vec![10.0, 20.0, 30.0]);
+
+        // nothing more once it's over
+        assert!(!speedrun.split(4, 3));
+    }
+
+    #[test]
+    fn delta_compares_against_the_best() {
+        let mut speedrun = Speedrun::default();
+        speedrun.start(&[10.0, 25.0]);
+        assert_eq!(speedrun.delta(), None);
+
+        speedrun.time = 8.0;
+        speedrun.split(1, 2);
+        assert_eq!(speedrun.delta(), Some(-2.0));
+
+        // behind the best's split for the level being played, so it counts up
+        speedrun.time = 26.0;
+        assert_eq!(speedrun.delta(), Some(1.0));
+    }
+
+    #[test]
+    fn personal_best_has_to_be_faster() {
+        assert!(run_with_splits(&[10.0, 20.0], &[]).is_personal_best());
+        assert!(run_with_splits(&[10.0, 20.0], &[12.0, 21.0]).is_personal_best());
+        assert!(!run_with_splits(&[10.0, 22.0], &[12.0, 21.0]).is_personal_best());
+        assert!(!run_with_splits(&[], &[12.0, 21.0]).is_personal_best());
+    }
+
+    #[test]
+    fn personal_best_from_a_different_number_of_levels() {
+        assert!(!run_with_splits(&[10.0, 20.0, 30.0], &[12.0, 21.0]).is_personal_best());
+        assert!(run_with_splits(&[10.0, 20.0], &[12.0, 21.0, 35.0]).is_personal_best());
+        assert!(!run_with_splits(&[10.0, 40.0], &[12.0, 21.0, 35.0]).is_personal_best());
+    }
+
+    #[test]
+    fn export_has_a_line_per_split() {
+        let speedrun = run_with_splits(&[10.0, 75.5], &[12.0]);
+        let export = speedrun.export(&Level::new());
+
+        assert_eq!(
+            export,
+            "# level\tsplit\tsegment\tdelta\ttitle\n\
+             1\t0:10.0\t0:10.0\t-0:02.0\t\n\
+             2\t1:15.5\t1:05.5\t-\t\n"
+        );
+    }
+}
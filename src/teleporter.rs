@@ -1,9 +1,9 @@
 use crate::{Direction, Position};
 use bevy::prelude::*;
 use bevy::reflect::TypeUuid;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, TypeUuid, Component)]
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid, Component)]
 #[uuid = "00aadc56-aa9c-4543-8640-a018b74b5052"] // this needs to be actually generated
 pub struct Teleporter {
     pub position: Position, // starting spot
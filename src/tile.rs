@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Everything that can be placed in a level grid. The numeric codes are what
+// the original level format uses, so the order here can't change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Tile {
+    Empty,
+    Block,
+    HoldableBlock,
+    WinFlag,
+    BonusFood,
+    Snake,
+    ShadowFood,
+    PathfindIgnore,
+    AltBlock,
+    UnstandableBlock,
+    ElectricSnake,
+    Dude,
+    Food,
+    PathfindIgnoreFood,
+    InvisibleBlock,
+}
+
+pub struct TileInfo {
+    pub tile: Tile,
+    pub symbol: char,
+    pub description: &'static str,
+}
+
+// symbol is what the tile is written as in a level's `layers` unless the
+// level's legend says otherwise
+pub static TILES: [TileInfo; 15] = [
+    TileInfo { tile: Tile::Empty,              symbol: '.', description: "empty" },
+    TileInfo { tile: Tile::Block,              symbol: '#', description: "unmovable block" },
+    TileInfo { tile: Tile::HoldableBlock,      symbol: 'B', description: "movable block" },
+    TileInfo { tile: Tile::WinFlag,            symbol: 'W', description: "win flag spawn point" },
+    TileInfo { tile: Tile::BonusFood,          symbol: '+', description: "bonus food spawn point" },
+    TileInfo { tile: Tile::Snake,              symbol: 'S', description: "snake spawn point" },
+    TileInfo { tile: Tile::ShadowFood,         symbol: 'f', description: "food with shadow spawn point" },
+    TileInfo { tile: Tile::PathfindIgnore,     symbol: '_', description: "path-finding ignore" },
+    TileInfo { tile: Tile::AltBlock,           symbol: '%', description: "unmovable block different color" },
+    TileInfo { tile: Tile::UnstandableBlock,   symbol: 'X', description: "unmovable block that food can't spawn on" },
+    TileInfo { tile: Tile::ElectricSnake,      symbol: 'E', description: "electric snake spawn point" },
+    TileInfo { tile: Tile::Dude,               symbol: 'D', description: "dude spawn point" },
+    TileInfo { tile: Tile::Food,               symbol: 'F', description: "food without shadow spawn point" },
+    TileInfo { tile: Tile::PathfindIgnoreFood, symbol: '*', description: "path-finding ignore with food" },
+    TileInfo { tile: Tile::InvisibleBlock,     symbol: 'I', description: "invisible block" },
+];
+
+impl Tile {
+    pub fn from_code(code: usize) -> Option<Tile> {
+        TILES.get(code).map(|info| info.tile)
+    }
+
+    pub fn code(self) -> usize {
+        self as usize
+    }
+
+    pub fn info(self) -> &'static TileInfo {
+        &TILES[self.code()]
+    }
+
+    pub fn is_food(self) -> bool {
+        matches!(
+            self,
+            Tile::BonusFood | Tile::ShadowFood | Tile::Food | Tile::PathfindIgnoreFood
+        )
+    }
+
+    // nothing's in the way, PathfindIgnore only steers snakes' paths around it
+    pub fn is_open(self) -> bool {
+        matches!(self, Tile::Empty | Tile::PathfindIgnore)
+    }
+
+    // things food and the dude can stand on top of
+    pub fn is_standable(self) -> bool {
+        matches!(
+            self,
+            Tile::Block | Tile::HoldableBlock | Tile::AltBlock | Tile::InvisibleBlock
+        )
+    }
+}
+
+// the default symbols with a level's own legend on top
+pub fn legend(overrides: &BTreeMap<char, Tile>) -> BTreeMap<char, Tile> {
+    let mut legend: BTreeMap<char, Tile> = TILES.iter().map(|info| (info.symbol, info.tile)).collect();
+    legend.extend(overrides.iter().map(|(symbol, tile)| (*symbol, *tile)));
+    legend
+}
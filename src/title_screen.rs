@@ -1,17 +1,21 @@
 use crate::{
     asset_loading, assets::GameAssets, audio::GameAudio, cleanup, game_controller, menus,
-    ui::text_size, AppState, menus::HOVERED_BUTTON, menus::NORMAL_BUTTON, score
+    ui::text_size, AppState, menus::HOVERED_BUTTON, menus::NORMAL_BUTTON, score, level,
+    level_pack, level_generator, snake_control, versus, classic, save_game, game_rng::GameRng,
 };
 use bevy::app::AppExit;
 use bevy::ecs::event::Events;
+use bevy::ecs::system::SystemParam;
 use bevy_utils::Instant;
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
+use rand::Rng;
 
 pub struct TitlePlugin;
 impl Plugin for TitlePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(InputManagerPlugin::<MenuAction>::default())
+            .init_resource::<TitleMenu>()
             .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(setup))
             .add_system_set(
                 SystemSet::on_update(AppState::MainMenu)
@@ -37,6 +41,59 @@ struct CleanupMarker;
 pub struct BylineText;
 #[derive(Component)]
 pub struct MenuButton;
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TitlePage {
+    Main,
+    Packs,
+}
+
+pub struct TitleMenu {
+    pub page: TitlePage,
+}
+
+impl Default for TitleMenu {
+    fn default() -> Self {
+        TitleMenu {
+            page: TitlePage::Main,
+        }
+    }
+}
+
+impl TitleMenu {
+    fn labels(&self, level_packs: &level_pack::LevelPacks, save_game: &save_game::SaveGame) -> Vec<String> {
+        match self.page {
+            TitlePage::Packs => {
+                let mut labels: Vec<String> = level_packs.packs.iter().map(|pack| pack.name.clone()).collect();
+                labels.push("Back".to_string());
+                labels
+            }
+            TitlePage::Main => {
+                let mut labels = vec![];
+                if save_game.has_progress() {
+                    labels.push("Continue".to_string());
+                }
+                labels.extend(
+                    [
+                        "Start",
+                        "Level Select",
+                        "Endless",
+                        "Snake Mode",
+                        "Versus",
+                        "Classic",
+                        "Level Packs",
+                        "Quit",
+                    ]
+                    .iter()
+                    .map(|label| label.to_string()),
+                );
+                labels
+            }
+        }
+    }
+}
 
 #[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub enum MenuAction {
@@ -89,17 +146,23 @@ fn setup(
     game_assets: Res<GameAssets>,
     mut audio: GameAudio,
     text_scaler: text_size::TextScaler,
+    mut title_menu: ResMut<TitleMenu>,
+    mut level_packs: ResMut<level_pack::LevelPacks>,
+    save_game: Res<save_game::SaveGame>,
+    menu_actions: Query<Entity, With<ActionState<MenuAction>>>,
 ) {
     commands
         .spawn_bundle(UiCameraBundle::default())
         .insert(CleanupMarker);
 
-    // TODO: move this into main or something, we only want one of these
-    commands
-        .spawn_bundle(InputManagerBundle {
-            input_map: MenuAction::default_input_map(),
-            action_state: ActionState::default(),
-        });
+    // TODO: move this into main or something, for now only the first visit makes one
+    if menu_actions.is_empty() {
+        commands
+            .spawn_bundle(InputManagerBundle {
+                input_map: MenuAction::default_input_map(),
+                action_state: ActionState::default(),
+            });
+    }
 
     commands
         .spawn_bundle(TextBundle {
@@ -127,10 +190,23 @@ fn setup(
         .insert(BylineText)
         .insert(CleanupMarker);
 
+    title_menu.page = TitlePage::Main;
+    level_packs.refresh();
+    spawn_menu(&mut commands, &game_assets, &text_scaler, &title_menu.labels(&level_packs, &save_game));
+
+    audio.play_bgm(&game_assets.intro_handle);
+}
+
+fn spawn_menu(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    text_scaler: &text_size::TextScaler,
+    labels: &[String],
+) {
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Percent(20.0), Val::Percent(15.0)),
+                size: Size::new(Val::Percent(20.0), Val::Percent(7.5 * labels.len() as f32)),
                 position_type: PositionType::Relative,
                 justify_content: JustifyContent::Center,
                 flex_direction: FlexDirection::ColumnReverse,
@@ -148,81 +224,162 @@ fn setup(
         })
         .insert(CleanupMarker)
         .insert(MenuButton)
+        .insert(MenuRoot)
         .with_children(|parent| {
-            parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        position_type: PositionType::Relative,
-                        margin: Rect::all(Val::Auto),
-                        size: Size::new(Val::Percent(100.0), Val::Percent(40.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..Default::default()
-                    },
-                    visibility: Visibility {
-                        is_visible: false,
-                    },
-                    color: NORMAL_BUTTON.into(),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::with_section(
-                            "Start",
-                            TextStyle {
-                                font: game_assets.font.clone(),
-                                font_size: text_scaler.scale(menus::BUTTON_LABEL_FONT_SIZE),
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                            Default::default(),
-                        ),
+            for label in labels.iter() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Relative,
+                            margin: Rect::all(Val::Auto),
+                            size: Size::new(Val::Percent(100.0), Val::Percent(80.0 / labels.len() as f32)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
                         visibility: Visibility {
                             is_visible: false,
                         },
+                        color: NORMAL_BUTTON.into(),
                         ..Default::default()
                     })
-                    .insert(MenuButton);
-                })
-                .insert(MenuButton);
-
-            parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(40.0)),
-                        margin: Rect::all(Val::Auto),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        position_type: PositionType::Relative,
-                        ..Default::default()
-                    },
-                    visibility: Visibility {
-                        is_visible: false,
-                    },
-                    color: NORMAL_BUTTON.into(),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::with_section(
-                            "Quit",
-                            TextStyle {
-                                font: game_assets.font.clone(),
-                                font_size: text_scaler.scale(menus::BUTTON_LABEL_FONT_SIZE),
-                                color: Color::rgb(0.9, 0.9, 0.9),
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                label.to_string(),
+                                TextStyle {
+                                    font: game_assets.font.clone(),
+                                    font_size: text_scaler.scale(menus::BUTTON_LABEL_FONT_SIZE),
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                                Default::default(),
+                            ),
+                            visibility: Visibility {
+                                is_visible: false,
                             },
-                            Default::default(),
-                        ),
-                        visibility: Visibility {
-                            is_visible: false,
-                        },
-                        ..Default::default()
+                            ..Default::default()
+                        })
+                        .insert(MenuButton);
                     })
                     .insert(MenuButton);
-                })
-                .insert(MenuButton);
+            }
         });
+}
 
-    audio.play_bgm(&game_assets.intro_handle);
+// Which levels get played and how, the title screen and level select start
+// everything through this
+#[derive(SystemParam)]
+pub struct GameModes<'w, 's> {
+    pub level: ResMut<'w, level::Level>,
+    pub level_packs: ResMut<'w, level_pack::LevelPacks>,
+    pub endless: ResMut<'w, level_generator::Endless>,
+    pub snake_mode: ResMut<'w, snake_control::SnakeMode>,
+    pub versus: ResMut<'w, versus::Versus>,
+    pub classic: ResMut<'w, classic::Classic>,
+
+    #[system_param(ignore)]
+    phantom: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> GameModes<'w, 's> {
+    // the shipped levels with every other mode switched off
+    pub fn play_shipped(&mut self) {
+        self.level_packs.selected = None;
+        self.endless.active = false;
+        self.snake_mode.active = false;
+        self.versus.active = false;
+        self.classic.active = false;
+    }
+
+    // the shipped levels, starting from the given one
+    pub fn start_at(&mut self, level: usize) {
+        self.play_shipped();
+        // LevelTitle and ChangingLevel move on to the level after this one,
+        // level 0 isn't one that can be started on
+        self.level.current_level = level.max(1) - 1;
+    }
+}
+
+#[derive(SystemParam)]
+struct PackMenu<'w, 's> {
+    commands: Commands<'w, 's>,
+    text_scaler: text_size::TextScaler<'w, 's>,
+    menu_roots: Query<'w, 's, Entity, With<MenuRoot>>,
+    title_menu: ResMut<'w, TitleMenu>,
+    modes: GameModes<'w, 's>,
+    save_game: Res<'w, save_game::SaveGame>,
+    rng: ResMut<'w, GameRng>,
+}
+
+impl<'w, 's> PackMenu<'w, 's> {
+    fn show(&mut self, page: TitlePage, game_assets: &GameAssets) {
+        if page == TitlePage::Packs {
+            if let Some(directory) = level_pack::LevelPacks::directory() {
+                println!("Looking for level packs in {:?}", directory);
+            }
+        }
+
+        self.title_menu.page = page;
+        for entity in self.menu_roots.iter() {
+            self.commands.entity(entity).despawn_recursive();
+        }
+        let labels = self.title_menu.labels(&self.modes.level_packs, &self.save_game);
+        spawn_menu(&mut self.commands, game_assets, &self.text_scaler, &labels);
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.title_menu.labels(&self.modes.level_packs, &self.save_game)
+    }
+
+    // swaps the pack's levels in, returns false if it couldn't be read
+    fn select_pack(&mut self, index: usize) -> bool {
+        match self.modes.level_packs.load(index) {
+            Ok(levels) => {
+                self.modes.endless.active = false;
+                self.modes.snake_mode.active = false;
+                self.modes.versus.active = false;
+                self.modes.classic.active = false;
+                self.modes.level.current_level = levels.start_level;
+                self.modes.level.load_stored_levels(levels.clone());
+                self.modes.level_packs.selected = Some(levels);
+                true
+            }
+            Err(e) => {
+                println!("Couldn't load level pack: {}", e);
+                false
+            }
+        }
+    }
+
+    fn start_endless(&mut self) {
+        let levels = self.modes.endless.start(self.rng.gen());
+        self.modes.snake_mode.active = false;
+        self.modes.versus.active = false;
+        self.modes.classic.active = false;
+        self.modes.level.current_level = levels.start_level;
+        self.modes.level.load_stored_levels(levels.clone());
+        self.modes.level_packs.selected = Some(levels);
+    }
+
+    fn start_classic(&mut self) {
+        let levels = self.modes.classic.start();
+        self.modes.endless.active = false;
+        self.modes.versus.active = false;
+        // classic steers the snake its own way but still needs one handed to the player
+        self.modes.snake_mode.active = true;
+        self.modes.level.current_level = levels.start_level;
+        self.modes.level.load_stored_levels(levels.clone());
+        self.modes.level_packs.selected = Some(levels);
+    }
+
+    // versus plays the shipped levels marked for it
+    fn start_versus(&mut self) -> bool {
+        self.modes.level_packs.selected = None;
+        self.modes.endless.active = false;
+        self.modes.snake_mode.active = true;
+        self.modes.classic.active = false;
+        self.modes.versus.start(&mut self.modes.level)
+    }
 }
 
 fn update_menu_buttons(
@@ -239,6 +396,7 @@ fn update_menu_buttons(
     mut menu_buttons: Query<&mut Visibility, With<MenuButton>>,
     time: Res<Time>,
     mut score: ResMut<score::Score>,
+    mut pack_menu: PackMenu,
 ) {
     for mut byline in bylines.iter_mut() {
         let a = byline.sections[0].style.color.a();
@@ -301,16 +459,87 @@ fn update_menu_buttons(
         }
     }
 
-    if pressed_button {
-        println!("title screen received pressed");
-        if *selected_button == 0 {
+    if !pressed_button {
+        return;
+    }
+
+    println!("title screen received pressed");
+    let labels = pack_menu.labels();
+    let label = match labels.get(*selected_button) {
+        Some(label) => label.as_str(),
+        None => return,
+    };
+
+    match pack_menu.title_menu.page {
+        TitlePage::Packs => {
             audio.play_sfx(&game_assets.blip);
-            *score = score::Score::new();
-            app_state.set(AppState::LevelTitle).unwrap();
-        }
-        if *selected_button == 1 {
-            exit.send(AppExit);
+            if *selected_button < pack_menu.modes.level_packs.packs.len() {
+                if pack_menu.select_pack(*selected_button) {
+                    *score = score::Score::new();
+                    app_state.set(AppState::LevelTitle).unwrap();
+                }
+            } else {
+                *selected_button = 0;
+                pack_menu.show(TitlePage::Main, &game_assets);
+            }
         }
+        TitlePage::Main => match label {
+            "Continue" => {
+                audio.play_sfx(&game_assets.blip);
+                let unlocked_level = pack_menu.save_game.unlocked_level.max(1);
+                pack_menu.modes.start_at(unlocked_level);
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+            "Start" => {
+                audio.play_sfx(&game_assets.blip);
+                pack_menu.modes.play_shipped();
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+            "Level Select" => {
+                audio.play_sfx(&game_assets.blip);
+                app_state.set(AppState::LevelSelect).unwrap();
+            }
+            "Endless" => {
+                audio.play_sfx(&game_assets.blip);
+                pack_menu.start_endless();
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+            "Snake Mode" => {
+                // the shipped levels, but playing as the snake
+                audio.play_sfx(&game_assets.blip);
+                pack_menu.modes.play_shipped();
+                pack_menu.modes.snake_mode.active = true;
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+            "Versus" => {
+                audio.play_sfx(&game_assets.blip);
+                if pack_menu.start_versus() {
+                    *score = score::Score::new();
+                    app_state.set(AppState::LevelTitle).unwrap();
+                } else {
+                    println!("There aren't any versus levels");
+                }
+            }
+            "Classic" => {
+                audio.play_sfx(&game_assets.blip);
+                pack_menu.start_classic();
+                *score = score::Score::new();
+                app_state.set(AppState::LevelTitle).unwrap();
+            }
+            "Level Packs" => {
+                audio.play_sfx(&game_assets.blip);
+                *selected_button = 0;
+                pack_menu.show(TitlePage::Packs, &game_assets);
+            }
+            "Quit" => {
+                exit.send(AppExit);
+            }
+            _ => (),
+        },
     }
 }
 
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+// Where player files (level packs, saves) live. NOT_SNAKE_DATA_DIR overrides
+// the platform default.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn directory() -> Option<PathBuf> {
+    if let Some(directory) = std::env::var_os("NOT_SNAKE_DATA_DIR") {
+        return Some(PathBuf::from(directory));
+    }
+
+    let base = if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(directory) => PathBuf::from(directory),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+        }
+    };
+
+    Some(base.join("not_snake"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn directory() -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera,
+    dude,
+    environment,
+    game_time::{self, GameTime},
+    level::Level,
+    level_over::LevelOverEvent,
+    moveable,
+    score::Score,
+    snake::Enemy,
+    AppState,
+};
+
+// Local versus for two gamepads: the first plays the dude and the second
+// plays a snake, on the levels the level pack lists in versus_levels. The
+// snake takes a round by getting the dude, the dude by reaching the flag or
+// lasting until the round timer runs out.
+pub struct VersusPlugin;
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Versus>()
+            .add_event::<RoundOverEvent>()
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(start_round))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(show_round_time.after(environment::update_hud_text_position)),
+            )
+            .add_system_set_to_stage(
+                game_time::FixedUpdateStage,
+                SystemSet::on_update(AppState::InGame).with_system(end_round),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(stop_death_animations));
+    }
+}
+
+pub const DUDE_GAMEPAD: usize = 0;
+pub const SNAKE_GAMEPAD: usize = 1;
+pub const BEST_OF: usize = 5;
+pub const ROUND_SECONDS: f32 = 60.0;
+// how long the end of a round stays up before the score shows
+const ROUND_END_SECONDS: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Dude,
+    Snake,
+}
+
+pub struct RoundOverEvent {
+    pub winner: Side,
+}
+
+pub struct Versus {
+    pub active: bool,
+    pub best_of: usize,
+    pub levels: Vec<usize>,
+    // rounds finished so far
+    pub round: usize,
+    pub round_timer: Timer,
+    round_over: bool,
+    round_end_timer: Timer,
+}
+
+impl Default for Versus {
+    fn default() -> Self {
+        Versus {
+            active: false,
+            best_of: BEST_OF,
+            levels: vec![],
+            round: 0,
+            round_timer: Timer::from_seconds(ROUND_SECONDS, false),
+            round_over: false,
+            round_end_timer: Timer::from_seconds(ROUND_END_SECONDS, false),
+        }
+    }
+}
+
+impl Versus {
+    // sets the level up for the first round, false if there's nothing to play
+    pub fn start(&mut self, level: &mut Level) -> bool {
+        // rounds start from the level before theirs, so 0 can't be played either
+        let level_count = level.level_infos().len();
+        let levels: Vec<usize> = level
+            .versus_levels()
+            .iter()
+            .copied()
+            .filter(|i| (1..level_count).contains(i))
+            .collect();
+        if levels.len() != level.versus_levels().len() {
+            println!("Skipping versus levels that aren't playable: {:?}", level.versus_levels());
+        }
+        if levels.is_empty() {
+            return false;
+        }
+
+        *self = Versus {
+            active: true,
+            levels,
+            ..Versus::default()
+        };
+        level.current_level = self.levels[0] - 1;
+        true
+    }
+
+    pub fn wins_needed(&self) -> usize {
+        self.best_of / 2 + 1
+    }
+
+    pub fn match_winner(&self, score: &Score) -> Option<Side> {
+        if score.dude_rounds >= self.wins_needed() {
+            Some(Side::Dude)
+        } else if score.snake_rounds >= self.wins_needed() {
+            Some(Side::Snake)
+        } else {
+            None
+        }
+    }
+
+    pub fn score_text(&self, score: &Score) -> String {
+        let name = |side| match side {
+            Side::Dude => "not snake",
+            Side::Snake => "snake",
+        };
+
+        let tally = format!(
+            "not snake {} - {} snake\nbest of {}",
+            score.dude_rounds, score.snake_rounds, self.best_of
+        );
+        match (self.match_winner(score), score.last_round_winner) {
+            (Some(winner), _) => format!("{} wins the match!\n\n{}", name(winner), tally),
+            (None, Some(winner)) => format!("round {}: {}\n\n{}", self.round, name(winner), tally),
+            (None, None) => tally,
+        }
+    }
+
+    // where the score screen goes next: the next round's level, or back to
+    // the title once somebody has won
+    pub fn next_round(&mut self, score: &Score, level: &mut Level) -> AppState {
+        if self.match_winner(score).is_some() {
+            self.active = false;
+            level.current_level = 0;
+            AppState::MainMenu
+        } else {
+            level.current_level = self.levels[self.round % self.levels.len()] - 1;
+            AppState::LevelTitle
+        }
+    }
+}
+
+fn start_round(mut versus: ResMut<Versus>) {
+    versus.round_timer.reset();
+    versus.round_end_timer.reset();
+    versus.round_over = false;
+}
+
+fn end_round(
+    mut commands: Commands,
+    mut versus: ResMut<Versus>,
+    mut score: ResMut<Score>,
+    mut state: ResMut<State<AppState>>,
+    time: Res<GameTime>,
+    mut round_over_events: EventReader<RoundOverEvent>,
+    mut level_over_events: EventReader<LevelOverEvent>,
+    mut dude_died_events: EventReader<dude::DudeDiedEvent>,
+    dudes: Query<Entity, With<dude::Dude>>,
+    mut snakes: Query<&mut Enemy>,
+) {
+    let caught = round_over_events.iter().last().map(|event| event.winner);
+    let died = dude_died_events.iter().count() > 0;
+    let reached_flag = level_over_events.iter().count() > 0;
+    if !versus.active {
+        return;
+    }
+
+    if versus.round_over {
+        if versus.round_end_timer.tick(time.delta()).just_finished() {
+            state.set(AppState::ScoreDisplay).unwrap();
+        }
+        return;
+    }
+
+    let out_of_time = versus.round_timer.tick(time.delta()).just_finished();
+    let winner = if let Some(winner) = caught {
+        winner
+    } else if died {
+        Side::Snake
+    } else if reached_flag || out_of_time {
+        Side::Dude
+    } else {
+        return;
+    };
+
+    println!("Versus round {} goes to {:?}", versus.round + 1, winner);
+    score.record_round(winner);
+    versus.round += 1;
+    versus.round_over = true;
+
+    // hold everything still until the score shows
+    for entity in dudes.iter() {
+        commands.entity(entity).remove::<moveable::Moveable>();
+    }
+    for mut snake in snakes.iter_mut() {
+        snake.is_dead = true;
+    }
+}
+
+fn show_round_time(
+    versus: Res<Versus>,
+    mut text_query: Query<&mut Text, With<environment::FollowText>>,
+) {
+    if !versus.active {
+        return;
+    }
+
+    let seconds_left = versus.round_timer.duration().as_secs_f32() - versus.round_timer.elapsed_secs();
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "{}  Round {}  Time: {}",
+            text.sections[0].value,
+            versus.round + 1,
+            seconds_left.ceil()
+        );
+    }
+}
+
+// A death still plays the camera's death animation, which would reset the
+// level once it finished. Versus moves on to the score instead, so the
+// animation is dropped here before it gets that far.
+fn stop_death_animations(
+    versus: Res<Versus>,
+    mut mouth_movement: ResMut<camera::CameraMouthMovement>,
+    mut bolt_movement: ResMut<camera::CameraBoltMovement>,
+    mut spike_movement: ResMut<camera::CameraSpikeMovement>,
+) {
+    if versus.active {
+        *mouth_movement = camera::CameraMouthMovement::default();
+        *bolt_movement = camera::CameraBoltMovement::default();
+        *spike_movement = camera::CameraSpikeMovement::default();
+    }
+}
+
+// which controllers each side listens to, every controller when it's not versus
+pub fn is_dude_gamepad(versus: &Versus, gamepad: usize) -> bool {
+    !versus.active || gamepad == DUDE_GAMEPAD
+}
+
+pub fn is_snake_gamepad(versus: &Versus, gamepad: usize) -> bool {
+    !versus.active || gamepad == SNAKE_GAMEPAD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(dude_rounds: usize, snake_rounds: usize) -> Score {
+        let mut score = Score::new();
+        score.dude_rounds = dude_rounds;
+        score.snake_rounds = snake_rounds;
+        score
+    }
+
+    #[test]
+    fn match_goes_to_whoever_takes_most_of_the_rounds() {
+        let versus = Versus::default();
+        assert_eq!(versus.wins_needed(), 3);
+        assert_eq!(versus.match_winner(&score(0, 0)), None);
+        assert_eq!(versus.match_winner(&score(2, 2)), None);
+        assert_eq!(versus.match_winner(&score(3, 1)), Some(Side::Dude));
+        assert_eq!(versus.match_winner(&score(2, 3)), Some(Side::Snake));
+    }
+
+    #[test]
+    fn best_of_can_change() {
+        let versus = Versus {
+            best_of: 1,
+            ..Versus::default()
+        };
+        assert_eq!(versus.match_winner(&score(0, 1)), Some(Side::Snake));
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use not_snake::{
+    dude::{Dude, PlayerAction},
+    simulation::{self, Simulation},
+    Position,
+};
+
+const LEVELS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data/levels.custom");
+
+fn dude_position(simulation: &mut Simulation) -> Option<Position> {
+    let mut dudes = simulation.app.world.query_filtered::<&Position, With<Dude>>();
+    dudes.iter(&simulation.app.world).next().copied()
+}
+
+fn hold(simulation: &mut Simulation, action: Option<PlayerAction>) {
+    let mut dudes = simulation
+        .app
+        .world
+        .query_filtered::<&mut ActionState<PlayerAction>, With<Dude>>();
+    for mut action_state in dudes.iter_mut(&mut simulation.app.world) {
+        for direction in PlayerAction::DIRECTIONS {
+            if Some(direction) == action {
+                action_state.press(direction);
+            } else {
+                action_state.release(direction);
+            }
+        }
+    }
+}
+
+// a recorded run played back ends the same way, on the same tick
+#[test]
+fn replays_match_the_recorded_run() {
+    let levels = simulation::load_levels(LEVELS).unwrap();
+    let mut recorded = Simulation::recording(levels.clone(), 2, 11);
+    let start = dude_position(&mut recorded);
+
+    let moves = [Some(PlayerAction::Right), Some(PlayerAction::Up), None, Some(PlayerAction::Left)];
+    for action in moves {
+        hold(&mut recorded, action);
+        for _ in 0..60 {
+            recorded.step();
+        }
+    }
+    let recording = recorded.take_recording().unwrap();
+    assert_ne!(dude_position(&mut recorded), start);
+
+    let mut replayed = Simulation::from_recording(levels, recording);
+    while replayed.ticks() < recorded.ticks() {
+        replayed.step();
+    }
+
+    assert_eq!(replayed.ticks(), recorded.ticks());
+    assert_eq!(replayed.outcome(), recorded.outcome());
+    assert_eq!(dude_position(&mut replayed), dude_position(&mut recorded));
+}
@@ -0,0 +1,36 @@
+use not_snake::{
+    dude::DudeDeath,
+    game_time,
+    simulation::{Simulation, SimulationOutcome},
+};
+
+const LEVELS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data/levels.custom");
+
+// no window or renderer, just the level rules on manual time
+#[test]
+fn runs_headless() {
+    let mut simulation = Simulation::from_file(LEVELS, 1, 7).unwrap();
+    let first = simulation.ticks();
+
+    for _ in 0..30 {
+        simulation.step();
+    }
+    assert_eq!(simulation.ticks(), first + 30);
+
+    simulation.step_for(game_time::TICK.as_secs_f32() * 30.0);
+    assert_eq!(simulation.ticks(), first + 60);
+    assert_eq!(simulation.outcome(), SimulationOutcome::Running);
+    assert!(simulation.level().width() > 0);
+}
+
+// standing still on the first level, the snake gets to the dude within a minute
+#[test]
+fn runs_to_an_outcome() {
+    let mut simulation = Simulation::from_file(LEVELS, 1, 7).unwrap();
+    while simulation.outcome() == SimulationOutcome::Running && simulation.ticks() < 60 * 60 {
+        simulation.step();
+    }
+
+    assert_eq!(simulation.outcome(), SimulationOutcome::DudeDied(DudeDeath::Eaten));
+    assert!(simulation.ticks() < 60 * 60);
+}